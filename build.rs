@@ -0,0 +1,18 @@
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=PROMOR_GIT_SHA");
+
+    // builds from a tarball have no git history, /version then just omits the sha
+    if std::env::var("PROMOR_GIT_SHA").is_ok() {
+        return;
+    }
+
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
+    if let Some(output) = output.ok().filter(|output| output.status.success()) {
+        let sha = String::from_utf8_lossy(&output.stdout);
+        println!("cargo:rustc-env=PROMOR_GIT_SHA={}", sha.trim());
+    }
+}
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS notification_channels (
+    id SERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    store_id INT REFERENCES stores(id) ON DELETE CASCADE,
+    product_id INT REFERENCES products(id) ON DELETE CASCADE,
+    brand VARCHAR(100)
+) INHERITS (base_table);
+
+CREATE INDEX notification_channels_channel_idx ON notification_channels (channel_id);
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS scrape_failures;
+
+ALTER TABLE product_prices DROP COLUMN IF EXISTS available;
+
+ALTER TABLE notification_channels
+    DROP COLUMN IF EXISTS digest,
+    DROP COLUMN IF EXISTS last_digest_at;
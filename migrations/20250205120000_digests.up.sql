@@ -0,0 +1,15 @@
+ALTER TABLE notification_channels
+    ADD COLUMN digest TEXT,
+    ADD COLUMN last_digest_at TIMESTAMPTZ;
+
+ALTER TABLE product_prices ADD COLUMN available BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE IF NOT EXISTS scrape_failures (
+    id SERIAL PRIMARY KEY,
+    store_id INT REFERENCES stores(id) ON DELETE CASCADE NOT NULL,
+    url TEXT NOT NULL,
+    handler TEXT NOT NULL,
+    error TEXT NOT NULL
+) INHERITS (base_table);
+
+CREATE INDEX scrape_failures_created_at_idx ON scrape_failures (created_at);
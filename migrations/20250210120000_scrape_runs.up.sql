@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS scrape_runs (
+    id SERIAL PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'running',
+    finished_at TIMESTAMPTZ
+) INHERITS (base_table);
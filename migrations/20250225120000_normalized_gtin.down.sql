@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS products_normalized_gtin_idx;
+ALTER TABLE products DROP COLUMN IF EXISTS normalized_gtin;
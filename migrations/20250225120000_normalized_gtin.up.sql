@@ -0,0 +1,40 @@
+ALTER TABLE products ADD COLUMN IF NOT EXISTS normalized_gtin TEXT;
+
+-- only used to backfill the column below, the application validates identifiers from now on
+CREATE FUNCTION pg_temp.normalize_gtin(value TEXT) RETURNS TEXT AS $$
+DECLARE
+    digits TEXT := regexp_replace(value, '[\s-]', '', 'g');
+    total INT := 0;
+BEGIN
+    IF digits !~ '^[0-9]+$' OR length(digits) NOT IN (8, 12, 13, 14) THEN
+        RETURN NULL;
+    END IF;
+
+    FOR position IN 0..length(digits) - 1 LOOP
+        total := total + substr(digits, length(digits) - position, 1)::INT * CASE WHEN position % 2 = 1 THEN 3 ELSE 1 END;
+    END LOOP;
+
+    IF total % 10 <> 0 THEN
+        RETURN NULL;
+    END IF;
+
+    RETURN lpad(digits, 14, '0');
+END;
+$$ LANGUAGE plpgsql IMMUTABLE STRICT;
+
+-- products sharing an identifier are left for merging, only the oldest one keeps it
+WITH normalized AS (
+    SELECT id, pg_temp.normalize_gtin(COALESCE(gtin, ean)) AS value
+    FROM products
+    WHERE active = true
+), ranked AS (
+    SELECT id, value, ROW_NUMBER() OVER (PARTITION BY value ORDER BY id) AS rank
+    FROM normalized
+    WHERE value IS NOT NULL
+)
+UPDATE products p
+SET normalized_gtin = ranked.value
+FROM ranked
+WHERE p.id = ranked.id AND ranked.rank = 1;
+
+CREATE UNIQUE INDEX products_normalized_gtin_idx ON products (normalized_gtin) WHERE active = true;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS products_brand_idx;
+DROP TABLE IF EXISTS product_matches;
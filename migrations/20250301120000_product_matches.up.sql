@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS product_matches (
+    id SERIAL PRIMARY KEY,
+    -- the product that would be merged away
+    product_id INT NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    -- the product it would be merged into
+    matched_product_id INT NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    method TEXT NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewed_at TIMESTAMPTZ,
+    UNIQUE (product_id, matched_product_id)
+) INHERITS (base_table);
+
+CREATE INDEX product_matches_status_idx ON product_matches (status);
+CREATE INDEX products_brand_idx ON products (LOWER(brand));
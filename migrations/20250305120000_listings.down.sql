@@ -0,0 +1,2 @@
+ALTER TABLE product_prices DROP COLUMN IF EXISTS listing_id;
+DROP TABLE IF EXISTS listings;
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS listings (
+    id SERIAL PRIMARY KEY,
+    product_id INT NOT NULL REFERENCES products(id),
+    store_id INT NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- the store's own code for the product
+    sku TEXT,
+    -- the product name as the store shows it
+    title TEXT NOT NULL,
+    UNIQUE (store_id, url)
+) INHERITS (base_table);
+
+CREATE INDEX listings_product_idx ON listings (product_id);
+
+ALTER TABLE product_prices ADD COLUMN IF NOT EXISTS listing_id INT REFERENCES listings(id);
+
+-- products only knew the url of the store that first scraped them, so that's the one listing we
+-- can recover, prices from the other stores are left without a listing
+INSERT INTO listings (product_id, store_id, url, title)
+SELECT DISTINCT ON (s.id, p.url) p.id, s.id, p.url, p.name
+FROM products p
+JOIN product_prices pp ON pp.product_id = p.id
+JOIN stores s ON s.id = pp.store_id
+WHERE p.url IS NOT NULL AND p.url LIKE s.url || '%'
+ORDER BY s.id, p.url, p.id
+ON CONFLICT (store_id, url) DO NOTHING;
+
+UPDATE product_prices pp
+SET listing_id = l.id
+FROM listings l
+WHERE l.product_id = pp.product_id AND l.store_id = pp.store_id;
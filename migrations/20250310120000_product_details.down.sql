@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS products_pending_image_idx;
+
+ALTER TABLE products DROP COLUMN IF EXISTS specs;
+ALTER TABLE products DROP COLUMN IF EXISTS image_error;
+ALTER TABLE products DROP COLUMN IF EXISTS image_hash;
@@ -0,0 +1,9 @@
+-- content hash of the downloaded copy of products.image, files live under the images directory
+ALTER TABLE products ADD COLUMN IF NOT EXISTS image_hash TEXT;
+-- why the image couldn't be downloaded, failed downloads are not retried
+ALTER TABLE products ADD COLUMN IF NOT EXISTS image_error TEXT;
+-- specifications as the store lists them, eg: {"Memória": "12GB GDDR6X"}
+ALTER TABLE products ADD COLUMN IF NOT EXISTS specs JSONB;
+
+CREATE INDEX IF NOT EXISTS products_pending_image_idx ON products (id)
+WHERE active = true AND image IS NOT NULL AND image_hash IS NULL AND image_error IS NULL;
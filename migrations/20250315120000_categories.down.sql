@@ -0,0 +1,7 @@
+DROP FUNCTION IF EXISTS category_subtree(INT);
+
+DROP INDEX IF EXISTS products_category_idx;
+ALTER TABLE products DROP COLUMN IF EXISTS category_id;
+ALTER TABLE pages DROP COLUMN IF EXISTS category_id;
+
+DROP TABLE IF EXISTS categories;
@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS categories (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- url friendly name, eg: "placas-de-video"
+    slug TEXT NOT NULL,
+    parent_id INT REFERENCES categories(id)
+) INHERITS (base_table);
+
+CREATE UNIQUE INDEX categories_slug_idx ON categories (slug) WHERE active = true;
+CREATE INDEX categories_parent_idx ON categories (parent_id);
+
+-- products found through a search page are put in its category
+ALTER TABLE pages ADD COLUMN IF NOT EXISTS category_id INT REFERENCES categories(id);
+ALTER TABLE products ADD COLUMN IF NOT EXISTS category_id INT REFERENCES categories(id);
+
+CREATE INDEX products_category_idx ON products (category_id);
+
+-- the ids of an active category and of every active category below it, at any depth. filtering
+-- by a category includes its subcategories, eg: "gpus" includes "nvidia" and "amd"
+CREATE OR REPLACE FUNCTION category_subtree(root INT) RETURNS SETOF INT AS $$
+    WITH RECURSIVE tree AS (
+        SELECT id FROM categories WHERE id = root AND active = true
+        UNION
+        SELECT c.id FROM categories c JOIN tree t ON c.parent_id = t.id WHERE c.active = true
+    )
+    SELECT id FROM tree
+$$ LANGUAGE sql STABLE;
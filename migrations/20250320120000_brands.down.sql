@@ -0,0 +1,8 @@
+DROP FUNCTION IF EXISTS resolve_brand(TEXT);
+
+DROP INDEX IF EXISTS products_brand_id_idx;
+ALTER TABLE products DROP COLUMN IF EXISTS brand_id;
+
+DROP TABLE IF EXISTS brands;
+
+DROP FUNCTION IF EXISTS brand_key(TEXT);
@@ -0,0 +1,62 @@
+-- what brand names are compared by, so "ASUS", "Asus" and "A.S.U.S" are the same brand
+CREATE OR REPLACE FUNCTION brand_key(name TEXT) RETURNS TEXT AS $$
+    SELECT LOWER(regexp_replace(name, '[^[:alnum:]]+', '', 'g'))
+$$ LANGUAGE sql IMMUTABLE STRICT;
+
+CREATE TABLE IF NOT EXISTS brands (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- other names stores use for the brand, eg: "ASUS TeK" for "ASUS"
+    aliases TEXT[] NOT NULL DEFAULT '{}'
+) INHERITS (base_table);
+
+CREATE UNIQUE INDEX brands_key_idx ON brands (brand_key(name)) WHERE active = true;
+
+ALTER TABLE products ADD COLUMN IF NOT EXISTS brand_id INT REFERENCES brands(id);
+
+CREATE INDEX products_brand_id_idx ON products (brand_id);
+
+-- finds the brand a scraped manufacturer name refers to, creating it when there is none. names
+-- without a single letter or digit refer to no brand
+CREATE OR REPLACE FUNCTION resolve_brand(raw TEXT) RETURNS INT AS $$
+DECLARE
+    found INT;
+BEGIN
+    IF brand_key(raw) = '' THEN
+        RETURN NULL;
+    END IF;
+
+    SELECT id INTO found FROM brands
+    WHERE active = true
+    AND (brand_key(name) = brand_key(raw) OR brand_key(raw) IN (SELECT brand_key(alias) FROM unnest(aliases) AS alias))
+    ORDER BY id
+    LIMIT 1;
+
+    IF found IS NULL THEN
+        INSERT INTO brands (name) VALUES (btrim(raw))
+        ON CONFLICT (brand_key(name)) WHERE active = true DO NOTHING
+        RETURNING id INTO found;
+    END IF;
+
+    -- someone else created it in the meantime
+    IF found IS NULL THEN
+        SELECT id INTO found FROM brands WHERE active = true AND brand_key(name) = brand_key(raw);
+    END IF;
+
+    RETURN found;
+END
+$$ LANGUAGE plpgsql;
+
+-- the most common spelling of every brand becomes its name
+INSERT INTO brands (name)
+SELECT DISTINCT ON (brand_key(brand)) btrim(brand)
+FROM products
+WHERE brand_key(brand) <> ''
+GROUP BY brand
+ORDER BY brand_key(brand), COUNT(*) DESC, brand
+ON CONFLICT DO NOTHING;
+
+UPDATE products p
+SET brand_id = b.id, brand = b.name
+FROM brands b
+WHERE b.active = true AND brand_key(b.name) = brand_key(p.brand);
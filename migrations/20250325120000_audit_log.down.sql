@@ -0,0 +1,15 @@
+DO $$
+DECLARE
+    child REGCLASS;
+BEGIN
+    FOR child IN
+        SELECT tgrelid::REGCLASS FROM pg_trigger WHERE tgname = 'set_updated_at'
+    LOOP
+        EXECUTE format('DROP TRIGGER IF EXISTS set_updated_at ON %s', child);
+    END LOOP;
+END
+$$;
+
+DROP TABLE IF EXISTS audit_log;
+
+DROP FUNCTION IF EXISTS set_updated_at();
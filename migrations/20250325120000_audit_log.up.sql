@@ -0,0 +1,50 @@
+-- keeps updated_at current without every query having to remember it, updates that change
+-- nothing leave it alone
+CREATE OR REPLACE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW IS DISTINCT FROM OLD THEN
+        NEW.updated_at = NOW();
+    END IF;
+
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id SERIAL PRIMARY KEY,
+    -- the key the change was made with
+    api_key_id INT NOT NULL REFERENCES api_keys(id),
+    action TEXT NOT NULL,
+    -- table of the changed record
+    entity TEXT NOT NULL,
+    -- missing for changes that touch many records at once, eg: a match scan
+    entity_id INT,
+    before JSONB,
+    after JSONB
+) INHERITS (base_table);
+
+CREATE INDEX audit_log_entity_idx ON audit_log (entity, entity_id);
+CREATE INDEX audit_log_api_key_idx ON audit_log (api_key_id);
+CREATE INDEX audit_log_created_at_idx ON audit_log (created_at);
+
+-- row triggers on a parent don't fire for its children, so every table inheriting base_table
+-- gets its own. tables created later have to add theirs
+DO $$
+DECLARE
+    child REGCLASS;
+BEGIN
+    FOR child IN
+        WITH RECURSIVE descendants AS (
+            SELECT inhrelid FROM pg_inherits WHERE inhparent = 'base_table'::REGCLASS
+            UNION
+            SELECT i.inhrelid FROM pg_inherits i JOIN descendants d ON i.inhparent = d.inhrelid
+        )
+        SELECT inhrelid::REGCLASS FROM descendants
+    LOOP
+        EXECUTE format(
+            'CREATE TRIGGER set_updated_at BEFORE UPDATE ON %s FOR EACH ROW EXECUTE FUNCTION set_updated_at()',
+            child
+        );
+    END LOOP;
+END
+$$;
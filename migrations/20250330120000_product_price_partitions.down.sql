@@ -0,0 +1,28 @@
+ALTER TABLE product_prices RENAME TO product_prices_partitioned;
+ALTER TABLE product_prices_partitioned
+    DROP CONSTRAINT IF EXISTS product_prices_pkey,
+    DROP CONSTRAINT IF EXISTS product_prices_product_id_fkey,
+    DROP CONSTRAINT IF EXISTS product_prices_store_id_fkey,
+    DROP CONSTRAINT IF EXISTS product_prices_listing_id_fkey;
+
+CREATE TABLE product_prices (
+    id INT PRIMARY KEY DEFAULT nextval('product_prices_id_seq'),
+    product_id INT NOT NULL REFERENCES products(id),
+    store_id INT NOT NULL REFERENCES stores(id),
+    price DECIMAL(19, 4) NOT NULL,
+    available BOOLEAN NOT NULL DEFAULT TRUE,
+    listing_id INT REFERENCES listings(id)
+) INHERITS (base_table);
+
+ALTER SEQUENCE product_prices_id_seq OWNED BY product_prices.id;
+
+CREATE TRIGGER set_updated_at BEFORE UPDATE ON product_prices FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+-- what was downsampled stays that way
+INSERT INTO product_prices (created_at, updated_at, deleted_at, active, id, product_id, store_id, price, available, listing_id)
+SELECT created_at, updated_at, deleted_at, active, id, product_id, store_id, price, available, listing_id
+FROM product_prices_partitioned;
+
+DROP TABLE product_prices_partitioned;
+
+DROP FUNCTION IF EXISTS create_product_prices_partition(DATE);
@@ -0,0 +1,87 @@
+-- prices are split into monthly partitions by when they were observed. a partitioned table can't
+-- inherit, so base_table's columns are declared here instead
+ALTER TABLE product_prices RENAME TO product_prices_old;
+ALTER TABLE product_prices_old
+    DROP CONSTRAINT IF EXISTS product_prices_pkey,
+    DROP CONSTRAINT IF EXISTS product_prices_product_id_fkey,
+    DROP CONSTRAINT IF EXISTS product_prices_store_id_fkey,
+    DROP CONSTRAINT IF EXISTS product_prices_listing_id_fkey;
+
+CREATE TABLE product_prices (
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ DEFAULT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    id INT NOT NULL DEFAULT nextval('product_prices_id_seq'),
+    product_id INT NOT NULL REFERENCES products(id),
+    store_id INT NOT NULL REFERENCES stores(id),
+    price DECIMAL(19, 4) NOT NULL,
+    available BOOLEAN NOT NULL DEFAULT TRUE,
+    listing_id INT REFERENCES listings(id),
+    -- kept when older observations of its day were downsampled, as the day's lowest, highest or
+    -- last price
+    downsampled BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (id, created_at)
+) PARTITION BY RANGE (created_at);
+
+ALTER SEQUENCE product_prices_id_seq OWNED BY product_prices.id;
+
+-- catches prices observed in a month whose partition is missing
+CREATE TABLE product_prices_default PARTITION OF product_prices DEFAULT;
+
+CREATE INDEX product_prices_history_idx ON product_prices (product_id, store_id, created_at);
+CREATE INDEX product_prices_created_at_idx ON product_prices (created_at);
+
+CREATE TRIGGER set_updated_at BEFORE UPDATE ON product_prices FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+-- creates the partition of the month `day` is in, moving over what the default partition holds
+-- for it. months are in utc
+CREATE OR REPLACE FUNCTION create_product_prices_partition(day DATE) RETURNS VOID AS $$
+DECLARE
+    start_at TIMESTAMPTZ := date_trunc('month', day)::TIMESTAMP AT TIME ZONE 'UTC';
+    end_at TIMESTAMPTZ := (date_trunc('month', day) + INTERVAL '1 month')::TIMESTAMP AT TIME ZONE 'UTC';
+    partition TEXT := 'product_prices_' || to_char(day, 'YYYY_MM');
+BEGIN
+    IF to_regclass(partition) IS NOT NULL THEN
+        RETURN;
+    END IF;
+
+    EXECUTE format('CREATE TABLE %I (LIKE product_prices INCLUDING DEFAULTS)', partition);
+    EXECUTE format(
+        'WITH moved AS (
+            DELETE FROM product_prices_default WHERE created_at >= $1 AND created_at < $2 RETURNING *
+        )
+        INSERT INTO %I SELECT * FROM moved',
+        partition
+    ) USING start_at, end_at;
+    EXECUTE format(
+        'ALTER TABLE product_prices ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+        partition,
+        start_at,
+        end_at
+    );
+END
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    month DATE;
+BEGIN
+    FOR month IN
+        SELECT generate_series(
+            date_trunc('month', COALESCE(MIN(created_at), NOW()) AT TIME ZONE 'UTC'),
+            date_trunc('month', NOW() AT TIME ZONE 'UTC') + INTERVAL '3 months',
+            INTERVAL '1 month'
+        )::DATE
+        FROM product_prices_old
+    LOOP
+        PERFORM create_product_prices_partition(month);
+    END LOOP;
+END
+$$;
+
+INSERT INTO product_prices (created_at, updated_at, deleted_at, active, id, product_id, store_id, price, available, listing_id)
+SELECT created_at, updated_at, deleted_at, active, id, product_id, store_id, price, available, listing_id
+FROM product_prices_old;
+
+DROP TABLE product_prices_old;
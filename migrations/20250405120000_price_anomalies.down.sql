@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS product_prices_suspected_idx;
+
+ALTER TABLE product_prices
+    DROP COLUMN IF EXISTS anomaly_score,
+    DROP COLUMN IF EXISTS anomaly;
@@ -0,0 +1,8 @@
+-- prices far off a product's recent ones, `suspected` until seen again or confirmed by
+-- scraping the page once more
+ALTER TABLE product_prices
+    ADD COLUMN anomaly TEXT CHECK (anomaly IN ('suspected', 'confirmed')),
+    ADD COLUMN anomaly_score DOUBLE PRECISION;
+
+CREATE INDEX product_prices_suspected_idx ON product_prices (product_id, store_id)
+    WHERE anomaly = 'suspected';
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS notification_channels_filters_idx;
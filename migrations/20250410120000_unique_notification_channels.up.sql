@@ -0,0 +1,23 @@
+-- a channel subscribed twice with the same filters got every message twice, the oldest
+-- subscription is kept
+UPDATE notification_channels nc
+SET active = false, deleted_at = NOW()
+WHERE active = true
+AND EXISTS (
+    SELECT 1 FROM notification_channels other
+    WHERE other.active = true
+    AND other.id < nc.id
+    AND other.channel_id = nc.channel_id
+    AND other.store_id IS NOT DISTINCT FROM nc.store_id
+    AND other.product_id IS NOT DISTINCT FROM nc.product_id
+    AND LOWER(other.brand) IS NOT DISTINCT FROM LOWER(nc.brand)
+    AND other.digest IS NOT DISTINCT FROM nc.digest
+);
+
+CREATE UNIQUE INDEX notification_channels_filters_idx ON notification_channels (
+    channel_id,
+    COALESCE(store_id, 0),
+    COALESCE(product_id, 0),
+    LOWER(COALESCE(brand, '')),
+    COALESCE(digest, '')
+) WHERE active = true;
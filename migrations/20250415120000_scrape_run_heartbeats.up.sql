@@ -0,0 +1,2 @@
+-- runs keep this fresh while they are going, a stale one was left behind by a process that died
+ALTER TABLE scrape_runs ADD COLUMN heartbeat_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1 @@
+-- failed deliveries of deleted webhooks can't be told apart from the others
@@ -0,0 +1,5 @@
+-- the worker only sends to active webhooks, these would stay pending forever
+UPDATE webhook_deliveries d
+SET status = 'failed', last_error = 'the webhook was deleted', updated_at = NOW()
+FROM webhooks w
+WHERE w.id = d.webhook_id AND w.active = false AND d.status = 'pending';
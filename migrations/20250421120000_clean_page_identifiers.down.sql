@@ -0,0 +1 @@
+-- the identifiers that were cleaned or dropped can't be restored
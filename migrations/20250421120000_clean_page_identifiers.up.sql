@@ -0,0 +1,37 @@
+-- only used below, the application validates identifiers of new pages. named apart from the
+-- function of an earlier migration, temporary functions outlive a migration on its connection
+CREATE FUNCTION pg_temp.normalize_page_identifier(value TEXT) RETURNS TEXT AS $$
+DECLARE
+    digits TEXT := regexp_replace(value, '[\s-]', '', 'g');
+    total INT := 0;
+BEGIN
+    IF digits !~ '^[0-9]+$' OR length(digits) NOT IN (8, 12, 13, 14) THEN
+        RETURN NULL;
+    END IF;
+
+    FOR position IN 0..length(digits) - 1 LOOP
+        total := total + substr(digits, length(digits) - position, 1)::INT * CASE WHEN position % 2 = 1 THEN 3 ELSE 1 END;
+    END LOOP;
+
+    IF total % 10 <> 0 THEN
+        RETURN NULL;
+    END IF;
+
+    RETURN lpad(digits, 14, '0');
+END;
+$$ LANGUAGE plpgsql IMMUTABLE STRICT;
+
+-- pages keep identifiers as they were written, minus spaces and dashes, and lose invalid ones
+UPDATE pages
+SET ean = CASE WHEN pg_temp.normalize_page_identifier(ean) IS NULL THEN NULL ELSE regexp_replace(ean, '[\s-]', '', 'g') END,
+    gtin = CASE WHEN pg_temp.normalize_page_identifier(gtin) IS NULL THEN NULL ELSE regexp_replace(gtin, '[\s-]', '', 'g') END,
+    updated_at = NOW()
+WHERE ean ~ '[\s-]' OR pg_temp.normalize_page_identifier(ean) IS NULL AND ean IS NOT NULL
+    OR gtin ~ '[\s-]' OR pg_temp.normalize_page_identifier(gtin) IS NULL AND gtin IS NOT NULL;
+
+-- an ean naming another product than the gtin can't be trusted, the gtin wins
+UPDATE pages
+SET ean = NULL, updated_at = NOW()
+WHERE pg_temp.normalize_page_identifier(ean) <> pg_temp.normalize_page_identifier(gtin);
+
+DROP FUNCTION pg_temp.normalize_page_identifier(TEXT);
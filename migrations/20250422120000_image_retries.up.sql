@@ -0,0 +1,15 @@
+-- image downloads that failed for a reason that may go away, eg: the host being down. They are
+-- retried with a growing delay and only recorded on products.image_error once out of attempts
+CREATE TABLE IF NOT EXISTS image_retries (
+    product_id INT PRIMARY KEY REFERENCES products(id),
+    attempts INT NOT NULL,
+    retry_at TIMESTAMPTZ NOT NULL
+);
+
+-- every failure used to be final, only those that still are stay recorded
+UPDATE products
+SET image_error = NULL, updated_at = NOW()
+WHERE image_error IS NOT NULL
+AND image_error <> 'not an image'
+AND image_error NOT LIKE 'image is larger than %'
+AND image_error NOT LIKE 'HTTP status client error%';
@@ -0,0 +1 @@
+-- the products of deleted brands can't be told apart from ones that never had one
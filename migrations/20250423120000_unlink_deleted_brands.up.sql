@@ -0,0 +1,4 @@
+-- deleting a brand used to leave its products referring to it
+UPDATE products
+SET brand_id = NULL, updated_at = NOW()
+WHERE brand_id IN (SELECT id FROM brands WHERE active = false);
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Days, Utc};
+use sqlx::PgPool;
+
+use crate::config::AnomalyConfig;
+use crate::models::product::ProductId;
+use crate::models::product_price::ProductPrice;
+use crate::models::store::StoreId;
+
+/// How many of the recent prices are compared with, the most recent ones.
+const SAMPLE_LIMIT: i64 = 200;
+/// Turns the median absolute deviation into an estimate of the standard deviation.
+const MAD_SCALE: f64 = 1.4826;
+/// Smallest spread assumed, as a fraction of the median, otherwise any change to a price that
+/// never moved would be infinitely far off.
+const MIN_SPREAD: f64 = 0.01;
+
+/// Compares `price` with the recent prices of a product on a store, returning how far off it
+/// is when it's far enough to be an anomaly.
+pub async fn check(
+    db: &PgPool,
+    config: &AnomalyConfig,
+    product_id: ProductId,
+    store_id: StoreId,
+    price: f64,
+) -> anyhow::Result<Option<f64>> {
+    let recent = ProductPrice::get_recent_prices(db, product_id, store_id, since(config)?, SAMPLE_LIMIT).await?;
+
+    Ok(outlier_score(config, recent, price))
+}
+
+/// Start of the window prices are compared within.
+pub fn since(config: &AnomalyConfig) -> anyhow::Result<DateTime<Utc>> {
+    Utc::now()
+        .checked_sub_days(Days::new(config.window_days.into()))
+        .ok_or_else(|| anyhow::anyhow!("scraper.anomaly.window_days is too large"))
+}
+
+/// Scores `price` by how many robust standard deviations it is from the median of `recent`,
+/// using the median absolute deviation so the outliers already in `recent` barely matter.
+///
+/// Returns the score only when it's over the threshold and the price is different enough from
+/// the median.
+fn outlier_score(config: &AnomalyConfig, mut recent: Vec<f64>, price: f64) -> Option<f64> {
+    if recent.len() < config.min_samples {
+        return None;
+    }
+
+    let center = median(&mut recent);
+    if center <= 0.0 {
+        return None;
+    }
+
+    let mut deviations = recent.iter().map(|value| (value - center).abs()).collect::<Vec<_>>();
+    let spread = (MAD_SCALE * median(&mut deviations)).max(center * MIN_SPREAD);
+
+    let distance = (price - center).abs();
+    let score = distance / spread;
+
+    (score >= config.threshold && distance / center >= config.min_change).then_some(score)
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AnomalyConfig {
+        AnomalyConfig {
+            enabled: true,
+            window_days: 30,
+            min_samples: 4,
+            threshold: 3.0,
+            min_change: 0.1,
+            confirm: false,
+            confirm_delay_secs: 1,
+        }
+    }
+
+    fn assert_score(score: Option<f64>, expected: f64) {
+        let score = score.expect("price should be an outlier");
+        assert!((score - expected).abs() < 1e-9, "score {score} is not {expected}");
+    }
+
+    #[test]
+    fn small_history_is_never_an_outlier() {
+        assert_eq!(outlier_score(&config(), vec![100.0; 3], 1000.0), None);
+        assert_eq!(outlier_score(&config(), vec![], 1000.0), None);
+        assert!(outlier_score(&config(), vec![100.0; 4], 1000.0).is_some());
+    }
+
+    #[test]
+    fn price_that_never_moved_has_a_minimum_spread() {
+        // no deviation at all, the spread is 1% of the median
+        assert_score(outlier_score(&config(), vec![100.0; 10], 150.0), 50.0);
+        assert_score(outlier_score(&config(), vec![100.0; 10], 50.0), 50.0);
+
+        // far enough in spreads but too small a change
+        assert_eq!(outlier_score(&config(), vec![100.0; 10], 105.0), None);
+    }
+
+    #[test]
+    fn even_history_uses_the_mean_of_the_middle_values() {
+        // the median is 25, deviations of 15, 5, 5 and 15 have a median of 10
+        assert_score(
+            outlier_score(&config(), vec![40.0, 10.0, 30.0, 20.0], 100.0),
+            75.0 / (MAD_SCALE * 10.0),
+        );
+        assert_eq!(outlier_score(&config(), vec![40.0, 10.0, 30.0, 20.0], 60.0), None);
+
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+    }
+
+    #[test]
+    fn outliers_in_history_barely_move_the_median() {
+        let recent = vec![100.0, 101.0, 99.0, 100.0, 5000.0];
+        assert!(outlier_score(&config(), recent.clone(), 150.0).is_some());
+        assert_eq!(outlier_score(&config(), recent, 101.0), None);
+    }
+}
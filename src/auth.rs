@@ -0,0 +1,72 @@
+use axum::extract::Request;
+use axum::http::{header, Method};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use sqlx::PgPool;
+
+use crate::config::AuthConfig;
+use crate::error::AppError;
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+
+/// Authenticates the `Authorization: Bearer <key>` header and requires the read scope for
+/// requests that only read data (unless reads are public) and the write scope for anything else.
+///
+/// The authenticated [`ApiKey`] is made available to the routes as an extension.
+pub async fn authenticate(
+    Extension(db): Extension<PgPool>,
+    Extension(config): Extension<AuthConfig>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let api_key = match bearer_token(&request) {
+        Some(key) => match ApiKey::authenticate(&db, key).await? {
+            Some(api_key) => Some(api_key),
+            None => return Err(AppError::Unauthorized("invalid api key".into())),
+        },
+        None => None,
+    };
+
+    let required = match *request.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS if config.public_reads => None,
+        Method::GET | Method::HEAD | Method::OPTIONS => Some(ApiKeyScope::Read),
+        _ => Some(ApiKeyScope::Write),
+    };
+
+    if let Some(required) = required {
+        check_scope(api_key.as_ref(), required)?;
+    }
+
+    if let Some(api_key) = api_key {
+        request.extensions_mut().insert(api_key);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Guards routes that manage api keys, must run after [`authenticate`].
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, AppError> {
+    check_scope(request.extensions().get::<ApiKey>(), ApiKeyScope::Admin)?;
+    Ok(next.run(request).await)
+}
+
+fn check_scope(api_key: Option<&ApiKey>, required: ApiKeyScope) -> Result<(), AppError> {
+    match api_key {
+        None => Err(AppError::Unauthorized("missing api key".into())),
+        Some(api_key) if api_key.scope < required => Err(AppError::Forbidden(format!(
+            "api key needs the {} scope",
+            required.inner()
+        ))),
+        Some(_) => Ok(()),
+    }
+}
+
+fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
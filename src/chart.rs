@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::sync::Once;
+
+use chrono::{DateTime, Duration, Utc};
+use image::{ImageFormat, RgbImage};
+use plotters::prelude::*;
+use sqlx::PgPool;
+
+use crate::models::product::Product;
+use crate::models::product_price::ProductPrice;
+use crate::models::store::Store;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+const FONT_FAMILY: &str = "sans-serif";
+
+// plotters renders text with ab_glyph, which needs the font bytes at hand instead of looking up
+// system fonts, so we ship one
+static FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+static REGISTER_FONT: Once = Once::new();
+
+/// Prices observed on a single store, ordered by date.
+#[derive(Debug)]
+pub struct PriceSeries {
+    pub label: String,
+    pub points: Vec<(DateTime<Utc>, f64)>,
+}
+
+/// Renders the price history of every store selling `product` as a PNG line chart.
+///
+/// Returns `None` when the product has no prices yet.
+pub async fn product_price_chart(db: &PgPool, product: &Product) -> anyhow::Result<Option<Vec<u8>>> {
+    let series = product_price_series(db, product).await?;
+
+    if series.is_empty() {
+        return Ok(None);
+    }
+
+    let title = product.name.clone();
+    let png = tokio::task::spawn_blocking(move || render_price_history(&title, &series)).await??;
+
+    Ok(Some(png))
+}
+
+/// Groups the price history of `product` by store.
+pub async fn product_price_series(db: &PgPool, product: &Product) -> anyhow::Result<Vec<PriceSeries>> {
+    let prices = ProductPrice::get_by_product(db, product.id).await?;
+    let mut by_store = BTreeMap::<_, Vec<_>>::new();
+
+    for price in prices {
+        by_store
+            .entry(price.store_id)
+            .or_default()
+            .push((price.created_at, price.price));
+    }
+
+    let mut series = vec![];
+    for (store_id, points) in by_store {
+        let label = match Store::get_by_id(db, store_id).await? {
+            Some(store) => store.name,
+            None => format!("store {}", store_id.inner()),
+        };
+
+        series.push(PriceSeries { label, points });
+    }
+
+    Ok(series)
+}
+
+pub fn render_price_history(title: &str, series: &[PriceSeries]) -> anyhow::Result<Vec<u8>> {
+    REGISTER_FONT.call_once(|| {
+        plotters::style::register_font(FONT_FAMILY, FontStyle::Normal, FONT)
+            .map_err(|_| ())
+            .expect("bundled font should be valid");
+    });
+
+    let points = series.iter().flat_map(|series| series.points.iter());
+    let (Some(first), Some(last)) = (
+        points.clone().map(|(date, _)| *date).min(),
+        points.clone().map(|(date, _)| *date).max(),
+    ) else {
+        anyhow::bail!("cannot render a chart without prices");
+    };
+
+    let lowest = points.clone().map(|(_, price)| *price).fold(f64::INFINITY, f64::min);
+    let highest = points.map(|(_, price)| *price).fold(f64::NEG_INFINITY, f64::max);
+
+    // a single observation would collapse the axes into a point, so we pad both of them
+    let last = if first == last { last + Duration::hours(1) } else { last };
+    let padding = ((highest - lowest) * 0.1).max(1.0);
+
+    let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT_FAMILY, 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(80)
+            .build_cartesian_2d(first..last, (lowest - padding)..(highest + padding))?;
+
+        chart
+            .configure_mesh()
+            .x_labels(6)
+            .x_label_formatter(&|date| date.format("%d/%m %Hh").to_string())
+            .y_label_formatter(&|price| format!("R$ {price:.2}"))
+            .label_style((FONT_FAMILY, 12))
+            .draw()?;
+
+        for (idx, series) in series.iter().enumerate() {
+            let color = Palette99::pick(idx).to_rgba();
+
+            chart
+                .draw_series(LineSeries::new(series.points.iter().copied(), color.stroke_width(2)))?
+                .label(&series.label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+        }
+
+        chart
+            .configure_series_labels()
+            .label_font((FONT_FAMILY, 12))
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        root.present()?;
+    }
+
+    let Some(image) = RgbImage::from_raw(WIDTH, HEIGHT, buffer) else {
+        anyhow::bail!("chart buffer does not match its dimensions");
+    };
+
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png)?;
+
+    Ok(png.into_inner())
+}
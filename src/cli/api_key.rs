@@ -0,0 +1,30 @@
+use sqlx::PgPool;
+
+use crate::models::api_key::{ApiKey, ApiKeyId, CreateApiKeyPayload};
+
+/// Issues a key and prints it to stdout, this is how the first admin key gets created.
+pub async fn issue(db: &PgPool, name: String, scope: String) -> anyhow::Result<()> {
+    let payload = CreateApiKeyPayload { name, scope }.parse()?;
+    let issued = ApiKey::create(db, payload).await?;
+
+    tracing::info!(
+        "issued {} key {} ({})",
+        issued.api_key.scope.inner(),
+        issued.api_key.id.inner(),
+        issued.api_key.name
+    );
+    println!("{}", issued.key);
+
+    Ok(())
+}
+
+pub async fn revoke(db: &PgPool, id: i32) -> anyhow::Result<()> {
+    let id = ApiKeyId::new(db, id).await?;
+
+    match ApiKey::revoke(db, id).await? {
+        Some(key) => tracing::info!("revoked key {} ({})", key.id.inner(), key.name),
+        None => tracing::warn!("key {} was already revoked", id.inner()),
+    }
+
+    Ok(())
+}
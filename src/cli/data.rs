@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use url::Url;
+
+use crate::error::AppError;
+use crate::handlers::import::{import_rows, read_rows, ImportFormat, Importable};
+use crate::models::category::{Category, CategoryId, CreateCategoryPayload};
+use crate::models::page::{CreatePagePayload, Page};
+use crate::models::product::{CreateProductPayload, Product};
+use crate::models::store::{CreateStorePayload, Store};
+
+/// Everything needed to recreate the categories, stores, pages and products of an instance.
+///
+/// Database ids are not exported, pages are nested under their store instead and categories
+/// are referred to by slug.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+    /// Missing from files exported before categories were.
+    #[serde(default)]
+    pub categories: Vec<CategoryExport>,
+    pub stores: Vec<StoreExport>,
+    pub products: Vec<CreateProductPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryExport {
+    pub name: String,
+    pub slug: String,
+    /// Slug of the parent category.
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoreExport {
+    pub name: String,
+    pub url: String,
+    pub pages: Vec<PageExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageExport {
+    pub name: String,
+    pub url: String,
+    pub handler: String,
+    #[serde(rename = "pageKind")]
+    pub page_kind: String,
+    pub ean: Option<String>,
+    pub gtin: Option<String>,
+    /// Slug of the category products found on the page are filed under.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl PageExport {
+    fn new(page: Page, slugs: &HashMap<CategoryId, String>) -> Self {
+        Self {
+            name: page.name,
+            url: page.url.to_string(),
+            handler: page.handler.inner().to_string(),
+            page_kind: page.page_kind.inner().to_string(),
+            ean: page.ean,
+            gtin: page.gtin,
+            category: page.category_id.and_then(|id| slugs.get(&id).cloned()),
+        }
+    }
+}
+
+impl From<Product> for CreateProductPayload {
+    fn from(product: Product) -> Self {
+        Self {
+            name: product.name,
+            brand: product.brand,
+            url: product.url,
+            image: product.image,
+            ean: product.ean,
+            gtin: product.gtin,
+        }
+    }
+}
+
+pub async fn export(db: &PgPool, output: Option<&Path>) -> anyhow::Result<()> {
+    let categories = Category::get_all(db).await?.unwrap_or_default();
+    let slugs = categories
+        .iter()
+        .map(|category| (category.id, category.slug.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut stores = vec![];
+
+    for store in Store::get_all(db).await?.unwrap_or_default() {
+        let pages = Page::get_by_store(db, store.id).await?;
+
+        stores.push(StoreExport {
+            name: store.name,
+            url: store.url.to_string(),
+            pages: pages.into_iter().map(|page| PageExport::new(page, &slugs)).collect(),
+        });
+    }
+
+    let products = Product::get_all(db, None).await?.unwrap_or_default();
+    let export = DataExport {
+        categories: categories
+            .iter()
+            .map(|category| CategoryExport {
+                name: category.name.clone(),
+                slug: category.slug.clone(),
+                parent: category.parent_id.and_then(|id| slugs.get(&id).cloned()),
+            })
+            .collect(),
+        stores,
+        products: products.into_iter().map(Into::into).collect(),
+    };
+
+    let json = serde_json::to_string_pretty(&export)?;
+
+    match output {
+        Some(path) => std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?,
+        None => writeln!(std::io::stdout(), "{json}")?,
+    }
+
+    Ok(())
+}
+
+/// Imports a file created by [`export`], skipping anything that already exists so the same
+/// file can be imported more than once. Products without a gtin or url already exist when one
+/// of the same brand has the same name.
+///
+/// Everything is imported in a single transaction, a file that fails half way imports nothing.
+pub async fn import(db: &PgPool, path: &Path) -> anyhow::Result<()> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let data = serde_json::from_str::<DataExport>(&content).context("invalid import file")?;
+
+    let mut tx = db.begin().await?;
+    let (mut stores, mut pages, mut products) = (0, 0, 0);
+    let categories = import_categories(&mut tx, data.categories).await?;
+
+    for store in data.stores {
+        let url = Url::parse(&store.url).with_context(|| format!("invalid store url {}", store.url))?;
+
+        let existing = match Store::get_by_url(&mut *tx, &url).await? {
+            Some(existing) => existing,
+            None => {
+                let payload = CreateStorePayload {
+                    url: store.url,
+                    name: store.name,
+                }
+                .parse()
+                .map_err(AppError::into_anyhow)?;
+
+                stores += 1;
+                Store::create(&mut *tx, payload).await?
+            }
+        };
+
+        for page in store.pages {
+            let url = Url::parse(&page.url).with_context(|| format!("invalid page url {}", page.url))?;
+
+            if Page::get_by_url(&mut *tx, existing.id, &url).await?.is_some() {
+                continue;
+            }
+
+            let category_id = match page.category {
+                Some(slug) => match Category::get_by_slug(&mut *tx, &slug).await? {
+                    Some(category) => Some(category.id.inner()),
+                    None => anyhow::bail!("page {} is in category {slug}, which does not exist", page.url),
+                },
+                None => None,
+            };
+
+            let payload = CreatePagePayload {
+                name: page.name,
+                url: page.url,
+                store_id: existing.id.inner(),
+                handler: page.handler,
+                page_kind: page.page_kind,
+                ean: page.ean,
+                gtin: page.gtin,
+                category_id,
+            }
+            .parse(&mut tx)
+            .await?;
+
+            pages += 1;
+            Page::create(&mut *tx, payload).await?;
+        }
+    }
+
+    for product in data.products {
+        let payload = product.parse()?;
+
+        if let Some(gtin) = payload.normalized_gtin.as_ref() {
+            if Product::get_by_gtin(&mut *tx, gtin).await?.is_some() {
+                continue;
+            }
+        }
+
+        if let Some(url) = payload.url.as_ref() {
+            if Product::get_by_url(&mut *tx, url).await?.is_some() {
+                continue;
+            }
+        }
+
+        if payload.normalized_gtin.is_none()
+            && payload.url.is_none()
+            && Product::get_by_name(&mut *tx, &payload.brand, &payload.name)
+                .await?
+                .is_some()
+        {
+            continue;
+        }
+
+        products += 1;
+        Product::create(&mut *tx, payload).await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!("imported {categories} categories, {stores} stores, {pages} pages and {products} products");
+
+    Ok(())
+}
+
+/// Creates the categories that don't exist yet, returning how many were. Parents are created
+/// before their children whatever the order of `categories`.
+async fn import_categories(conn: &mut PgConnection, mut categories: Vec<CategoryExport>) -> anyhow::Result<usize> {
+    let mut created = 0;
+
+    while !categories.is_empty() {
+        let pending = categories.len();
+        let mut waiting = vec![];
+
+        for category in std::mem::take(&mut categories) {
+            if Category::get_by_slug(&mut *conn, &category.slug).await?.is_some() {
+                continue;
+            }
+
+            let parent_id = match category.parent.as_deref() {
+                Some(parent) => match Category::get_by_slug(&mut *conn, parent).await? {
+                    Some(parent) => Some(parent.id.inner()),
+                    None => {
+                        waiting.push(category);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let payload = CreateCategoryPayload {
+                name: category.name,
+                slug: Some(category.slug),
+                parent_id,
+            }
+            .parse(conn)
+            .await
+            .map_err(AppError::into_anyhow)?;
+
+            created += 1;
+            Category::create(&mut *conn, payload).await?;
+        }
+
+        // nothing was created on this pass, so the parents left are neither in the file nor on the database
+        if waiting.len() == pending {
+            anyhow::bail!("category {} has a parent that does not exist", waiting[0].slug);
+        }
+
+        categories = waiting;
+    }
+
+    Ok(created)
+}
+
+/// Inserts every row of a CSV or JSON file in a single transaction, see [`import_rows`].
+pub async fn bulk_import<P: Importable>(db: &PgPool, path: &Path) -> anyhow::Result<()> {
+    let format = ImportFormat::from_path(path)?;
+    let content = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let rows = read_rows::<P>(format, &content).context("invalid import file")?;
+
+    let report = import_rows(db, None, rows).await?;
+
+    for error in &report.errors {
+        tracing::error!("row {}: {}", error.row, error.message);
+    }
+
+    if !report.is_ok() {
+        anyhow::bail!("{} rows failed, nothing was imported", report.errors.len());
+    }
+
+    tracing::info!("imported {} rows", report.inserted);
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+pub mod api_key;
+pub mod data;
+
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Keeps track of product prices across stores")]
+pub struct Cli {
+    /// Defaults to `run` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the API, the scraper, the digests and the discord bot
+    Run,
+    /// Run only the API
+    Serve,
+    /// Scrap a single page, or every page of a store, once and exit
+    ScrapeOnce(ScrapeOnceArgs),
+    /// Apply pending database migrations and exit
+    Migrate,
+    /// Import categories, stores, pages and products from a file created by `export`
+    Import {
+        /// JSON file to read from
+        path: PathBuf,
+    },
+    /// Export categories, stores, pages and products as JSON
+    Export {
+        /// File to write to, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Insert stores, pages or products from a CSV or a JSON array, all of them or none
+    BulkImport {
+        kind: ImportKind,
+        /// .csv or .json file to read from
+        path: PathBuf,
+    },
+    /// Manage the keys allowed to call the API
+    #[command(subcommand)]
+    ApiKey(ApiKeyCommand),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportKind {
+    Stores,
+    Pages,
+    Products,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ApiKeyCommand {
+    /// Issue a new key and print it, it can't be recovered afterwards
+    Issue {
+        /// What the key is for
+        #[arg(long)]
+        name: String,
+        /// One of read, write or admin
+        #[arg(long, default_value = "admin")]
+        scope: String,
+    },
+    /// Revoke a key by its id
+    Revoke { id: i32 },
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct ScrapeOnceArgs {
+    /// Id of the page to scrap
+    #[arg(long)]
+    pub page: Option<i32>,
+    /// Id of the store to scrap every active page of
+    #[arg(long)]
+    pub store: Option<i32>,
+}
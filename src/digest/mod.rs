@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::models::digest::{Digest, DigestEntry};
+use crate::models::notification_channel::{DigestSchedule, NotificationChannel};
+use crate::notifier::TaskNotification;
+
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    db: PgPool,
+    notifier: UnboundedSender<TaskNotification>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<JoinHandle<()>> {
+    let handle = tokio::spawn(async move {
+        // digests are due at most once a day, checking hourly keeps them close to their schedule
+        const INTERVAL_SECS: u64 = 60 * 60;
+
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(INTERVAL_SECS));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            if let Err(e) = send_due_digests(&db, &notifier).await {
+                tracing::error!("failed to send digests: {e}");
+            }
+        }
+    });
+
+    Ok(handle)
+}
+
+async fn send_due_digests(db: &PgPool, notifier: &UnboundedSender<TaskNotification>) -> anyhow::Result<()> {
+    let channels = NotificationChannel::get_due_digests(db).await?;
+
+    for channel in channels {
+        let Some(schedule) = channel.digest else {
+            continue;
+        };
+
+        // one channel failing shouldn't hold back the digests of the others, it's retried on the
+        // next check as it's still due
+        if let Err(e) = send_digest(db, notifier, &channel, schedule).await {
+            tracing::error!("failed to send digest of channel {}: {e}", channel.id.inner());
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_digest(
+    db: &PgPool,
+    notifier: &UnboundedSender<TaskNotification>,
+    channel: &NotificationChannel,
+    schedule: DigestSchedule,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let since = channel.last_digest_at.unwrap_or(channel.created_at);
+    let digest = Digest::compile(db, channel, since).await?;
+
+    if !digest.is_empty() {
+        let notification = TaskNotification {
+            title: format!("{} digest", schedule.inner()),
+            message: format_digest(&digest, schedule),
+            channels: vec![channel.channel_id],
+            image: None,
+            subscribers_only: true,
+        };
+
+        // the receiving end only goes away when the notifier is shutting down, nothing to do then
+        _ = notifier.send(notification);
+    }
+
+    NotificationChannel::mark_digest_sent(db, channel.id, now).await?;
+
+    Ok(())
+}
+
+fn format_digest(digest: &Digest, schedule: DigestSchedule) -> String {
+    let mut message = format!(
+        "what happened since {} ({})",
+        digest.since.format("%d/%m %H:%M"),
+        schedule.inner()
+    );
+
+    format_section(&mut message, "biggest drops", &digest.biggest_drops);
+    format_section(&mut message, "new lows", &digest.new_lows);
+
+    if !digest.back_in_stock.is_empty() {
+        message.push_str("\n\n**back in stock**");
+        for entry in digest.back_in_stock.iter() {
+            _ = write!(
+                message,
+                "\n- {} @ {}: R$ {:.2}",
+                entry.product, entry.store, entry.new_price
+            );
+        }
+    }
+
+    if !digest.failures.is_empty() {
+        message.push_str("\n\n**scrape failures**");
+        for failure in digest.failures.iter() {
+            _ = write!(message, "\n- {}: {} failed pages", failure.store, failure.failures);
+        }
+    }
+
+    message
+}
+
+fn format_section(message: &mut String, title: &str, entries: &[DigestEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+
+    _ = write!(message, "\n\n**{title}**");
+    for entry in entries {
+        let change = (entry.old_price - entry.new_price) / entry.old_price * 100.0;
+        _ = write!(
+            message,
+            "\n- {} @ {}: R$ {:.2} -> R$ {:.2} (-{change:.1}%)",
+            entry.product, entry.store, entry.old_price, entry.new_price
+        );
+    }
+}
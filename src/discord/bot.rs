@@ -0,0 +1,226 @@
+use poise::serenity_prelude::{self as serenity, ConnectionStage, CreateAttachment, FullEvent};
+use poise::CreateReply;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::{BotState, BotStatus};
+use crate::chart;
+use crate::config::DiscordConfig;
+use crate::models::notification_channel::{CreateNotificationChannelPayload, NotificationChannel};
+use crate::models::product::{Product, ProductId};
+
+#[derive(Debug)]
+struct Data {
+    db: PgPool,
+    status: BotStatus,
+}
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum DigestChoice {
+    #[name = "daily"]
+    Daily,
+    #[name = "weekly"]
+    Weekly,
+}
+
+impl DigestChoice {
+    fn inner(&self) -> &str {
+        match self {
+            DigestChoice::Daily => "daily",
+            DigestChoice::Weekly => "weekly",
+        }
+    }
+}
+
+/// Subscribe this channel to price notifications, optionally filtered by store, product or brand
+#[poise::command(slash_command, guild_only)]
+async fn subscribe(
+    ctx: Context<'_>,
+    #[description = "Only notify about prices from this store id"] store: Option<i32>,
+    #[description = "Only notify about prices of this product id"] product: Option<i32>,
+    #[description = "Only notify about products of this brand"] brand: Option<String>,
+    #[description = "Receive a periodic summary instead of every change"] digest: Option<DigestChoice>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("subscriptions are only available inside a server".into());
+    };
+
+    let payload = CreateNotificationChannelPayload {
+        guild_id: guild_id.get(),
+        channel_id: ctx.channel_id().get(),
+        store_id: store,
+        product_id: product,
+        brand,
+        digest: digest.map(|digest| digest.inner().to_string()),
+    }
+    .parse(&ctx.data().db)
+    .await?;
+
+    if let Some(existing) = NotificationChannel::get_duplicate(&ctx.data().db, &payload).await? {
+        ctx.say(format!(
+            "this channel is already subscribed with these filters (#{})",
+            existing.id.inner()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let channel = NotificationChannel::create(&ctx.data().db, payload).await?;
+
+    ctx.say(format!(
+        "subscribed this channel to price notifications (#{})",
+        channel.id.inner()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Show the price history of a product on every store it is sold
+#[poise::command(slash_command)]
+async fn history(ctx: Context<'_>, #[description = "Id of the product to chart"] product: i32) -> Result<(), Error> {
+    let db = &ctx.data().db;
+
+    let Ok(product_id) = ProductId::new(db, product).await else {
+        ctx.say(format!("product #{product} does not exist")).await?;
+        return Ok(());
+    };
+
+    let Some(product) = Product::get_by_id(db, product_id).await? else {
+        ctx.say(format!("product #{} does not exist", product_id.inner()))
+            .await?;
+        return Ok(());
+    };
+
+    // rendering can take a while, so we let discord know we are working on it
+    ctx.defer().await?;
+
+    let series = chart::product_price_series(db, &product).await?;
+    if series.is_empty() {
+        ctx.say(format!("no prices recorded for {} yet", product.name)).await?;
+        return Ok(());
+    }
+
+    let mut content = format!("**{}**", product.name);
+    for store in series.iter() {
+        let lowest = store
+            .points
+            .iter()
+            .map(|(_, price)| *price)
+            .fold(f64::INFINITY, f64::min);
+        if let Some((_, latest)) = store.points.last() {
+            content.push_str(&format!("\n{}: R$ {latest:.2} (lowest R$ {lowest:.2})", store.label));
+        }
+    }
+
+    let title = product.name.clone();
+    let png = tokio::task::spawn_blocking(move || chart::render_price_history(&title, &series)).await??;
+
+    let reply = CreateReply::default()
+        .content(content)
+        .attachment(CreateAttachment::bytes(png, "history.png"));
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+async fn event_handler(
+    _: &serenity::Context,
+    event: &FullEvent,
+    _: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+) -> Result<(), Error> {
+    match event {
+        FullEvent::Ready { .. } | FullEvent::Resume { .. } => data.status.set(BotState::Connected),
+        FullEvent::ShardStageUpdate { event } => match event.new {
+            ConnectionStage::Connected => data.status.set(BotState::Connected),
+            ConnectionStage::Disconnected => data.status.set(BotState::Disconnected),
+            _ => data.status.set(BotState::Connecting),
+        },
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Starts the bot in the background when a discord token is configured and the bot was not
+/// disabled through `discord.bot_enabled`.
+///
+/// Failing to connect never takes the rest of the application down, it is reported through
+/// `status` instead. The bot disconnects once `shutdown` is cancelled.
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    db: PgPool,
+    status: BotStatus,
+    config: &DiscordConfig,
+    shutdown: CancellationToken,
+) -> anyhow::Result<Option<JoinHandle<()>>> {
+    let Some(token) = config.token.as_ref().map(|token| token.expose().to_string()) else {
+        tracing::info!("discord token is not set, discord bot is disabled");
+        return Ok(None);
+    };
+
+    if !config.bot_enabled {
+        tracing::info!("discord bot disabled through configuration");
+        return Ok(None);
+    }
+
+    status.set(BotState::Connecting);
+
+    let handle = tokio::spawn(async move {
+        let intents = serenity::GatewayIntents::non_privileged();
+        let data_status = status.clone();
+
+        let framework = poise::Framework::<Data, Error>::builder()
+            .options(poise::FrameworkOptions {
+                commands: vec![subscribe(), history()],
+                event_handler: |ctx, event, framework, data| Box::pin(event_handler(ctx, event, framework, data)),
+                ..Default::default()
+            })
+            .setup(move |ctx, _ready, framework| {
+                Box::pin(async move {
+                    poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                    data_status.set(BotState::Connected);
+
+                    Ok(Data {
+                        db,
+                        status: data_status,
+                    })
+                })
+            })
+            .build();
+
+        let client = serenity::ClientBuilder::new(token, intents).framework(framework).await;
+
+        let mut client = match client {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("failed to build discord client: {e}");
+                status.set(BotState::Failed);
+                return;
+            }
+        };
+
+        let shard_manager = client.shard_manager.clone();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            shard_manager.shutdown_all().await;
+        });
+
+        match client.start().await {
+            Ok(_) => status.set(BotState::Disconnected),
+            Err(e) => {
+                tracing::error!("discord bot stopped: {e}");
+                status.set(BotState::Failed);
+            }
+        }
+    });
+
+    Ok(Some(handle))
+}
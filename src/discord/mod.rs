@@ -1,66 +1,49 @@
-use anyhow::Context as AContext;
-use poise::serenity_prelude::{self as serenity, ChannelId};
-use tokio::sync::mpsc::UnboundedReceiver;
-
-#[derive(Default, Debug)]
-struct Data {}
-
-type Error = Box<dyn std::error::Error + Send + Sync>;
-
-type Context<'a> = poise::Context<'a, Data, Error>;
-
-pub struct TaskNotification {
-    channel: u64,
-    message: String,
+#[cfg(feature = "discord")]
+mod bot;
+
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "discord")]
+pub use bot::start_thread;
+use serde::Serialize;
+
+#[cfg_attr(not(feature = "discord"), allow(dead_code))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotState {
+    /// The bot was not compiled in or not configured to run.
+    #[default]
+    Disabled,
+    Connecting,
+    Connected,
+    Disconnected,
+    /// The bot could not connect and will not retry.
+    Failed,
 }
 
-async fn scrap_thread(
-    mut task_receiver: UnboundedReceiver<TaskNotification>,
-    ctx: serenity::Context,
-) -> Result<(), Error> {
-    ChannelId::from(1329172988486221897).say(&ctx.http, "lol hi").await?;
-    while let Some(notification) = task_receiver.recv().await {
-        if let Err(why) = ChannelId::from(notification.channel)
-            .say(&ctx.http, &notification.message)
-            .await
-        {
-            tracing::error!("{why:?}");
+impl BotState {
+    pub fn inner(&self) -> &str {
+        match self {
+            BotState::Disabled => "disabled",
+            BotState::Connecting => "connecting",
+            BotState::Connected => "connected",
+            BotState::Disconnected => "disconnected",
+            BotState::Failed => "failed",
         }
     }
-
-    Ok(())
 }
 
-pub async fn start_thread(task_receiver: UnboundedReceiver<TaskNotification>) -> anyhow::Result<()> {
-    tokio::spawn(async move {
-        tracing::error!("discord token asda");
-
-        let token = std::env::var("DISCORD_TOKEN")
-            .context("missing DISCORD_TOKEN")
-            .expect("lol");
-        let intents = serenity::GatewayIntents::non_privileged();
+/// Connection state of the discord bot, shared between the bot task and the health endpoint.
+#[derive(Debug, Default, Clone)]
+pub struct BotStatus(Arc<RwLock<BotState>>);
 
-        tracing::error!("discord token {token:?}");
-
-        let framework = poise::Framework::<Data, Error>::builder()
-            .options(poise::FrameworkOptions {
-                commands: vec![],
-                ..Default::default()
-            })
-            .setup(move |ctx, _ready, framework| {
-                Box::pin(async move {
-                    tokio::spawn(scrap_thread(task_receiver, ctx.clone()));
-
-                    poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-
-                    Ok(Data {})
-                })
-            })
-            .build();
-
-        let client = serenity::ClientBuilder::new(token, intents).framework(framework).await;
+impl BotStatus {
+    pub fn get(&self) -> BotState {
+        *self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
 
-        client.unwrap().start().await.unwrap();
-    });
-    Ok(())
+    #[cfg_attr(not(feature = "discord"), allow(dead_code))]
+    pub fn set(&self, state: BotState) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = state;
+    }
 }
@@ -8,6 +8,37 @@ use validator::ValidationErrors;
 pub enum AppError {
     ServerError(String),
     ValidationError(ValidationErrors),
+    /// The request body can't be read at all, as opposed to holding invalid values.
+    BadRequest(String),
+    /// The request has no api key, or one that doesn't exist.
+    Unauthorized(String),
+    /// The api key is valid but lacks the scope the route requires.
+    Forbidden(String),
+    /// The record would duplicate one that already exists.
+    Conflict(String),
+}
+
+/// An id that doesn't belong to any row of its table, raised by the id newtypes.
+#[derive(Debug)]
+pub struct UnknownId {
+    pub table: &'static str,
+    pub id: i32,
+}
+
+impl std::fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no row of {} has id {}", self.table, self.id)
+    }
+}
+
+impl std::error::Error for UnknownId {}
+
+/// Whether `err` is a statement that failed on a unique index, eg: two requests creating the
+/// same record at once.
+pub fn is_unique_violation(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<sqlx::Error>()
+        .and_then(|e| e.as_database_error())
+        .is_some_and(|e| e.is_unique_violation())
 }
 
 #[derive(Serialize)]
@@ -21,6 +52,21 @@ impl std::fmt::Display for AppError {
         match self {
             AppError::ServerError(msg) => write!(f, "internal server error {msg}"),
             AppError::ValidationError(_) => write!(f, "invalid payload"),
+            AppError::BadRequest(msg) => write!(f, "bad request {msg}"),
+            AppError::Unauthorized(msg) => write!(f, "unauthorized {msg}"),
+            AppError::Forbidden(msg) => write!(f, "forbidden {msg}"),
+            AppError::Conflict(msg) => write!(f, "conflict {msg}"),
+        }
+    }
+}
+
+impl AppError {
+    /// Converts back into an [`anyhow::Error`] for code outside of request handling, keeping the
+    /// validation details that the response body would otherwise carry.
+    pub fn into_anyhow(self) -> anyhow::Error {
+        match self {
+            AppError::ValidationError(errors) => anyhow::anyhow!("invalid payload: {errors}"),
+            other => anyhow::anyhow!("{other}"),
         }
     }
 }
@@ -30,6 +76,10 @@ impl IntoResponse for AppError {
         let code = match self {
             AppError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
         };
 
         let message = Json(ErrorBody {
@@ -47,6 +97,9 @@ where
 {
     fn from(err: E) -> Self {
         let err: anyhow::Error = err.into();
+        if let Some(unknown) = err.downcast_ref::<UnknownId>() {
+            return AppError::BadRequest(unknown.to_string());
+        }
         let msg = err.to_string();
         AppError::ServerError(msg)
     }
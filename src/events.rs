@@ -0,0 +1,65 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::models::product::ProductId;
+use crate::models::product_price::ProductPrice;
+use crate::models::scrape_run::ScrapeRun;
+use crate::models::store::StoreId;
+
+/// Something that happened while scraping that live clients may want to know about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    PriceInserted(ProductPrice),
+    PriceDropped(PriceDrop),
+    ScrapeRunChanged(ScrapeRun),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceDrop {
+    #[serde(rename = "productId")]
+    pub product_id: ProductId,
+    #[serde(rename = "storeId")]
+    pub store_id: StoreId,
+    #[serde(rename = "previousPrice")]
+    pub previous_price: f64,
+    pub price: f64,
+}
+
+impl Event {
+    /// Name the event is sent under, lets clients listen to a single kind.
+    pub fn name(&self) -> &str {
+        match self {
+            Event::PriceInserted(_) => "price",
+            Event::PriceDropped(_) => "price_drop",
+            Event::ScrapeRunChanged(_) => "scrape_run",
+        }
+    }
+}
+
+/// Fans events out to every subscriber, events published without subscribers are dropped.
+#[derive(Debug, Clone)]
+pub struct EventBus(broadcast::Sender<Event>);
+
+impl EventBus {
+    /// `capacity` is how many events a slow subscriber may fall behind before missing some.
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self(tx)
+    }
+
+    pub fn publish(&self, event: Event) {
+        // an error only means nobody is listening right now
+        _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.0.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use validator::ValidationError;
+
+/// Lengths of every GTIN flavour: GTIN-8 (EAN-8), GTIN-12 (UPC-A), GTIN-13 (EAN-13) and GTIN-14.
+const LENGTHS: [usize; 4] = [8, 12, 13, 14];
+
+/// A product identifier with a valid check digit, normalized to 14 digits.
+///
+/// Shorter GTINs are the same number padded with leading zeros, so normalizing them lets an
+/// EAN-13 match the GTIN-13 or GTIN-14 of the same product regardless of how a store or a
+/// person wrote it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Gtin(String);
+
+impl Gtin {
+    /// Parses a GTIN of any length, spaces and dashes are ignored as they often show up in
+    /// copied identifiers.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let digits = clean(value);
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            anyhow::bail!("gtin must only contain digits");
+        }
+
+        if !LENGTHS.contains(&digits.len()) {
+            anyhow::bail!("gtin must have 8, 12, 13 or 14 digits");
+        }
+
+        if !has_valid_check_digit(&digits) {
+            anyhow::bail!("gtin check digit is invalid");
+        }
+
+        Ok(Self(format!("{digits:0>14}")))
+    }
+
+    pub fn inner(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Gtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Strips what people usually put between digits, keeping the identifier as it was written
+/// otherwise.
+pub fn clean(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+/// The last digit is chosen so the digits, weighted 3 and 1 alternately from the right, add up to
+/// a multiple of 10.
+fn has_valid_check_digit(digits: &str) -> bool {
+    let sum = digits
+        .bytes()
+        .rev()
+        .map(|byte| u32::from(byte - b'0'))
+        .enumerate()
+        .map(|(position, digit)| if position % 2 == 1 { digit * 3 } else { digit })
+        .sum::<u32>();
+
+    sum % 10 == 0
+}
+
+/// Validator for `Option<String>` payload fields holding an EAN or a GTIN.
+pub fn validate(value: &str) -> Result<(), ValidationError> {
+    match Gtin::parse(value) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ValidationError::new("gtin").with_message(Cow::Owned(e.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_codes_of_every_length() {
+        for (code, normalized) in [
+            ("96385074", "00000096385074"),
+            ("036000291452", "00036000291452"),
+            ("4006381333931", "04006381333931"),
+            ("10012345678902", "10012345678902"),
+        ] {
+            assert_eq!(Gtin::parse(code).unwrap().inner(), normalized, "{code}");
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_check_digits() {
+        for code in ["96385075", "036000291453", "4006381333932", "10012345678903"] {
+            let error = Gtin::parse(code).unwrap_err();
+            assert_eq!(error.to_string(), "gtin check digit is invalid", "{code}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_lengths_and_non_digits() {
+        assert!(Gtin::parse("1234567").is_err());
+        assert!(Gtin::parse("12345678901").is_err());
+        assert!(Gtin::parse("400638133393A").is_err());
+        assert!(Gtin::parse("").is_err());
+    }
+
+    #[test]
+    fn ignores_spaces_and_dashes() {
+        assert_eq!(
+            Gtin::parse(" 400-6381 333931 ").unwrap(),
+            Gtin::parse("4006381333931").unwrap()
+        );
+    }
+
+    #[test]
+    fn shorter_codes_match_their_padded_form() {
+        assert_eq!(
+            Gtin::parse("036000291452").unwrap(),
+            Gtin::parse("0036000291452").unwrap()
+        );
+    }
+}
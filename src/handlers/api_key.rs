@@ -0,0 +1,45 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::api_key::{ApiKey, ApiKeyId, CreateApiKeyPayload, IssuedApiKey};
+use crate::models::audit_log::AuditLog;
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ApiKey>>> {
+    let keys = ApiKey::get_all(db).await?;
+    Ok(keys)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<ApiKey>, AppError> {
+    let id = ApiKeyId::new(db, id).await?;
+    let key = ApiKey::get_by_id(db, id).await?;
+    Ok(key)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create(
+    db: &PgPool,
+    api_key: &ApiKey,
+    payload: CreateApiKeyPayload,
+) -> anyhow::Result<IssuedApiKey, AppError> {
+    let payload = payload.parse().map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let mut tx = db.begin().await?;
+    let key = ApiKey::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &key.api_key).await?;
+    tx.commit().await?;
+    Ok(key)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn revoke(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Option<ApiKey>, AppError> {
+    let id = ApiKeyId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let before = ApiKey::get_by_id_for_update(&mut *tx, id).await?;
+    let key = ApiKey::revoke(&mut *tx, id).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), key.as_ref()) {
+        AuditLog::deleted(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(key)
+}
@@ -0,0 +1,22 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::audit_log::{AuditFilter, AuditLog, AuditLogId};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool, filter: AuditFilter) -> anyhow::Result<Vec<AuditLog>, AppError> {
+    let filter = filter
+        .parse(db)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let entries = AuditLog::get_all(db, filter).await?;
+    Ok(entries)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<AuditLog>, AppError> {
+    let id = AuditLogId::new(db, id).await?;
+    let entry = AuditLog::get_by_id(db, id).await?;
+    Ok(entry)
+}
@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::{AuditAction, AuditLog, Audited};
+use crate::models::brand::{Brand, BrandId, BrandStats, CreateBrandPayload, ValidCreateBrandPayload};
+use crate::models::category::CategoryFilter;
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Brand>>, AppError> {
+    let brands = Brand::get_all(db).await?;
+    Ok(brands)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Brand>, AppError> {
+    let id = BrandId::new(db, id).await?;
+    let brand = Brand::get_by_id(db, id).await?;
+    Ok(brand)
+}
+
+/// A name or alias resolves to a single brand, checked here so one already taken is a bad
+/// request rather than a brand that can never be matched.
+async fn ensure_names_are_free(
+    db: &PgPool,
+    payload: &ValidCreateBrandPayload,
+    id: Option<BrandId>,
+) -> anyhow::Result<(), AppError> {
+    let mut names = payload.aliases.clone();
+    names.push(payload.name.clone());
+
+    match Brand::get_conflicting(db, &names, id).await? {
+        Some(existing) => Err(AppError::BadRequest(format!(
+            "brand {} ({}) already uses one of these names, merge it instead",
+            existing.id.inner(),
+            existing.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create(db: &PgPool, api_key: &ApiKey, payload: CreateBrandPayload) -> anyhow::Result<Brand, AppError> {
+    let payload = payload.parse()?;
+    ensure_names_are_free(db, &payload, None).await?;
+
+    let mut tx = db.begin().await?;
+    let brand = Brand::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &brand).await?;
+    tx.commit().await?;
+    Ok(brand)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: CreateBrandPayload,
+) -> anyhow::Result<Option<Brand>, AppError> {
+    let id = BrandId::new(db, id).await?;
+    let payload = payload.parse()?;
+    ensure_names_are_free(db, &payload, Some(id)).await?;
+
+    let mut tx = db.begin().await?;
+    let before = Brand::get_by_id_for_update(&mut *tx, id).await?;
+    let brand = Brand::update(&mut tx, id, payload).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), brand.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(brand)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Option<Brand>, AppError> {
+    let id = BrandId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let before = Brand::get_by_id_for_update(&mut *tx, id).await?;
+    let brand = Brand::delete(&mut tx, id).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), brand.as_ref()) {
+        AuditLog::deleted(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(brand)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MergeBrandPayload {
+    /// The brand to merge into, it's the one left afterwards.
+    pub into: i32,
+}
+
+/// Merges brand `id` into another one, returning the brand that is left. This is how a brand
+/// the scraper created from a store's spelling becomes an alias of the right one.
+#[tracing::instrument(skip_all)]
+pub async fn merge(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: MergeBrandPayload,
+) -> anyhow::Result<Brand, AppError> {
+    let source = BrandId::new(db, id).await?;
+    let target = BrandId::new(db, payload.into).await?;
+
+    if source == target {
+        return Err(AppError::BadRequest("cannot merge a brand into itself".into()));
+    }
+
+    let mut tx = db.begin().await?;
+    let before = (
+        Brand::get_by_id_for_update(&mut *tx, source).await?,
+        Brand::get_by_id_for_update(&mut *tx, target).await?,
+    );
+    let (Some(source_before), Some(target_before)) = before else {
+        return Err(AppError::BadRequest("cannot merge a deleted brand".into()));
+    };
+
+    let Some(brand) = Brand::merge(&mut tx, source, target).await? else {
+        return Err(AppError::BadRequest("cannot merge a deleted brand".into()));
+    };
+    AuditLog::record(
+        &mut *tx,
+        api_key,
+        AuditAction::Delete,
+        Brand::ENTITY,
+        Some(source_before.id.inner()),
+        Some(serde_json::to_value(&source_before)?),
+        None,
+    )
+    .await?;
+    AuditLog::updated(&mut *tx, api_key, &target_before, &brand).await?;
+    tx.commit().await?;
+
+    Ok(brand)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_stats(db: &PgPool, id: i32, filter: CategoryFilter) -> anyhow::Result<BrandStats, AppError> {
+    let id = BrandId::new(db, id).await?;
+    let category = filter.parse(db).await?;
+    let stats = Brand::get_stats(db, id, category).await?;
+    Ok(stats)
+}
@@ -0,0 +1,94 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
+use crate::models::category::{Category, CategoryId, CreateCategoryPayload, ValidCreateCategoryPayload};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Category>>, AppError> {
+    let categories = Category::get_all(db).await?;
+    Ok(categories)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Category>, AppError> {
+    let id = CategoryId::new(db, id).await?;
+    let category = Category::get_by_id(db, id).await?;
+    Ok(category)
+}
+
+/// Slugs are unique among active categories, checked here so taken ones are a bad request
+/// rather than a constraint violation.
+async fn ensure_slug_is_free(
+    db: &PgPool,
+    payload: &ValidCreateCategoryPayload,
+    id: Option<CategoryId>,
+) -> anyhow::Result<(), AppError> {
+    match Category::get_by_slug(db, &payload.slug).await? {
+        Some(existing) if Some(existing.id) != id => Err(AppError::BadRequest(format!(
+            "category {} already has slug {}",
+            existing.id.inner(),
+            payload.slug
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create(
+    db: &PgPool,
+    api_key: &ApiKey,
+    payload: CreateCategoryPayload,
+) -> anyhow::Result<Category, AppError> {
+    let mut tx = db.begin().await?;
+    let payload = payload.parse(&mut tx).await?;
+    ensure_slug_is_free(db, &payload, None).await?;
+
+    let category = Category::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &category).await?;
+    tx.commit().await?;
+    Ok(category)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: CreateCategoryPayload,
+) -> anyhow::Result<Option<Category>, AppError> {
+    let id = CategoryId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let payload = payload.parse(&mut tx).await?;
+    ensure_slug_is_free(db, &payload, Some(id)).await?;
+
+    if let Some(parent_id) = payload.parent_id {
+        if Category::contains(db, id, parent_id).await? {
+            return Err(AppError::BadRequest(
+                "a category cannot be moved under itself or one of its subcategories".into(),
+            ));
+        }
+    }
+
+    let before = Category::get_by_id_for_update(&mut *tx, id).await?;
+    let category = Category::update(&mut *tx, id, payload).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), category.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(category)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Option<Category>, AppError> {
+    let id = CategoryId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let before = Category::get_by_id_for_update(&mut *tx, id).await?;
+    let category = Category::delete(&mut tx, id).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), category.as_ref()) {
+        AuditLog::deleted(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(category)
+}
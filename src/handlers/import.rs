@@ -0,0 +1,221 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::{Acquire, PgConnection, PgPool};
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::{AuditLog, Audited};
+use crate::models::page::{CreatePagePayload, Page};
+use crate::models::product::{CreateProductPayload, Product};
+use crate::models::store::{CreateStorePayload, Store};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+impl ImportFormat {
+    /// JSON is assumed when no content type is given.
+    pub fn from_content_type(content_type: Option<&str>) -> anyhow::Result<Self> {
+        let mime = content_type.and_then(|value| value.split(';').next()).map(str::trim);
+
+        match mime {
+            Some("text/csv") => Ok(Self::Csv),
+            Some("application/json") | None => Ok(Self::Json),
+            Some(mime) => anyhow::bail!("unsupported content type {mime}, expected text/csv or application/json"),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Ok(Self::Csv),
+            Some("json") => Ok(Self::Json),
+            _ => anyhow::bail!(
+                "cannot tell the format of {}, expected a .csv or .json file",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Outcome of a bulk import, rows are either all inserted or none is.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportRowError {
+    /// 1-based position of the row, the CSV header is not counted.
+    pub row: usize,
+    pub message: String,
+}
+
+impl ImportReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn fail(&mut self, row: usize, error: anyhow::Error) {
+        self.errors.push(ImportRowError {
+            row,
+            message: format!("{error:#}"),
+        });
+    }
+}
+
+/// A create payload that can be bulk imported.
+pub trait Importable: DeserializeOwned {
+    type Valid;
+    type Record: Audited;
+
+    async fn parse(self, db: &PgPool) -> anyhow::Result<Self::Valid>;
+
+    async fn insert(conn: &mut PgConnection, payload: Self::Valid) -> anyhow::Result<Self::Record>;
+}
+
+impl Importable for CreateStorePayload {
+    type Valid = crate::models::store::ValidCreateStorePayload;
+    type Record = Store;
+
+    async fn parse(self, _: &PgPool) -> anyhow::Result<Self::Valid> {
+        CreateStorePayload::parse(self).map_err(AppError::into_anyhow)
+    }
+
+    async fn insert(conn: &mut PgConnection, payload: Self::Valid) -> anyhow::Result<Self::Record> {
+        Store::create(conn, payload).await
+    }
+}
+
+impl Importable for CreatePagePayload {
+    type Valid = crate::models::page::ValidCreatePagePayload;
+    type Record = Page;
+
+    async fn parse(self, db: &PgPool) -> anyhow::Result<Self::Valid> {
+        CreatePagePayload::parse(self, &mut *db.acquire().await?).await
+    }
+
+    async fn insert(conn: &mut PgConnection, payload: Self::Valid) -> anyhow::Result<Self::Record> {
+        Page::create(conn, payload).await
+    }
+}
+
+impl Importable for CreateProductPayload {
+    type Valid = crate::models::product::ValidCreateProductPayload;
+    type Record = Product;
+
+    async fn parse(self, _: &PgPool) -> anyhow::Result<Self::Valid> {
+        CreateProductPayload::parse(self)
+    }
+
+    async fn insert(conn: &mut PgConnection, payload: Self::Valid) -> anyhow::Result<Self::Record> {
+        Product::create(conn, payload).await
+    }
+}
+
+/// Reads every row of `body`, a row that can't be deserialized is kept as an error so it shows
+/// up on the report with the others.
+///
+/// Fails only when `body` as a whole is unreadable, eg: JSON that isn't an array.
+pub fn read_rows<P: Importable>(format: ImportFormat, body: &[u8]) -> anyhow::Result<Vec<anyhow::Result<P>>> {
+    let rows = match format {
+        ImportFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(body)
+            .deserialize::<P>()
+            .map(|row| row.map_err(Into::into))
+            .collect(),
+        ImportFormat::Json => serde_json::from_slice::<Vec<serde_json::Value>>(body)?
+            .into_iter()
+            .map(|row| serde_json::from_value::<P>(row).map_err(Into::into))
+            .collect(),
+    };
+
+    Ok(rows)
+}
+
+/// Validates every row and inserts them in a single transaction. Nothing is inserted when any
+/// row fails, the report then lists every failing row instead of only the first one.
+///
+/// Every inserted row is kept on the audit log when the import is made with `api_key`.
+#[tracing::instrument(skip_all)]
+pub async fn import_rows<P: Importable>(
+    db: &PgPool,
+    api_key: Option<&ApiKey>,
+    rows: Vec<anyhow::Result<P>>,
+) -> anyhow::Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut valid = vec![];
+
+    for (idx, row) in rows.into_iter().enumerate() {
+        let parsed = match row {
+            Ok(payload) => payload.parse(db).await,
+            Err(e) => Err(e),
+        };
+
+        match parsed {
+            Ok(payload) => valid.push((idx + 1, payload)),
+            Err(e) => report.fail(idx + 1, e),
+        }
+    }
+
+    if !report.is_ok() {
+        return Ok(report);
+    }
+
+    let mut tx = db.begin().await?;
+
+    for (row, payload) in valid {
+        // a failing statement aborts the whole transaction, rolling back to a savepoint instead
+        // lets the remaining rows be checked too
+        let mut savepoint = (&mut *tx).begin().await?;
+
+        let result = match P::insert(&mut savepoint, payload).await {
+            Ok(record) => match api_key {
+                Some(api_key) => AuditLog::created(&mut *savepoint, api_key, &record).await,
+                None => Ok(()),
+            },
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => {
+                savepoint.commit().await?;
+                report.inserted += 1;
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                report.fail(row, e);
+            }
+        }
+    }
+
+    if report.is_ok() {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+        report.inserted = 0;
+    }
+
+    Ok(report)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn import_body<P: Importable>(
+    db: &PgPool,
+    api_key: &ApiKey,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> anyhow::Result<ImportReport, AppError> {
+    let rows = ImportFormat::from_content_type(content_type)
+        .and_then(|format| read_rows::<P>(format, body))
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let report = import_rows(db, Some(api_key), rows).await?;
+    Ok(report)
+}
@@ -0,0 +1,32 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::category::CategoryFilter;
+use crate::models::listing::{Listing, ListingId};
+use crate::models::product::ProductId;
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool, filter: CategoryFilter) -> anyhow::Result<Option<Vec<Listing>>, AppError> {
+    let category = filter.parse(db).await?;
+    let listings = Listing::get_all(db, category).await?;
+    Ok(listings)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Listing>, AppError> {
+    let id = ListingId::new(db, id).await?;
+    let listing = Listing::get_by_id(db, id).await?;
+    Ok(listing)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_by_product(
+    db: &PgPool,
+    product_id: i32,
+    filter: CategoryFilter,
+) -> anyhow::Result<Vec<Listing>, AppError> {
+    let product_id = ProductId::new(db, product_id).await?;
+    let category = filter.parse(db).await?;
+    let listings = Listing::get_by_product(db, product_id, category).await?;
+    Ok(listings)
+}
@@ -1,4 +1,15 @@
+pub mod api_key;
+pub mod audit_log;
+pub mod brand;
+pub mod category;
+pub mod import;
+pub mod listing;
+pub mod notification_channel;
 pub mod page;
 pub mod product;
+pub mod product_match;
 pub mod product_price;
+pub mod scrape_failure;
+pub mod scrape_run;
 pub mod store;
+pub mod webhook;
@@ -0,0 +1,46 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
+use crate::models::notification_channel::{
+    CreateNotificationChannelPayload, NotificationChannel, NotificationChannelId,
+};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<NotificationChannel>>> {
+    let channels = NotificationChannel::get_all(db).await?;
+    Ok(channels)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<NotificationChannel>, AppError> {
+    let id = NotificationChannelId::new(db, id).await?;
+    let channel = NotificationChannel::get_by_id(db, id).await?;
+    Ok(channel)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create(
+    db: &PgPool,
+    api_key: &ApiKey,
+    payload: CreateNotificationChannelPayload,
+) -> anyhow::Result<NotificationChannel, AppError> {
+    let payload = payload
+        .parse(db)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    if let Some(existing) = NotificationChannel::get_duplicate(db, &payload).await? {
+        return Err(AppError::Conflict(format!(
+            "channel is already subscribed with these filters ({})",
+            existing.id.inner()
+        )));
+    }
+
+    let mut tx = db.begin().await?;
+    let channel = NotificationChannel::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &channel).await?;
+    tx.commit().await?;
+    Ok(channel)
+}
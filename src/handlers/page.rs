@@ -1,6 +1,8 @@
 use sqlx::PgPool;
 
 use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
 use crate::models::page::{CreatePagePayload, Page, PageId};
 
 #[tracing::instrument(skip_all)]
@@ -17,8 +19,36 @@ pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Page>, AppEr
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn create(db: &PgPool, payload: CreatePagePayload) -> anyhow::Result<Page, AppError> {
-    let payload = payload.parse(db).await?;
-    let store = Page::create(db, payload).await?;
-    Ok(store)
+pub async fn create(db: &PgPool, api_key: &ApiKey, payload: CreatePagePayload) -> anyhow::Result<Page, AppError> {
+    let mut tx = db.begin().await?;
+    let payload = payload
+        .parse(&mut tx)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let page = Page::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &page).await?;
+    tx.commit().await?;
+    Ok(page)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: CreatePagePayload,
+) -> anyhow::Result<Option<Page>, AppError> {
+    let id = PageId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let payload = payload
+        .parse(&mut tx)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let before = Page::get_by_id_for_update(&mut *tx, id).await?;
+    let page = Page::update(&mut *tx, id, payload).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), page.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(page)
 }
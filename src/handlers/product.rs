@@ -1,11 +1,18 @@
-use sqlx::PgPool;
+use serde::Deserialize;
+use sqlx::{PgConnection, PgPool};
+use utoipa::ToSchema;
 
-use crate::error::AppError;
+use crate::error::{is_unique_violation, AppError};
+use crate::images::{ImageStore, StoredImage};
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::{AuditAction, AuditLog, Audited};
+use crate::models::category::{CategoryFilter, CategoryId};
 use crate::models::product::{CreateProductPayload, Product, ProductId};
 
 #[tracing::instrument(skip_all)]
-pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Product>>, AppError> {
-    let products = Product::get_all(db).await?;
+pub async fn get_all(db: &PgPool, filter: CategoryFilter) -> anyhow::Result<Option<Vec<Product>>, AppError> {
+    let category = filter.parse(db).await?;
+    let products = Product::get_all(db, category).await?;
     Ok(products)
 }
 
@@ -17,8 +24,136 @@ pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Product>, Ap
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn create(db: &PgPool, payload: CreateProductPayload) -> anyhow::Result<Product, AppError> {
-    let payload = payload.parse()?;
-    let product = Product::create(db, payload).await?;
+pub async fn create(db: &PgPool, api_key: &ApiKey, payload: CreateProductPayload) -> anyhow::Result<Product, AppError> {
+    let payload = payload.parse().map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let gtin = payload.normalized_gtin.clone();
+
+    let mut tx = db.begin().await?;
+    if let Some(gtin) = gtin.as_ref() {
+        if let Some(existing) = Product::get_by_gtin(&mut *tx, gtin).await? {
+            return Err(AppError::BadRequest(format!(
+                "product {} already has gtin {gtin}",
+                existing.id.inner()
+            )));
+        }
+    }
+
+    // another request may have created a product with the same gtin since it was checked
+    let product = match Product::create(&mut *tx, payload).await {
+        Ok(product) => product,
+        Err(e) if is_unique_violation(&e) => {
+            return Err(AppError::Conflict(match gtin {
+                Some(gtin) => format!("another product was just created with gtin {gtin}"),
+                None => e.to_string(),
+            }))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    AuditLog::created(&mut *tx, api_key, &product).await?;
+    tx.commit().await?;
     Ok(product)
 }
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MergeProductPayload {
+    /// The product to merge into, it's the one left afterwards.
+    pub into: i32,
+}
+
+/// Merges product `id` into another one, returning the product that is left.
+#[tracing::instrument(skip_all)]
+pub async fn merge(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: MergeProductPayload,
+) -> anyhow::Result<Product, AppError> {
+    let source = ProductId::new(db, id).await?;
+    let target = ProductId::new(db, payload.into).await?;
+
+    if source == target {
+        return Err(AppError::BadRequest("cannot merge a product into itself".into()));
+    }
+
+    let mut tx = db.begin().await?;
+    let before = (
+        Product::get_by_id_for_update(&mut *tx, source).await?,
+        Product::get_by_id_for_update(&mut *tx, target).await?,
+    );
+    let product = Product::merge(&mut tx, source, target).await?;
+    audit_merge(&mut tx, api_key, before, &product).await?;
+    tx.commit().await?;
+
+    Ok(product)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetCategoryPayload {
+    /// Removes the product from any category when null.
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<i32>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn set_category(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: SetCategoryPayload,
+) -> anyhow::Result<Option<Product>, AppError> {
+    let id = ProductId::new(db, id).await?;
+    let category = match payload.category_id {
+        Some(category) => Some(CategoryId::new(db, category).await?),
+        None => None,
+    };
+
+    let mut tx = db.begin().await?;
+    let before = Product::get_by_id_for_update(&mut *tx, id).await?;
+    let product = Product::set_category(&mut *tx, id, category).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), product.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(product)
+}
+
+/// Records a merge as the deletion of the merged product and an update of the one it was merged
+/// into. `before` holds both products as they were, in that order.
+pub async fn audit_merge(
+    conn: &mut PgConnection,
+    api_key: &ApiKey,
+    before: (Option<Product>, Option<Product>),
+    merged: &Product,
+) -> anyhow::Result<()> {
+    if let Some(source) = before.0 {
+        AuditLog::record(
+            &mut *conn,
+            api_key,
+            AuditAction::Delete,
+            Product::ENTITY,
+            Some(source.id.inner()),
+            Some(serde_json::to_value(&source)?),
+            None,
+        )
+        .await?;
+    }
+
+    if let Some(target) = before.1 {
+        AuditLog::updated(&mut *conn, api_key, &target, merged).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the downloaded image of product `id`, `None` while it has none.
+#[tracing::instrument(skip_all)]
+pub async fn image(db: &PgPool, images: &ImageStore, id: i32) -> anyhow::Result<Option<StoredImage>, AppError> {
+    let id = ProductId::new(db, id).await?;
+
+    let Some(hash) = Product::get_by_id(db, id).await?.and_then(|product| product.image_hash) else {
+        return Ok(None);
+    };
+
+    let image = images.load(&hash).await?;
+    Ok(image)
+}
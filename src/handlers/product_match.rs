@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+use crate::handlers::product::audit_merge;
+use crate::matching;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::{AuditAction, AuditLog, Audited};
+use crate::models::product::Product;
+use crate::models::product_match::{MatchStatus, ProductMatch, ProductMatchId};
+
+/// Set while a scan runs, each one compares every pair of products of a brand so they aren't
+/// run side by side.
+static SCANNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanReport {
+    /// How many suggestions were made, pairs suggested before are not counted.
+    pub suggested: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScanStarted {
+    /// Suggestions show up on `/product_matches` as the scan makes them.
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool, status: Option<String>) -> anyhow::Result<Vec<ProductMatch>, AppError> {
+    let status = status
+        .map(MatchStatus::try_from)
+        .transpose()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let matches = ProductMatch::get_all(db, status).await?;
+    Ok(matches)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<ProductMatch>, AppError> {
+    let id = ProductMatchId::new(db, id).await?;
+    let product_match = ProductMatch::get_by_id(db, id).await?;
+    Ok(product_match)
+}
+
+/// Merges the suggested product into the one it matched, returning the product that is left.
+#[tracing::instrument(skip_all)]
+pub async fn accept(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Product, AppError> {
+    let id = ProductMatchId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+
+    let product_match = pending(ProductMatch::get_by_id_for_update(&mut *tx, id).await?)?;
+    let before = products(&mut tx, &product_match).await?;
+    let product = Product::merge(&mut tx, product_match.product_id, product_match.matched_product_id).await?;
+    ProductMatch::review(&mut *tx, id, MatchStatus::Accepted).await?;
+
+    audit_merge(&mut tx, api_key, before, &product).await?;
+    if let Some(reviewed) = ProductMatch::get_by_id(&mut *tx, id).await? {
+        AuditLog::updated(&mut *tx, api_key, &product_match, &reviewed).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(product)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn reject(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Option<ProductMatch>, AppError> {
+    let id = ProductMatchId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let before = pending(ProductMatch::get_by_id_for_update(&mut *tx, id).await?)?;
+    ProductMatch::review(&mut *tx, id, MatchStatus::Rejected).await?;
+    let product_match = ProductMatch::get_by_id(&mut *tx, id).await?;
+    if let Some(after) = product_match.as_ref() {
+        AuditLog::updated(&mut *tx, api_key, &before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(product_match)
+}
+
+/// Starts a scan in the background, it takes as long as there are products to compare.
+#[tracing::instrument(skip_all)]
+pub async fn scan(db: &PgPool, api_key: &ApiKey) -> anyhow::Result<ScanStarted, AppError> {
+    if SCANNING.swap(true, Ordering::AcqRel) {
+        return Err(AppError::Conflict("a scan is already running".into()));
+    }
+
+    let db = db.clone();
+    let api_key = api_key.clone();
+    tokio::spawn(async move {
+        match run_scan(&db, &api_key).await {
+            Ok(report) => tracing::info!("match scan made {} suggestions", report.suggested),
+            Err(e) => tracing::error!("match scan failed: {e}"),
+        }
+        SCANNING.store(false, Ordering::Release);
+    });
+
+    Ok(ScanStarted { started_at: Utc::now() })
+}
+
+async fn run_scan(db: &PgPool, api_key: &ApiKey) -> anyhow::Result<ScanReport> {
+    let suggested = matching::scan(db).await?;
+    let report = ScanReport { suggested };
+
+    // suggestions are recorded as a whole, there can be thousands of them
+    if report.suggested > 0 {
+        let after = serde_json::to_value(&report)?;
+        AuditLog::record(
+            db,
+            api_key,
+            AuditAction::Create,
+            ProductMatch::ENTITY,
+            None,
+            None,
+            Some(after),
+        )
+        .await?;
+    }
+
+    Ok(report)
+}
+
+/// Both products of a match as they were before it's accepted.
+async fn products(
+    conn: &mut PgConnection,
+    product_match: &ProductMatch,
+) -> anyhow::Result<(Option<Product>, Option<Product>)> {
+    Ok((
+        Product::get_by_id_for_update(&mut *conn, product_match.product_id).await?,
+        Product::get_by_id_for_update(&mut *conn, product_match.matched_product_id).await?,
+    ))
+}
+
+/// Only pending suggestions can be reviewed, the others already were.
+fn pending(product_match: Option<ProductMatch>) -> anyhow::Result<ProductMatch, AppError> {
+    match product_match {
+        Some(product_match) if product_match.status == MatchStatus::Pending => Ok(product_match),
+        Some(product_match) => Err(AppError::BadRequest(format!(
+            "match {} was already {}",
+            product_match.id.inner(),
+            product_match.status.inner()
+        ))),
+        None => Err(AppError::BadRequest("match does not exist".into())),
+    }
+}
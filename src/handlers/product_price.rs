@@ -1,11 +1,19 @@
+use axum::body::{Body, Bytes};
+use futures::StreamExt;
+use serde::Deserialize;
 use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::ToSchema;
 
 use crate::error::AppError;
-use crate::models::product_price::{ProductPrice, ProductPriceId};
+use crate::models::category::CategoryFilter;
+use crate::models::product_price::{PriceHistoryFilter, ProductPrice, ProductPriceId};
 
 #[tracing::instrument(skip_all)]
-pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ProductPrice>>> {
-    let product_prices = ProductPrice::get_all(db).await?;
+pub async fn get_all(db: &PgPool, filter: CategoryFilter) -> anyhow::Result<Option<Vec<ProductPrice>>> {
+    let category = filter.parse(db).await?;
+    let product_prices = ProductPrice::get_all(db, category).await?;
     Ok(product_prices)
 }
 
@@ -15,3 +23,92 @@ pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<ProductPrice
     let product_price = ProductPrice::get_by_id(db, id).await?;
     Ok(product_price)
 }
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 9] = [
+    "id",
+    "productId",
+    "productName",
+    "brand",
+    "storeId",
+    "storeName",
+    "price",
+    "available",
+    "createdAt",
+];
+
+/// Streams the price history matching `filter` as `format`.
+///
+/// Rows are encoded as they come from the database and the buffer between both ends is bounded,
+/// so a slow client holds the query back instead of piling rows up in memory.
+#[tracing::instrument(skip_all)]
+pub fn export(db: PgPool, filter: PriceHistoryFilter, format: ExportFormat) -> Body {
+    let (tx, rx) = mpsc::channel::<anyhow::Result<Bytes>>(32);
+
+    tokio::spawn(async move {
+        if format == ExportFormat::Csv {
+            let header = encode_csv(|writer| writer.write_record(CSV_HEADER));
+            if tx.send(header).await.is_err() {
+                return;
+            }
+        }
+
+        let mut rows = ProductPrice::stream_history(&db, &filter);
+
+        while let Some(row) = rows.next().await {
+            let chunk = row.and_then(|row| match format {
+                ExportFormat::Csv => encode_csv(|writer| writer.serialize(&row)),
+                ExportFormat::Ndjson => {
+                    let mut line = serde_json::to_vec(&row)?;
+                    line.push(b'\n');
+                    Ok(line.into())
+                }
+            });
+
+            if let Err(e) = &chunk {
+                tracing::error!("price history export failed: {e}");
+            }
+
+            // the client going away closes the channel, there's no one left to send rows to
+            let failed = chunk.is_err();
+            if tx.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+fn encode_csv(write: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>) -> anyhow::Result<Bytes> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(vec![]);
+    write(&mut writer)?;
+    let buffer = writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("failed to encode csv row: {e}"))?;
+    Ok(buffer.into())
+}
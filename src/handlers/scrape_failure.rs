@@ -0,0 +1,17 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::scrape_failure::{ScrapeFailure, ScrapeFailureId};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ScrapeFailure>>> {
+    let failures = ScrapeFailure::get_all(db).await?;
+    Ok(failures)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<ScrapeFailure>, AppError> {
+    let id = ScrapeFailureId::new(db, id).await?;
+    let failure = ScrapeFailure::get_by_id(db, id).await?;
+    Ok(failure)
+}
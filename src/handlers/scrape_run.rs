@@ -0,0 +1,17 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::scrape_run::{ScrapeRun, ScrapeRunId};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ScrapeRun>>> {
+    let runs = ScrapeRun::get_all(db).await?;
+    Ok(runs)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<ScrapeRun>, AppError> {
+    let id = ScrapeRunId::new(db, id).await?;
+    let run = ScrapeRun::get_by_id(db, id).await?;
+    Ok(run)
+}
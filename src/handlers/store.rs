@@ -1,6 +1,8 @@
 use sqlx::PgPool;
 
 use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
 use crate::models::store::{CreateStorePayload, Store, StoreId};
 
 #[tracing::instrument(skip_all)]
@@ -17,8 +19,11 @@ pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Store>, AppE
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn create(db: &PgPool, payload: CreateStorePayload) -> anyhow::Result<Store, AppError> {
+pub async fn create(db: &PgPool, api_key: &ApiKey, payload: CreateStorePayload) -> anyhow::Result<Store, AppError> {
     let payload = payload.parse()?;
-    let store = Store::create(db, payload).await?;
+    let mut tx = db.begin().await?;
+    let store = Store::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &store).await?;
+    tx.commit().await?;
     Ok(store)
 }
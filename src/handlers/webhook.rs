@@ -0,0 +1,99 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
+use crate::models::webhook::{CreateWebhookPayload, CreatedWebhook, Webhook, WebhookId};
+use crate::models::webhook_delivery::{WebhookDelivery, WebhookDeliveryId};
+
+#[tracing::instrument(skip_all)]
+pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Webhook>>> {
+    let webhooks = Webhook::get_all(db).await?;
+    Ok(webhooks)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_one(db: &PgPool, id: i32) -> anyhow::Result<Option<Webhook>, AppError> {
+    let id = WebhookId::new(db, id).await?;
+    let webhook = Webhook::get_by_id(db, id).await?;
+    Ok(webhook)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create(
+    db: &PgPool,
+    api_key: &ApiKey,
+    payload: CreateWebhookPayload,
+) -> anyhow::Result<CreatedWebhook, AppError> {
+    let payload = payload.parse()?;
+    let mut tx = db.begin().await?;
+    let webhook = Webhook::create(&mut *tx, payload).await?;
+    AuditLog::created(&mut *tx, api_key, &webhook.webhook).await?;
+    tx.commit().await?;
+    Ok(webhook)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    payload: CreateWebhookPayload,
+) -> anyhow::Result<Option<Webhook>, AppError> {
+    let id = WebhookId::new(db, id).await?;
+    let payload = payload.parse()?;
+    let mut tx = db.begin().await?;
+    let before = Webhook::get_by_id_for_update(&mut *tx, id).await?;
+    let webhook = Webhook::update(&mut *tx, id, payload).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), webhook.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(webhook)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete(db: &PgPool, api_key: &ApiKey, id: i32) -> anyhow::Result<Option<Webhook>, AppError> {
+    let id = WebhookId::new(db, id).await?;
+    let mut tx = db.begin().await?;
+    let before = Webhook::get_by_id_for_update(&mut *tx, id).await?;
+    let webhook = Webhook::delete(&mut *tx, id).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), webhook.as_ref()) {
+        AuditLog::deleted(&mut *tx, api_key, before, after).await?;
+    }
+
+    // the worker only sends to active webhooks, whatever was still queued would stay pending
+    WebhookDelivery::fail_pending(&mut *tx, id).await?;
+    tx.commit().await?;
+    Ok(webhook)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_deliveries(db: &PgPool, id: i32) -> anyhow::Result<Vec<WebhookDelivery>, AppError> {
+    let id = WebhookId::new(db, id).await?;
+    let deliveries = WebhookDelivery::get_by_webhook(db, id).await?;
+    Ok(deliveries)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn redeliver(
+    db: &PgPool,
+    api_key: &ApiKey,
+    id: i32,
+    delivery_id: i32,
+) -> anyhow::Result<Option<WebhookDelivery>, AppError> {
+    let id = WebhookId::new(db, id).await?;
+    let delivery_id = WebhookDeliveryId::new(db, delivery_id).await?;
+    if Webhook::get_by_id(db, id).await?.is_none() {
+        return Err(AppError::BadRequest(format!("webhook {} was deleted", id.inner())));
+    }
+
+    let mut tx = db.begin().await?;
+    let before = WebhookDelivery::get_by_id_for_update(&mut *tx, delivery_id).await?;
+    let delivery = WebhookDelivery::redeliver(&mut *tx, id, delivery_id).await?;
+    if let (Some(before), Some(after)) = (before.as_ref(), delivery.as_ref()) {
+        AuditLog::updated(&mut *tx, api_key, before, after).await?;
+    }
+    tx.commit().await?;
+    Ok(delivery)
+}
@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::ImagesConfig;
+use crate::models::product::Product;
+
+/// How many images are downloaded on each poll.
+const BATCH_SIZE: i64 = 20;
+/// Downloads that may work later are tried this many times before giving up on them.
+const MAX_ATTEMPTS: i32 = 5;
+/// Delay before the first retry, doubled on every attempt after it.
+const RETRY_DELAY: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Product images on disk, named after the sha256 of their content so the same image shared by
+/// many products, or downloaded twice, is only stored once.
+#[derive(Debug, Clone)]
+pub struct ImageStore {
+    dir: PathBuf,
+}
+
+/// Why an image couldn't be downloaded.
+#[derive(Debug)]
+enum DownloadError {
+    /// Trying again won't help, the host refused the image or it's not one that is kept.
+    Permanent(String),
+    /// The host may answer later, eg: it was down or timed out.
+    Transient(String),
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) if status.is_client_error() => DownloadError::Permanent(e.to_string()),
+            _ => DownloadError::Transient(e.to_string()),
+        }
+    }
+}
+
+/// An image read back from the store.
+#[derive(Debug)]
+pub struct StoredImage {
+    pub hash: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+impl ImageStore {
+    pub fn new(config: &ImagesConfig) -> Self {
+        Self {
+            dir: config.dir.clone(),
+        }
+    }
+
+    /// Images are spread over directories named after the first two characters of their hash,
+    /// so no directory ends up with every file.
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    /// Stores `bytes` and returns their hash, anything that isn't a known image format is
+    /// refused.
+    pub async fn save(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        image::guess_format(bytes).map_err(|_| anyhow::anyhow!("not an image"))?;
+
+        let hash = Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let path = self.path(&hash);
+
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(hash);
+        }
+
+        // written aside and renamed, so a file under its hash is always complete
+        let parent = path.parent().expect("image paths always have a parent");
+        tokio::fs::create_dir_all(parent).await?;
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        Ok(hash)
+    }
+
+    /// Reads the image stored under `hash`, `None` when there is no such file.
+    pub async fn load(&self, hash: &str) -> anyhow::Result<Option<StoredImage>> {
+        if hash.len() < 2 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("invalid image hash {hash}");
+        }
+
+        let bytes = match tokio::fs::read(self.path(hash)).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let content_type = image::guess_format(&bytes)
+            .map(|format| format.to_mime_type())
+            .unwrap_or("application/octet-stream");
+
+        Ok(Some(StoredImage {
+            hash: hash.to_string(),
+            content_type,
+            bytes,
+        }))
+    }
+}
+
+/// Downloads the images of products that have one until `shutdown` is cancelled. Scrapers only
+/// record where an image is, so a slow image host never holds up scraping.
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    db: PgPool,
+    config: ImagesConfig,
+    shutdown: CancellationToken,
+) -> anyhow::Result<JoinHandle<()>> {
+    let client = reqwest::Client::builder().timeout(config.timeout()).build()?;
+    let store = ImageStore::new(&config);
+
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.poll_interval());
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            if let Err(e) = download_pending(&db, &client, &store, config.max_bytes).await {
+                tracing::error!("failed to download product images: {e}");
+            }
+        }
+
+        tracing::info!("image downloader stopped");
+    });
+
+    Ok(handle)
+}
+
+async fn download_pending(
+    db: &PgPool,
+    client: &reqwest::Client,
+    store: &ImageStore,
+    max_bytes: usize,
+) -> anyhow::Result<()> {
+    for product in Product::get_pending_images(db, BATCH_SIZE).await? {
+        // Safety: pending products always have an image
+        let url = product.image.as_deref().unwrap();
+
+        match download(client, url, max_bytes).await {
+            Ok(bytes) => {
+                // failing to write it is not the image's fault, nothing is recorded then
+                let hash = store.save(&bytes).await?;
+                Product::set_image_hash(db, product.id, &hash).await?;
+            }
+            Err(DownloadError::Permanent(e)) => {
+                tracing::warn!("failed to download image of product {}: {e}", product.id.inner());
+                Product::set_image_error(db, product.id, &e).await?;
+            }
+            Err(DownloadError::Transient(e)) => {
+                let attempts = Product::schedule_image_retry(db, product.id, RETRY_DELAY).await?;
+                tracing::warn!(
+                    "failed to download image of product {} on attempt {attempts}: {e}",
+                    product.id.inner()
+                );
+
+                if attempts >= MAX_ATTEMPTS {
+                    Product::set_image_error(db, product.id, &e).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn download(client: &reqwest::Client, url: &str, max_bytes: usize) -> Result<Vec<u8>, DownloadError> {
+    let too_large = || DownloadError::Permanent(format!("image is larger than {max_bytes} bytes"));
+    let mut response = client.get(url).send().await?.error_for_status()?;
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+
+    // the length header may be missing or lie, so the body is checked as it arrives
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if image::guess_format(&bytes).is_err() {
+        return Err(DownloadError::Permanent("not an image".into()));
+    }
+
+    Ok(bytes)
+}
@@ -1,11 +1,13 @@
 #[macro_export]
 macro_rules! newtype_id {
     ($name:ident => $table:ident) => {
-        #[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+        #[derive(
+            Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, utoipa::ToSchema,
+        )]
         pub struct $name(i32);
 
         impl $name {
-            pub async fn new(db: &sqlx::PgPool, id: i32) -> anyhow::Result<Self> {
+            pub async fn new(db: impl sqlx::PgExecutor<'_>, id: i32) -> anyhow::Result<Self> {
                 let query = format!(
                     "SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)",
                     stringify!($table)
@@ -13,7 +15,11 @@ macro_rules! newtype_id {
                 let result: bool = sqlx::query_scalar(&query).bind(id).fetch_one(db).await?;
 
                 if !result {
-                    anyhow::bail!("invalid page id");
+                    return Err($crate::error::UnknownId {
+                        table: stringify!($table),
+                        id,
+                    }
+                    .into());
                 }
 
                 Ok(Self(id))
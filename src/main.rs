@@ -1,54 +1,305 @@
+mod anomaly;
+mod auth;
+mod chart;
+mod cli;
+mod config;
+mod digest;
 mod discord;
 mod error;
+mod events;
+mod gtin;
 mod handlers;
+mod images;
 mod macros;
+mod matching;
 mod models;
+mod notifier;
+mod retention;
 mod routers;
 mod scraper;
+mod shutdown;
+mod telemetry;
+mod webhooks;
+
+use std::future::IntoFuture;
 
 use anyhow::Context;
-use axum::{Extension, Router};
+use axum::{middleware, Extension, Router};
+use clap::Parser;
+use cli::{ApiKeyCommand, Cli, Command, ImportKind, ScrapeOnceArgs};
+use config::{AuthConfig, BrowserConfig, Config, DatabaseConfig, HttpConfig};
+use discord::BotStatus;
+use events::EventBus;
+use images::ImageStore;
+use metrics_exporter_prometheus::PrometheusHandle;
+use models::page::{CreatePagePayload, PageId};
+use models::product::CreateProductPayload;
+use models::store::{CreateStorePayload, StoreId};
+use routers::health::Readiness;
+use scraper::ScrapTarget;
+use shutdown::Shutdown;
+use sqlx::migrate::Migrator;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+/// Migrations embedded at build time, also used by the readiness probe to tell whether the
+/// database schema is up to date.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
     setup_tracing();
 
-    let db = setup_db().await?;
+    let config = config::load()?;
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(config).await,
+        Command::Serve => {
+            let metrics = telemetry::install()?;
+            let db = setup_db(&config.database).await?;
+            migrate(&db).await?;
+            let shutdown = Shutdown {
+                token: shutdown::listen(),
+                timeout: config.shutdown.timeout(),
+            };
+
+            serve(
+                &config.http,
+                app(
+                    db,
+                    BotStatus::default(),
+                    None,
+                    None,
+                    metrics,
+                    config.auth,
+                    ImageStore::new(&config.images),
+                ),
+                &shutdown,
+            )
+            .await
+        }
+        Command::ScrapeOnce(args) => scrape_once(config, args).await,
+        Command::Migrate => {
+            let db = setup_db(&config.database).await?;
+            migrate(&db).await?;
+            tracing::info!("database is up to date");
+            Ok(())
+        }
+        Command::Import { path } => {
+            let db = setup_db(&config.database).await?;
+            cli::data::import(&db, &path).await
+        }
+        Command::Export { output } => {
+            let db = setup_db(&config.database).await?;
+            cli::data::export(&db, output.as_deref()).await
+        }
+        Command::BulkImport { kind, path } => {
+            let db = setup_db(&config.database).await?;
+            match kind {
+                ImportKind::Stores => cli::data::bulk_import::<CreateStorePayload>(&db, &path).await,
+                ImportKind::Pages => cli::data::bulk_import::<CreatePagePayload>(&db, &path).await,
+                ImportKind::Products => cli::data::bulk_import::<CreateProductPayload>(&db, &path).await,
+            }
+        }
+        Command::ApiKey(command) => {
+            let db = setup_db(&config.database).await?;
+            match command {
+                ApiKeyCommand::Issue { name, scope } => cli::api_key::issue(&db, name, scope).await,
+                ApiKeyCommand::Revoke { id } => cli::api_key::revoke(&db, id).await,
+            }
+        }
+    }
+}
+
+async fn run(config: Config) -> anyhow::Result<()> {
+    let metrics = telemetry::install()?;
+    let db = setup_db(&config.database).await?;
+    migrate(&db).await?;
+    let bot_status = BotStatus::default();
+    let events = EventBus::default();
+    let shutdown = Shutdown {
+        token: shutdown::listen(),
+        timeout: config.shutdown.timeout(),
+    };
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let notifiers = notifier::NotifierBackend::from_config(&config.notifier, &config.discord)?;
+    let notifier_handle = notifier::start_thread(notifiers, rx).await?;
+    #[cfg(feature = "discord")]
+    let bot_handle =
+        discord::start_thread(db.clone(), bot_status.clone(), &config.discord, shutdown.token.clone()).await?;
+    let digest_handle = digest::start_thread(db.clone(), tx.clone(), shutdown.token.clone()).await?;
+    let webhooks_handle = webhooks::start_thread(db.clone(), config.webhooks, shutdown.token.clone()).await?;
+    let images = ImageStore::new(&config.images);
+    let images_handle = images::start_thread(db.clone(), config.images, shutdown.token.clone()).await?;
+    let retention_handle = retention::start_thread(db.clone(), config.retention, shutdown.token.clone()).await?;
+    let scraper_handle = scraper::start_thread(
+        db.clone(),
+        tx,
+        events.clone(),
+        config.scraper,
+        config.browser.clone(),
+        shutdown.clone(),
+    )
+    .await?;
+
+    let result = serve(
+        &config.http,
+        app(
+            db.clone(),
+            bot_status,
+            Some((events, shutdown.token.clone())),
+            Some(config.browser),
+            metrics,
+            config.auth,
+            images,
+        ),
+        &shutdown,
+    )
+    .await;
+
+    // the api may also stop on its own, every other task must follow it
+    shutdown.token.cancel();
+
+    scraper_handle.await?;
+    digest_handle.await?;
+    webhooks_handle.await?;
+    images_handle.await?;
+    retention_handle.await?;
+    #[cfg(feature = "discord")]
+    if let Some(bot_handle) = bot_handle {
+        bot_handle.await?;
+    }
+
+    // every sender is gone by now, so this returns once pending notifications are delivered
+    notifier_handle.await?;
+    db.close().await;
+
+    tracing::info!("shutdown complete");
+
+    result
+}
+
+async fn scrape_once(config: Config, args: ScrapeOnceArgs) -> anyhow::Result<()> {
+    let db = setup_db(&config.database).await?;
+    let shutdown = Shutdown {
+        token: shutdown::listen(),
+        timeout: config.shutdown.timeout(),
+    };
+
+    let target = match (args.page, args.store) {
+        (Some(page), _) => ScrapTarget::Page(PageId::new(&db, page).await?),
+        (_, Some(store)) => ScrapTarget::Store(StoreId::new(&db, store).await?),
+        (None, None) => anyhow::bail!("either --page or --store must be given"),
+    };
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let notifiers = notifier::NotifierBackend::from_config(&config.notifier, &config.discord)?;
+    let notifier_handle = notifier::start_thread(notifiers, rx).await?;
+
+    // nothing serves `/api/events` in this mode, run changes only reach the logs and the database
+    let events = EventBus::default();
+    let status = scraper::scrap_once(&db, &tx, &events, &config.scraper, &config.browser, target, &shutdown).await?;
+    tracing::info!("scrape run {}", status.inner());
+
+    // dropping the last sender lets the notifier finish delivering what is left and stop
+    drop(tx);
+    notifier_handle.await?;
+
+    Ok(())
+}
 
-    let api_routes = Router::new()
+/// Builds the http app, `events` and `browser` are given when this process also runs the scraper.
+/// Without a scraper nothing is ever published, so `/api/events` isn't served at all. The token
+/// given with `events` ends the event streams on shutdown.
+fn app(
+    db: PgPool,
+    bot_status: BotStatus,
+    events: Option<(EventBus, CancellationToken)>,
+    browser: Option<BrowserConfig>,
+    metrics: PrometheusHandle,
+    auth: AuthConfig,
+    images: ImageStore,
+) -> Router {
+    let mut api_routes = Router::new()
         .merge(routers::store::store_routes())
         .merge(routers::page::page_routes())
         .merge(routers::product::product_routes())
-        .merge(routers::product_price::product_price_routes());
+        .merge(routers::listing::listing_routes())
+        .merge(routers::category::category_routes())
+        .merge(routers::brand::brand_routes())
+        .merge(routers::product_price::product_price_routes())
+        .merge(routers::product_match::product_match_routes())
+        .merge(routers::notification_channel::notification_channel_routes())
+        .merge(routers::scrape_failure::scrape_failure_routes())
+        .merge(routers::scrape_run::scrape_run_routes())
+        .merge(routers::api_key::api_key_routes())
+        .merge(routers::audit_log::audit_log_routes())
+        .merge(routers::import::import_routes())
+        .merge(routers::webhook::webhook_routes())
+        .merge(routers::openapi::openapi_routes());
 
-    let app = Router::new().nest("/api", api_routes).layer(Extension(db.clone()));
+    if let Some((events, shutdown)) = events {
+        let events_routes = routers::events::events_routes()
+            .layer(Extension(events))
+            .layer(Extension(shutdown));
+        api_routes = api_routes.merge(events_routes);
+    }
 
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let api_routes = api_routes.layer(middleware::from_fn(auth::authenticate));
+
+    Router::new()
+        .nest("/api", api_routes)
+        .merge(routers::health::health_routes())
+        .merge(routers::metrics::metrics_routes())
+        .layer(middleware::from_fn(telemetry::track_http))
+        .layer(Extension(db))
+        .layer(Extension(bot_status))
+        .layer(Extension(Readiness { browser }))
+        .layer(Extension(metrics))
+        .layer(Extension(auth))
+        .layer(Extension(images))
+}
+
+/// Serves `app` until `shutdown` is cancelled, then gives in-flight requests until the shutdown
+/// deadline to finish.
+async fn serve(config: &HttpConfig, app: Router, shutdown: &Shutdown) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(config.address)
+        .await
+        .with_context(|| format!("failed to bind to {}", config.address))?;
 
-    discord::start_thread(rx).await?;
-    scraper::start_thread(db).await?;
+    let server = axum::serve(listener, app).with_graceful_shutdown(shutdown.token.clone().cancelled_owned());
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3333").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    tokio::select! {
+        result = server.into_future() => result?,
+        _ = shutdown::deadline(shutdown.token.clone(), shutdown.timeout) => {
+            tracing::warn!("requests still in flight after the shutdown deadline were dropped");
+        }
+    }
 
     Ok(())
 }
 
-async fn setup_db() -> anyhow::Result<PgPool> {
-    let db_url = dotenvy::var("DATABASE_URL").context("DATABASE_URL env var must be set")?;
+async fn setup_db(config: &DatabaseConfig) -> anyhow::Result<PgPool> {
     let db = PgPoolOptions::new()
-        .max_connections(20)
-        .connect(&db_url)
+        .max_connections(config.max_connections)
+        .connect(&config.url)
         .await
-        .context("failed to connect to DATABASE_URL")?;
-
-    sqlx::migrate!().run(&db).await.context("failed to run migrations")?;
+        .context("failed to connect to the database")?;
 
     Ok(db)
 }
 
+/// Only commands that keep running apply migrations, one-off commands expect the schema to be
+/// up to date already so they never change it behind the back of a running server.
+async fn migrate(db: &PgPool) -> anyhow::Result<()> {
+    MIGRATOR.run(db).await.context("failed to run migrations")
+}
+
 fn setup_tracing() {
     tracing_subscriber::fmt()
         .pretty()
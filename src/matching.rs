@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+
+use sqlx::PgPool;
+
+use crate::models::product::{Product, ValidCreateProductPayload};
+use crate::models::product_match::{MatchMethod, ProductMatch};
+
+/// Names at least this similar are suggested as the same product.
+const NAME_THRESHOLD: f64 = 0.6;
+/// A shared model number is only trusted when the names are at least this similar, model
+/// extraction is a heuristic and can pick up things like memory types.
+const MODEL_THRESHOLD: f64 = 0.35;
+/// Model matches are only linked without review when the names are the same, trigram-wise. The
+/// model number may still be the same for different variants, eg: colors or kits.
+const LINK_THRESHOLD: f64 = 1.0;
+
+/// Measures that look like model numbers, eg: `12GB` or `650W`.
+const UNITS: &[&str] = &[
+    "GB", "TB", "MB", "MHZ", "GHZ", "HZ", "W", "MM", "CM", "V", "MAH", "RPM", "DPI", "FPS", "K", "P", "MBPS",
+];
+/// Technology names shared by many products of the same brand.
+const GENERIC_TOKENS: &[&str] = &[
+    "GDDR", "DDR", "PCIE", "USB", "HDMI", "NVME", "SATA", "WIFI", "LGA", "AM",
+];
+
+/// Another product that looks like the same one.
+#[derive(Debug)]
+pub struct Candidate {
+    pub product: Product,
+    pub method: MatchMethod,
+    pub score: f64,
+}
+
+/// Picks the token of `name` most likely to be a model number: the longest one mixing letters
+/// and digits that isn't a measure or a technology name, eg: `DUAL-RTX4070-O12G`.
+///
+/// Slashes are kept since they often set variants apart, eg: `KF432C16BB/8` and `KF432C16BB/16`
+/// are kits of different capacities.
+pub fn extract_model(name: &str) -> Option<String> {
+    name.split(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')' | '[' | ']' | '|' | ';' | ':'))
+        .map(|token| {
+            token
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '/')
+                .collect::<String>()
+                .trim_matches('/')
+                .to_uppercase()
+        })
+        .filter(|token| token.len() >= 5)
+        .filter(|token| token.chars().any(|c| c.is_ascii_alphabetic()) && token.chars().any(|c| c.is_ascii_digit()))
+        .filter(|token| !is_measure(token) && !is_generic(token))
+        .max_by_key(|token| token.len())
+}
+
+fn is_measure(token: &str) -> bool {
+    let unit = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    UNITS.contains(&unit)
+}
+
+/// A technology name followed by its version, eg: `GDDR6X`, `PCIE40` or `LGA1700`.
+fn is_generic(token: &str) -> bool {
+    GENERIC_TOKENS.iter().any(|name| {
+        token
+            .strip_prefix(name)
+            .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == 'X' || c == 'E'))
+    })
+}
+
+/// Storage and memory sizes in `name`, eg: `16GB` or `1 TB`.
+fn capacities(name: &str) -> HashSet<String> {
+    let tokens = name
+        .to_uppercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut found = HashSet::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            continue;
+        }
+
+        let (size, unit) = token.split_at(digits);
+        let unit = match unit {
+            "" => tokens.get(i + 1).map(String::as_str).unwrap_or_default(),
+            unit => unit,
+        };
+
+        if matches!(unit, "GB" | "TB") {
+            found.insert(format!("{size}{unit}"));
+        }
+    }
+
+    found
+}
+
+/// Similarity between two product names from 0 to 1, as the jaccard index of their trigrams.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Trigrams of every word padded with spaces, the same way postgres' pg_trgm does it.
+fn trigrams(name: &str) -> HashSet<[char; 3]> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let chars = ["  ", word, " "].concat().chars().collect::<Vec<_>>();
+            chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Compares `name` with `other`, returning how they match if they do.
+fn compare(name: &str, model: Option<&str>, other: &str) -> Option<(MatchMethod, f64)> {
+    // the same line often comes in several sizes whose names only differ by it
+    let sizes = (capacities(name), capacities(other));
+    if !sizes.0.is_empty() && !sizes.1.is_empty() && sizes.0 != sizes.1 {
+        return None;
+    }
+
+    let score = name_similarity(name, other);
+
+    // names of sibling products, eg: a 4060 and a 4070 of the same line, are nearly the same, so
+    // different model numbers rule a match out
+    match (model, extract_model(other).as_deref()) {
+        (Some(model), Some(other)) if model != other => None,
+        (Some(_), Some(_)) if score >= MODEL_THRESHOLD => Some((MatchMethod::Model, score)),
+        _ if score >= NAME_THRESHOLD => Some((MatchMethod::Name, score)),
+        _ => None,
+    }
+}
+
+/// Whether a match is certain enough to skip the review queue.
+fn is_trusted(method: MatchMethod, score: f64) -> bool {
+    method == MatchMethod::Model && score >= LINK_THRESHOLD
+}
+
+/// Orders matches by confidence, model matches first.
+fn by_confidence(a: (MatchMethod, f64), b: (MatchMethod, f64)) -> std::cmp::Ordering {
+    let a = (a.0 == MatchMethod::Model, a.1);
+    let b = (b.0 == MatchMethod::Model, b.1);
+    a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// Products with different identifiers are never the same, whatever their names say.
+fn conflicting_gtin(a: Option<&str>, b: Option<&str>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+/// Finds the product of the same brand that best matches an offer.
+pub async fn find(
+    db: &PgPool,
+    name: &str,
+    brand: &str,
+    normalized_gtin: Option<&str>,
+) -> anyhow::Result<Option<Candidate>> {
+    let model = extract_model(name);
+
+    let candidate = Product::get_by_brand(db, brand)
+        .await?
+        .into_iter()
+        .filter(|product| !conflicting_gtin(normalized_gtin, product.normalized_gtin.as_deref()))
+        .filter_map(|product| {
+            compare(name, model.as_deref(), &product.name).map(|(method, score)| Candidate { product, method, score })
+        })
+        .max_by(|a, b| by_confidence((a.method, a.score), (b.method, b.score)));
+
+    Ok(candidate)
+}
+
+/// Links a scraped offer to the product it's the same as, creating one when there is none.
+///
+/// Called once the offer's gtin and url matched nothing. A model number match on the same name
+/// is trusted and the existing product is returned, any other match is created anyway and
+/// suggested as a match for someone to review.
+pub async fn link_or_create(db: &PgPool, payload: ValidCreateProductPayload) -> anyhow::Result<Product> {
+    let normalized_gtin = payload.normalized_gtin.as_ref().map(|gtin| gtin.inner().to_string());
+    let candidate = find(db, &payload.name, &payload.brand, normalized_gtin.as_deref()).await?;
+
+    match candidate {
+        Some(candidate) if is_trusted(candidate.method, candidate.score) => {
+            tracing::info!(
+                "linked {} to product {} by model number",
+                payload.name,
+                candidate.product.id.inner()
+            );
+            Ok(candidate.product)
+        }
+        Some(candidate) => {
+            let product = Product::create(db, payload).await?;
+            ProductMatch::suggest(db, product.id, candidate.product.id, candidate.method, candidate.score).await?;
+            Ok(product)
+        }
+        None => Product::create(db, payload).await,
+    }
+}
+
+/// Compares every pair of active products of the same brand, suggesting the newer of each
+/// matching pair be merged into the older one. Returns how many new suggestions were made.
+pub async fn scan(db: &PgPool) -> anyhow::Result<u64> {
+    let brands = sqlx::query_scalar!("SELECT DISTINCT LOWER(brand) FROM products WHERE active = true")
+        .fetch_all(db)
+        .await?;
+
+    let mut suggested = 0;
+
+    for brand in brands.into_iter().flatten() {
+        let products = Product::get_by_brand(db, &brand).await?;
+
+        for (i, newer) in products.iter().enumerate() {
+            let model = extract_model(&newer.name);
+
+            let best = products[..i]
+                .iter()
+                .filter(|older| !conflicting_gtin(newer.normalized_gtin.as_deref(), older.normalized_gtin.as_deref()))
+                .filter_map(|older| compare(&newer.name, model.as_deref(), &older.name).map(|found| (older, found)))
+                .max_by(|(_, a), (_, b)| by_confidence(*a, *b));
+
+            if let Some((older, (method, score))) = best {
+                if ProductMatch::suggest(db, newer.id, older.id, method, score).await? {
+                    suggested += 1;
+                }
+            }
+        }
+    }
+
+    Ok(suggested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_the_longest_model_like_token() {
+        assert_eq!(
+            extract_model("Placa de Vídeo ASUS Dual RTX 4070 OC, 12GB GDDR6X, DUAL-RTX4070-O12G").as_deref(),
+            Some("DUALRTX4070O12G")
+        );
+    }
+
+    #[test]
+    fn model_skips_measures_and_technology_names() {
+        assert_eq!(extract_model("Memória 16GB 3200MHz DDR4 PCIE40"), None);
+    }
+
+    #[test]
+    fn model_keeps_capacity_suffixes() {
+        assert_eq!(
+            extract_model("Memória Kingston Fury Beast, 8GB, 3200MHz, DDR4, KF432C16BB/8").as_deref(),
+            Some("KF432C16BB/8")
+        );
+        assert_ne!(
+            extract_model("Memória Kingston Fury Beast, 8GB, 3200MHz, DDR4, KF432C16BB/8"),
+            extract_model("Memória Kingston Fury Beast, 16GB, 3200MHz, DDR4, KF432C16BB/16")
+        );
+    }
+
+    #[test]
+    fn finds_capacities_with_or_without_a_space() {
+        let found = capacities("SSD 1 TB, Cache 512GB, 7000MB/s");
+        assert_eq!(found, HashSet::from(["1TB".to_string(), "512GB".to_string()]));
+    }
+
+    #[test]
+    fn same_names_are_fully_similar() {
+        assert_eq!(name_similarity("Mouse Gamer G203", "mouse gamer g203"), 1.0);
+        assert_eq!(name_similarity("Mouse", ""), 0.0);
+    }
+
+    #[test]
+    fn different_capacities_never_match() {
+        let name = "SSD Kingston NV2 500GB M.2 NVMe SNV2S500G";
+        let other = "SSD Kingston NV2 1TB M.2 NVMe SNV2S500G";
+        assert_eq!(compare(name, extract_model(name).as_deref(), other), None);
+    }
+
+    #[test]
+    fn different_models_never_match() {
+        let name = "Placa de Vídeo ASUS Dual RTX 4070 OC, 12GB, DUAL-RTX4070-O12G";
+        let other = "Placa de Vídeo ASUS Dual RTX 4060 OC, 12GB, DUAL-RTX4060-O12G";
+        assert_eq!(compare(name, extract_model(name).as_deref(), other), None);
+    }
+
+    #[test]
+    fn shared_model_matches_below_the_name_threshold() {
+        let name = "Placa de Vídeo ASUS Dual RTX 4070 OC, 12GB, DUAL-RTX4070-O12G";
+        let other = "ASUS DUAL-RTX4070-O12G";
+        let (method, score) = compare(name, extract_model(name).as_deref(), other).unwrap();
+        assert_eq!(method, MatchMethod::Model);
+        assert!(score < NAME_THRESHOLD);
+    }
+
+    #[test]
+    fn similar_names_without_models_match_by_name() {
+        let name = "Cadeira Gamer Elements Veda Preta";
+        let other = "Cadeira Gamer Elements Veda, Preta";
+        assert_eq!(
+            compare(name, extract_model(name).as_deref(), other),
+            Some((MatchMethod::Name, 1.0))
+        );
+    }
+
+    #[test]
+    fn only_model_matches_on_the_same_name_are_trusted() {
+        assert!(is_trusted(MatchMethod::Model, 1.0));
+        assert!(!is_trusted(MatchMethod::Model, 0.9));
+        assert!(!is_trusted(MatchMethod::Name, 1.0));
+    }
+}
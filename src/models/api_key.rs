@@ -0,0 +1,248 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::newtype_id;
+
+newtype_id! {
+    ApiKeyId => api_keys
+}
+
+const KEY_PREFIX: &str = "promor_";
+
+/// What an api key is allowed to do, every scope also grants the ones before it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum ApiKeyScope {
+    Read,
+    Write,
+    /// Can also issue and revoke api keys.
+    Admin,
+}
+
+impl ApiKeyScope {
+    pub fn inner(&self) -> &str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Write => "write",
+            ApiKeyScope::Admin => "admin",
+        }
+    }
+}
+
+impl TryFrom<String> for ApiKeyScope {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "admin" => Ok(Self::Admin),
+            _ => anyhow::bail!("invalid api key scope"),
+        }
+    }
+}
+
+/// A key allowed to call the API. Only a hash of the key is stored, the key itself is shown
+/// once when issued.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub name: String,
+    /// First characters of the key, enough to tell keys apart without exposing them.
+    pub prefix: String,
+    pub scope: ApiKeyScope,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ApiKeyRow {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    // only ever compared on the database, it's dropped when converting into an ApiKey
+    #[allow(dead_code)]
+    pub key_hash: String,
+    pub scope: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(value: ApiKeyRow) -> Self {
+        Self {
+            id: ApiKeyId::new_unchecked(value.id),
+            name: value.name,
+            prefix: value.prefix,
+            scope: ApiKeyScope::try_from(value.scope).expect("invalid api key scope on the database"),
+            last_used_at: value.last_used_at,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// A freshly issued key, the only time the plain key is available.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateApiKeyPayload {
+    #[validate(length(min = 1, max = 100, message = "name must have between 1 and 100 characters"))]
+    pub name: String,
+    pub scope: String,
+}
+
+#[derive(Debug)]
+pub struct ValidCreateApiKeyPayload {
+    pub name: String,
+    pub scope: ApiKeyScope,
+}
+
+impl CreateApiKeyPayload {
+    pub fn parse(self) -> anyhow::Result<ValidCreateApiKeyPayload> {
+        self.validate()?;
+
+        Ok(ValidCreateApiKeyPayload {
+            name: self.name,
+            scope: ApiKeyScope::try_from(self.scope)?,
+        })
+    }
+}
+
+/// Keys are random with 256 bits of entropy, so a plain sha256 is enough to store them, unlike
+/// passwords they can't be guessed from a dictionary.
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+impl ApiKey {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ApiKey>>> {
+        let result = sqlx::query_as!(
+            ApiKeyRow,
+            "SELECT * FROM api_keys WHERE active = true ORDER BY created_at DESC"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(keys) => Ok(Some(keys.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: ApiKeyId) -> anyhow::Result<Option<ApiKey>> {
+        let key = sqlx::query_as!(
+            ApiKeyRow,
+            "SELECT * FROM api_keys WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(key)
+    }
+
+    /// Same as [`ApiKey::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: ApiKeyId) -> anyhow::Result<Option<ApiKey>> {
+        let key = sqlx::query_as!(
+            ApiKeyRow,
+            "SELECT * FROM api_keys WHERE id = $1 AND active = true FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(key)
+    }
+
+    /// Looks up the active key matching `key`, recording that it was used.
+    pub async fn authenticate(db: &PgPool, key: &str) -> anyhow::Result<Option<ApiKey>> {
+        let key = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE key_hash = $1 AND active = true
+            RETURNING *
+            "#,
+            hash_key(key)
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(key)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, payload: ValidCreateApiKeyPayload) -> anyhow::Result<IssuedApiKey> {
+        let secret = rand::random::<[u8; 32]>()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let key = format!("{KEY_PREFIX}{secret}");
+        let prefix = key[..KEY_PREFIX.len() + 8].to_string();
+
+        let api_key = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            INSERT INTO api_keys (name, prefix, key_hash, scope)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            payload.name,
+            prefix,
+            hash_key(&key),
+            payload.scope.inner(),
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(IssuedApiKey { api_key, key })
+    }
+
+    pub async fn revoke(db: impl PgExecutor<'_>, id: ApiKeyId) -> anyhow::Result<Option<ApiKey>> {
+        let key = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            UPDATE api_keys
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(key)
+    }
+}
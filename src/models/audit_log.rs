@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use utoipa::{IntoParams, ToSchema};
+
+use super::api_key::{ApiKey, ApiKeyId};
+use super::brand::Brand;
+use super::category::Category;
+use super::notification_channel::NotificationChannel;
+use super::page::Page;
+use super::product::Product;
+use super::product_match::ProductMatch;
+use super::store::Store;
+use super::webhook::Webhook;
+use super::webhook_delivery::WebhookDelivery;
+use crate::newtype_id;
+
+newtype_id! {
+    AuditLogId => audit_log
+}
+
+/// How many entries are returned when no limit is given.
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// Records are only ever soft deleted, so the entry has both what it was and what is left.
+    Delete,
+}
+
+impl AuditAction {
+    pub fn inner(&self) -> &str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+impl TryFrom<String> for AuditAction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "create" => Ok(Self::Create),
+            "update" => Ok(Self::Update),
+            "delete" => Ok(Self::Delete),
+            _ => anyhow::bail!("invalid audit action"),
+        }
+    }
+}
+
+/// A change made through the API, with the record as it was before and after it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditLog {
+    pub id: AuditLogId,
+    #[serde(rename = "apiKeyId")]
+    pub api_key_id: ApiKeyId,
+    pub action: AuditAction,
+    /// Table of the changed record, eg: `products`.
+    pub entity: String,
+    /// Missing for changes that touch many records at once.
+    #[serde(rename = "entityId")]
+    pub entity_id: Option<i32>,
+    /// Missing when the record was created.
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AuditLogRow {
+    pub id: i32,
+    pub api_key_id: i32,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<i32>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditLogRow> for AuditLog {
+    fn from(value: AuditLogRow) -> Self {
+        Self {
+            id: AuditLogId::new_unchecked(value.id),
+            api_key_id: ApiKeyId::new_unchecked(value.api_key_id),
+            action: AuditAction::try_from(value.action).expect("invalid audit action on the database"),
+            entity: value.entity,
+            entity_id: value.entity_id,
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    /// Table of the changed records, eg: `products`.
+    pub entity: Option<String>,
+    #[serde(rename = "entityId")]
+    #[param(rename = "entityId")]
+    pub entity_id: Option<i32>,
+    #[serde(rename = "apiKeyId")]
+    #[param(rename = "apiKeyId")]
+    pub api_key_id: Option<i32>,
+    /// One of `create`, `update` or `delete`.
+    pub action: Option<String>,
+    /// Only changes made at or after this instant.
+    pub from: Option<DateTime<Utc>>,
+    /// Only changes made before this instant.
+    pub to: Option<DateTime<Utc>>,
+    /// At most 1000, 100 when missing.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct ValidAuditFilter {
+    pub entity: Option<String>,
+    pub entity_id: Option<i32>,
+    pub api_key_id: Option<ApiKeyId>,
+    pub action: Option<AuditAction>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+impl AuditFilter {
+    pub async fn parse(self, db: &PgPool) -> anyhow::Result<ValidAuditFilter> {
+        let api_key_id = match self.api_key_id {
+            Some(id) => Some(ApiKeyId::new(db, id).await?),
+            None => None,
+        };
+
+        Ok(ValidAuditFilter {
+            entity: self.entity,
+            entity_id: self.entity_id,
+            api_key_id,
+            action: self.action.map(AuditAction::try_from).transpose()?,
+            from: self.from,
+            to: self.to,
+            limit: self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        })
+    }
+}
+
+/// A record whose changes are kept on the audit log.
+pub trait Audited: Serialize {
+    /// Table the record is stored on.
+    const ENTITY: &'static str;
+
+    fn audit_id(&self) -> i32;
+}
+
+macro_rules! audited {
+    ($($model:ty => $entity:literal),* $(,)?) => {
+        $(
+            impl Audited for $model {
+                const ENTITY: &'static str = $entity;
+
+                fn audit_id(&self) -> i32 {
+                    self.id.inner()
+                }
+            }
+        )*
+    };
+}
+
+audited! {
+    ApiKey => "api_keys",
+    Brand => "brands",
+    Category => "categories",
+    NotificationChannel => "notification_channels",
+    Page => "pages",
+    Product => "products",
+    ProductMatch => "product_matches",
+    Store => "stores",
+    Webhook => "webhooks",
+    WebhookDelivery => "webhook_deliveries",
+}
+
+impl AuditLog {
+    /// Returns the most recent changes first.
+    pub async fn get_all(db: &PgPool, filter: ValidAuditFilter) -> anyhow::Result<Vec<AuditLog>> {
+        let entries = sqlx::query_as!(
+            AuditLogRow,
+            r#"
+            SELECT id, api_key_id, action, entity, entity_id, before, after, created_at
+            FROM audit_log
+            WHERE ($1::TEXT IS NULL OR entity = $1)
+            AND ($2::INT IS NULL OR entity_id = $2)
+            AND ($3::INT IS NULL OR api_key_id = $3)
+            AND ($4::TEXT IS NULL OR action = $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            filter.entity,
+            filter.entity_id,
+            filter.api_key_id.map(|id| id.inner()),
+            filter.action.as_ref().map(AuditAction::inner),
+            filter.from,
+            filter.to,
+            filter.limit,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(entries)
+    }
+
+    pub async fn get_by_id(db: &PgPool, id: AuditLogId) -> anyhow::Result<Option<AuditLog>> {
+        let entry = sqlx::query_as!(
+            AuditLogRow,
+            r#"
+            SELECT id, api_key_id, action, entity, entity_id, before, after, created_at
+            FROM audit_log
+            WHERE id = $1
+            "#,
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(entry)
+    }
+
+    /// Records a change to `entity`, see [`AuditLog::created`], [`AuditLog::updated`] and
+    /// [`AuditLog::deleted`] for the usual changes to a single record.
+    pub async fn record(
+        db: impl PgExecutor<'_>,
+        api_key: &ApiKey,
+        action: AuditAction,
+        entity: &str,
+        entity_id: Option<i32>,
+        before: Option<serde_json::Value>,
+        after: Option<serde_json::Value>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log (api_key_id, action, entity, entity_id, before, after)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            api_key.id.inner(),
+            action.inner(),
+            entity,
+            entity_id,
+            before,
+            after,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn created<T: Audited>(db: impl PgExecutor<'_>, api_key: &ApiKey, after: &T) -> anyhow::Result<()> {
+        Self::record(
+            db,
+            api_key,
+            AuditAction::Create,
+            T::ENTITY,
+            Some(after.audit_id()),
+            None,
+            Some(serde_json::to_value(after)?),
+        )
+        .await
+    }
+
+    pub async fn updated<T: Audited>(
+        db: impl PgExecutor<'_>,
+        api_key: &ApiKey,
+        before: &T,
+        after: &T,
+    ) -> anyhow::Result<()> {
+        Self::changed(db, api_key, AuditAction::Update, before, after).await
+    }
+
+    pub async fn deleted<T: Audited>(
+        db: impl PgExecutor<'_>,
+        api_key: &ApiKey,
+        before: &T,
+        after: &T,
+    ) -> anyhow::Result<()> {
+        Self::changed(db, api_key, AuditAction::Delete, before, after).await
+    }
+
+    async fn changed<T: Audited>(
+        db: impl PgExecutor<'_>,
+        api_key: &ApiKey,
+        action: AuditAction,
+        before: &T,
+        after: &T,
+    ) -> anyhow::Result<()> {
+        Self::record(
+            db,
+            api_key,
+            action,
+            T::ENTITY,
+            Some(before.audit_id()),
+            Some(serde_json::to_value(before)?),
+            Some(serde_json::to_value(after)?),
+        )
+        .await
+    }
+}
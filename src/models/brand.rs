@@ -0,0 +1,509 @@
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::category::CategoryId;
+use crate::error::AppError;
+use crate::newtype_id;
+
+newtype_id! {
+    BrandId => brands
+}
+
+/// The canonical name of a manufacturer. Scraped names are resolved to a brand when products are
+/// created, comparing them ignoring case and anything that isn't a letter or a digit.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Brand {
+    pub id: BrandId,
+    pub name: String,
+    /// Other names stores use for the brand, eg: `ASUS TeK` for `ASUS`.
+    pub aliases: Vec<String>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct BrandRow {
+    pub id: i32,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<BrandRow> for Brand {
+    fn from(value: BrandRow) -> Self {
+        Self {
+            id: BrandId::new_unchecked(value.id),
+            name: value.name,
+            aliases: value.aliases,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// Prices of a brand's products, `min`, `avg` and `max` only look at the latest available price
+/// of every product on every store. Suspected anomalies are left out of all of them.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BrandStats {
+    #[serde(rename = "brandId")]
+    pub brand_id: BrandId,
+    pub products: i64,
+    /// Every price ever observed.
+    pub observations: i64,
+    #[serde(rename = "minPrice")]
+    pub min_price: Option<f64>,
+    #[serde(rename = "avgPrice")]
+    pub avg_price: Option<f64>,
+    #[serde(rename = "maxPrice")]
+    pub max_price: Option<f64>,
+    /// The lowest available price ever observed.
+    #[serde(rename = "lowestEver")]
+    pub lowest_ever: Option<f64>,
+    #[serde(rename = "lastObservedAt")]
+    pub last_observed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct BrandStatsRow {
+    products: i64,
+    observations: i64,
+    min_price: Option<BigDecimal>,
+    avg_price: Option<BigDecimal>,
+    max_price: Option<BigDecimal>,
+    lowest_ever: Option<BigDecimal>,
+    last_observed_at: Option<DateTime<Utc>>,
+}
+
+/// Used both to create and to replace a brand.
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateBrandPayload {
+    #[validate(length(min = 1, max = 100, message = "name of brand must have between 1 and 100 characters"))]
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ValidCreateBrandPayload {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+impl CreateBrandPayload {
+    pub fn parse(self) -> anyhow::Result<ValidCreateBrandPayload, AppError> {
+        self.validate().map_err(AppError::ValidationError)?;
+
+        let mut aliases = self
+            .aliases
+            .into_iter()
+            .map(|alias| alias.trim().to_string())
+            .filter(|alias| !alias.is_empty())
+            .collect::<Vec<_>>();
+        aliases.sort();
+        aliases.dedup();
+
+        Ok(ValidCreateBrandPayload {
+            name: self.name.trim().to_string(),
+            aliases,
+        })
+    }
+}
+
+impl Brand {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Brand>>> {
+        let result = sqlx::query_as!(BrandRow, "SELECT * FROM brands WHERE active = true ORDER BY name ASC")
+            .fetch_all(db)
+            .await;
+
+        match result {
+            Ok(brands) => Ok(Some(brands.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: BrandId) -> anyhow::Result<Option<Brand>> {
+        let brand = sqlx::query_as!(
+            BrandRow,
+            "SELECT * FROM brands WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(brand)
+    }
+
+    /// Same as [`Brand::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: BrandId) -> anyhow::Result<Option<Brand>> {
+        let brand = sqlx::query_as!(
+            BrandRow,
+            "SELECT * FROM brands WHERE id = $1 AND active = true FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(brand)
+    }
+
+    /// Returns another brand already known by any of `names`, as its name or as an alias.
+    pub async fn get_conflicting(
+        db: &PgPool,
+        names: &[String],
+        except: Option<BrandId>,
+    ) -> anyhow::Result<Option<Brand>> {
+        let brand = sqlx::query_as!(
+            BrandRow,
+            r#"
+            SELECT * FROM brands
+            WHERE active = true
+            AND ($2::INT IS NULL OR id <> $2)
+            AND EXISTS (
+                SELECT 1 FROM unnest(array_append(aliases, name)) AS known, unnest($1::TEXT[]) AS given
+                WHERE brand_key(known) = brand_key(given)
+            )
+            LIMIT 1
+            "#,
+            names,
+            except.map(|id| id.inner()),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(brand)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, payload: ValidCreateBrandPayload) -> anyhow::Result<Brand> {
+        let brand = sqlx::query_as!(
+            BrandRow,
+            r#"
+            INSERT INTO brands (name, aliases)
+            VALUES ($1, $2)
+            RETURNING *
+            "#,
+            payload.name,
+            &payload.aliases,
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(brand)
+    }
+
+    /// Replaces a brand, its products are renamed along with it.
+    ///
+    /// Meant to run inside a transaction, so the brand is never left with a different name than
+    /// its products.
+    pub async fn update(
+        conn: &mut PgConnection,
+        id: BrandId,
+        payload: ValidCreateBrandPayload,
+    ) -> anyhow::Result<Option<Brand>> {
+        let brand: Option<Brand> = sqlx::query_as!(
+            BrandRow,
+            r#"
+            UPDATE brands
+            SET name = $2, aliases = $3
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner(),
+            payload.name,
+            &payload.aliases,
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(Into::into);
+
+        if let Some(brand) = brand.as_ref() {
+            sqlx::query!(
+                "UPDATE products SET brand = $2 WHERE brand_id = $1 AND brand <> $2",
+                id.inner(),
+                brand.name
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(brand)
+    }
+
+    /// Merges `source` into `target`: the name and aliases of `source` become aliases of
+    /// `target`, its products and the channels subscribed to it are moved to `target` and
+    /// `source` is deleted. Returns `None` when either brand doesn't exist.
+    ///
+    /// Meant to run inside a transaction, so a merge that fails half way leaves nothing behind.
+    pub async fn merge(conn: &mut PgConnection, source: BrandId, target: BrandId) -> anyhow::Result<Option<Brand>> {
+        if source == target {
+            anyhow::bail!("cannot merge brand {} into itself", source.inner());
+        }
+
+        let source = sqlx::query_as!(
+            BrandRow,
+            r#"
+            UPDATE brands
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            source.inner()
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(source) = source else {
+            return Ok(None);
+        };
+        let source_names = [vec![source.name], source.aliases].concat();
+
+        // names that only differ from another in case or punctuation are kept once
+        let target: Option<Brand> = sqlx::query_as!(
+            BrandRow,
+            r#"
+            UPDATE brands
+            SET aliases = ARRAY(
+                SELECT alias FROM (
+                    SELECT DISTINCT ON (brand_key(alias)) alias
+                    FROM unnest(aliases || $2::TEXT[]) AS alias
+                    WHERE brand_key(alias) NOT IN ('', brand_key(name))
+                    ORDER BY brand_key(alias), alias
+                ) AS merged
+                ORDER BY alias
+            )
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            target.inner(),
+            &source_names,
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(Into::into);
+        let Some(target) = target else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            "UPDATE products SET brand_id = $2, brand = $3 WHERE brand_id = $1",
+            source.id,
+            target.id.inner(),
+            target.name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // channels filter on the name products have, a channel that would end up subscribed
+        // twice with the same filters keeps its subscription to the target, or the oldest one
+        sqlx::query!(
+            r#"
+            UPDATE notification_channels nc
+            SET active = false, deleted_at = NOW()
+            WHERE nc.active = true
+            AND brand_key(nc.brand) IN (SELECT brand_key(name) FROM unnest($1::TEXT[]) AS name)
+            AND EXISTS (
+                SELECT 1 FROM notification_channels other
+                WHERE other.active = true
+                AND other.id <> nc.id
+                AND other.channel_id = nc.channel_id
+                AND other.store_id IS NOT DISTINCT FROM nc.store_id
+                AND other.product_id IS NOT DISTINCT FROM nc.product_id
+                AND other.digest IS NOT DISTINCT FROM nc.digest
+                AND (
+                    LOWER(other.brand) = LOWER($2)
+                    OR other.id < nc.id
+                    AND brand_key(other.brand) IN (SELECT brand_key(name) FROM unnest($1::TEXT[]) AS name)
+                )
+            )
+            "#,
+            &source_names,
+            target.name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE notification_channels
+            SET brand = $2
+            WHERE active = true
+            AND brand_key(brand) IN (SELECT brand_key(name) FROM unnest($1::TEXT[]) AS name)
+            "#,
+            &source_names,
+            target.name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(Some(target))
+    }
+
+    /// Deletes a brand, its products keep its name but no longer refer to it. A product created
+    /// later with the same name starts a new brand.
+    ///
+    /// Meant to run inside a transaction, so no product is left referring to a deleted brand.
+    pub async fn delete(conn: &mut PgConnection, id: BrandId) -> anyhow::Result<Option<Brand>> {
+        let brand = sqlx::query_as!(
+            BrandRow,
+            r#"
+            UPDATE brands
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner()
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(Into::into);
+
+        sqlx::query!("UPDATE products SET brand_id = NULL WHERE brand_id = $1", id.inner())
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(brand)
+    }
+
+    pub async fn get_stats(db: &PgPool, id: BrandId, category: Option<CategoryId>) -> anyhow::Result<BrandStats> {
+        let stats = sqlx::query_as!(
+            BrandStatsRow,
+            r#"
+            WITH brand_products AS (
+                SELECT id FROM products
+                WHERE brand_id = $1 AND active = true
+                AND ($2::INT IS NULL OR category_id IN (SELECT category_subtree($2)))
+            ), prices AS (
+                SELECT * FROM product_prices
+                WHERE product_id IN (SELECT id FROM brand_products) AND anomaly IS DISTINCT FROM 'suspected'
+            ), latest AS (
+                SELECT DISTINCT ON (product_id, store_id) price, available
+                FROM prices
+                ORDER BY product_id, store_id, created_at DESC
+            )
+            SELECT
+                (SELECT COUNT(*) FROM brand_products) AS "products!",
+                (SELECT COUNT(*) FROM prices) AS "observations!",
+                (SELECT MIN(price) FROM latest WHERE available = true) AS min_price,
+                (SELECT AVG(price) FROM latest WHERE available = true) AS avg_price,
+                (SELECT MAX(price) FROM latest WHERE available = true) AS max_price,
+                (SELECT MIN(price) FROM prices WHERE available = true) AS lowest_ever,
+                (SELECT MAX(created_at) FROM prices) AS last_observed_at
+            "#,
+            id.inner(),
+            category.map(|id| id.inner()),
+        )
+        .fetch_one(db)
+        .await?;
+
+        let price = |value: Option<BigDecimal>| value.and_then(|value| value.to_f64());
+
+        Ok(BrandStats {
+            brand_id: id,
+            products: stats.products,
+            observations: stats.observations,
+            min_price: price(stats.min_price),
+            avg_price: price(stats.avg_price).map(|avg| (avg * 100.0).round() / 100.0),
+            max_price: price(stats.max_price),
+            lowest_ever: price(stats.lowest_ever),
+            last_observed_at: stats.last_observed_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn subscribe(db: &PgPool, channel_id: i64, brand: &str) -> anyhow::Result<i32> {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO notification_channels (guild_id, channel_id, brand) VALUES (1, $1, $2) RETURNING id",
+            channel_id,
+            brand,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn create(db: &PgPool, name: &str, aliases: &[&str]) -> anyhow::Result<BrandId> {
+        let payload = ValidCreateBrandPayload {
+            name: name.into(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        };
+
+        Ok(Brand::create(db, payload).await?.id)
+    }
+
+    #[sqlx::test]
+    async fn merge_moves_channels_to_the_target_name(db: PgPool) -> anyhow::Result<()> {
+        let target = create(&db, "ASUS", &[]).await?;
+        let source = create(&db, "ASUS TeK", &["ASUSTeK Computer"]).await?;
+
+        // channel 10 is subscribed to both, channel 20 to the source by two of its names
+        let kept = subscribe(&db, 10, "asus").await?;
+        let duplicate = subscribe(&db, 10, "ASUS TeK").await?;
+        let oldest = subscribe(&db, 20, "asus tek").await?;
+        let newest = subscribe(&db, 20, "ASUSTeK Computer").await?;
+        let moved = subscribe(&db, 30, "ASUS TeK").await?;
+
+        let mut tx = db.begin().await?;
+        let merged = Brand::merge(&mut tx, source, target).await?.expect("both brands exist");
+        tx.commit().await?;
+        assert_eq!(merged.aliases, vec!["ASUS TeK", "ASUSTeK Computer"]);
+
+        let channels = sqlx::query!("SELECT id, brand, active FROM notification_channels ORDER BY id")
+            .fetch_all(&db)
+            .await?
+            .into_iter()
+            .map(|row| (row.id, row.brand.unwrap_or_default(), row.active))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            channels,
+            vec![
+                (kept, "asus".into(), true),
+                (duplicate, "ASUS TeK".into(), false),
+                (oldest, "ASUS".into(), true),
+                (newest, "ASUSTeK Computer".into(), false),
+                (moved, "ASUS".into(), true),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn merge_with_deleted_brand_does_nothing(db: PgPool) -> anyhow::Result<()> {
+        let target = create(&db, "ASUS", &[]).await?;
+        let source = create(&db, "ASUS TeK", &[]).await?;
+        Brand::delete(&mut *db.acquire().await?, target).await?;
+
+        let mut tx = db.begin().await?;
+        assert!(Brand::merge(&mut tx, source, target).await?.is_none());
+        tx.rollback().await?;
+
+        assert!(Brand::get_by_id(&db, source).await?.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::error::AppError;
+use crate::newtype_id;
+
+newtype_id! {
+    CategoryId => categories
+}
+
+/// Groups products that are worth comparing, eg: GPUs. Categories nest, so a filter on one also
+/// matches everything in the categories below it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Category {
+    pub id: CategoryId,
+    pub name: String,
+    pub slug: String,
+    /// Missing on top level categories.
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<CategoryId>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct CategoryRow {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<i32>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<CategoryRow> for Category {
+    fn from(value: CategoryRow) -> Self {
+        Self {
+            id: CategoryId::new_unchecked(value.id),
+            name: value.name,
+            slug: value.slug,
+            parent_id: value.parent_id.map(CategoryId::new_unchecked),
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// Narrows a listing down to one category, subcategories included.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryFilter {
+    #[serde(rename = "categoryId")]
+    #[param(rename = "categoryId")]
+    pub category_id: Option<i32>,
+}
+
+impl CategoryFilter {
+    pub async fn parse(&self, db: &PgPool) -> anyhow::Result<Option<CategoryId>> {
+        match self.category_id {
+            Some(id) => Ok(Some(CategoryId::new(db, id).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Used both to create and to replace a category.
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateCategoryPayload {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "name of category must have between 1 and 100 characters"
+    ))]
+    pub name: String,
+    /// Lowercase letters, digits and dashes, made from `name` when missing.
+    #[validate(custom(function = "validate_slug"))]
+    pub slug: Option<String>,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug)]
+pub struct ValidCreateCategoryPayload {
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<CategoryId>,
+}
+
+fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    let valid = !slug.is_empty()
+        && slug.len() <= 100
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    match valid {
+        true => Ok(()),
+        false => Err(ValidationError::new("slug")
+            .with_message("slug must only have lowercase letters, digits and dashes".into())),
+    }
+}
+
+/// Makes a slug out of a name, eg: `Placas de Vídeo` becomes `placas-de-video`. Accents common
+/// in portuguese are dropped, any other character that isn't ascii is skipped.
+fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            'ñ' => 'n',
+            c if c.is_ascii_alphanumeric() => c,
+            c if c.is_alphabetic() => '\0',
+            _ => ' ',
+        })
+        .filter(|c| *c != '\0')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl CreateCategoryPayload {
+    pub async fn parse(self, conn: &mut PgConnection) -> anyhow::Result<ValidCreateCategoryPayload, AppError> {
+        self.validate().map_err(AppError::ValidationError)?;
+
+        let slug = self.slug.unwrap_or_else(|| slugify(&self.name));
+        if slug.is_empty() {
+            return Err(AppError::BadRequest(format!("cannot make a slug out of {}", self.name)));
+        }
+
+        let parent_id = match self.parent_id {
+            Some(id) => Some(CategoryId::new(&mut *conn, id).await?),
+            None => None,
+        };
+
+        Ok(ValidCreateCategoryPayload {
+            name: self.name,
+            slug,
+            parent_id,
+        })
+    }
+}
+
+impl Category {
+    /// Returns every category, parents before their children.
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Category>>> {
+        let result = sqlx::query_as!(
+            CategoryRow,
+            "SELECT * FROM categories WHERE active = true ORDER BY parent_id ASC NULLS FIRST, name ASC"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(categories) => Ok(Some(categories.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: CategoryId) -> anyhow::Result<Option<Category>> {
+        let category = sqlx::query_as!(
+            CategoryRow,
+            "SELECT * FROM categories WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(category)
+    }
+
+    /// Same as [`Category::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: CategoryId) -> anyhow::Result<Option<Category>> {
+        let category = sqlx::query_as!(
+            CategoryRow,
+            "SELECT * FROM categories WHERE id = $1 AND active = true FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(category)
+    }
+
+    pub async fn get_by_slug(db: impl PgExecutor<'_>, slug: &str) -> anyhow::Result<Option<Category>> {
+        let category = sqlx::query_as!(
+            CategoryRow,
+            "SELECT * FROM categories WHERE slug = $1 AND active = true",
+            slug
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(category)
+    }
+
+    /// Whether `other` is `id` or sits anywhere below it.
+    pub async fn contains(db: impl PgExecutor<'_>, id: CategoryId, other: CategoryId) -> anyhow::Result<bool> {
+        let contains = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM category_subtree($1) AS tree(id) WHERE tree.id = $2) AS "contains!""#,
+            id.inner(),
+            other.inner(),
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(contains)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, payload: ValidCreateCategoryPayload) -> anyhow::Result<Category> {
+        let category = sqlx::query_as!(
+            CategoryRow,
+            r#"
+            INSERT INTO categories (name, slug, parent_id)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            payload.name,
+            payload.slug,
+            payload.parent_id.map(|id| id.inner()),
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(category)
+    }
+
+    pub async fn update(
+        db: impl PgExecutor<'_>,
+        id: CategoryId,
+        payload: ValidCreateCategoryPayload,
+    ) -> anyhow::Result<Option<Category>> {
+        let category = sqlx::query_as!(
+            CategoryRow,
+            r#"
+            UPDATE categories
+            SET name = $2, slug = $3, parent_id = $4
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner(),
+            payload.name,
+            payload.slug,
+            payload.parent_id.map(|id| id.inner()),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(category)
+    }
+
+    /// Deletes a category, what was in it moves up to its parent so nothing loses its place in
+    /// the tree.
+    ///
+    /// Meant to run inside a transaction, so nothing is left in a deleted category.
+    pub async fn delete(conn: &mut PgConnection, id: CategoryId) -> anyhow::Result<Option<Category>> {
+        let category: Option<Category> = sqlx::query_as!(
+            CategoryRow,
+            r#"
+            UPDATE categories
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner()
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(Into::into);
+
+        let Some(category) = category else {
+            return Ok(None);
+        };
+
+        let parent_id = category.parent_id.map(|id| id.inner());
+
+        sqlx::query!(
+            "UPDATE categories SET parent_id = $2 WHERE parent_id = $1",
+            id.inner(),
+            parent_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE pages SET category_id = $2 WHERE category_id = $1",
+            id.inner(),
+            parent_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE products SET category_id = $2 WHERE category_id = $1",
+            id.inner(),
+            parent_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(Some(category))
+    }
+}
@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+
+use super::notification_channel::NotificationChannel;
+
+const DIGEST_SECTION_LIMIT: i64 = 10;
+
+/// A product whose price or availability changed on a store during the digest period.
+#[derive(Debug)]
+pub struct DigestEntry {
+    pub product: String,
+    pub store: String,
+    pub old_price: f64,
+    pub new_price: f64,
+}
+
+#[derive(Debug)]
+struct DigestEntryRow {
+    product: String,
+    store: String,
+    old_price: BigDecimal,
+    new_price: BigDecimal,
+}
+
+impl From<DigestEntryRow> for DigestEntry {
+    fn from(value: DigestEntryRow) -> Self {
+        Self {
+            product: value.product,
+            store: value.store,
+            old_price: value.old_price.to_f64().unwrap_or_default(),
+            new_price: value.new_price.to_f64().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DigestFailure {
+    pub store: String,
+    pub failures: i64,
+}
+
+/// Summary of what happened since `since`, restricted by the filters of a notification channel.
+#[derive(Debug)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub biggest_drops: Vec<DigestEntry>,
+    pub new_lows: Vec<DigestEntry>,
+    pub back_in_stock: Vec<DigestEntry>,
+    pub failures: Vec<DigestFailure>,
+}
+
+impl Digest {
+    pub async fn compile(db: &PgPool, channel: &NotificationChannel, since: DateTime<Utc>) -> anyhow::Result<Digest> {
+        let store_id = channel.store_id.map(|id| id.inner());
+        let product_id = channel.product_id.map(|id| id.inner());
+        let brand = channel.brand.as_deref();
+
+        // compares the last price seen before the period with the latest one inside of it
+        let biggest_drops = sqlx::query_as!(
+            DigestEntryRow,
+            r#"
+            WITH latest AS (
+                SELECT DISTINCT ON (product_id, store_id) product_id, store_id, price
+                FROM product_prices
+                WHERE created_at >= $1 AND anomaly IS DISTINCT FROM 'suspected'
+                ORDER BY product_id, store_id, created_at DESC
+            ), previous AS (
+                SELECT DISTINCT ON (product_id, store_id) product_id, store_id, price
+                FROM product_prices
+                WHERE created_at < $1 AND anomaly IS DISTINCT FROM 'suspected'
+                ORDER BY product_id, store_id, created_at DESC
+            )
+            SELECT
+                products.name AS "product!",
+                stores.name AS "store!",
+                previous.price AS "old_price!",
+                latest.price AS "new_price!"
+            FROM latest
+            JOIN previous USING (product_id, store_id)
+            JOIN products ON products.id = latest.product_id
+            JOIN stores ON stores.id = latest.store_id
+            WHERE latest.price < previous.price
+            AND ($2::INT IS NULL OR latest.store_id = $2)
+            AND ($3::INT IS NULL OR latest.product_id = $3)
+            AND ($4::TEXT IS NULL OR LOWER(products.brand) = LOWER($4))
+            ORDER BY (previous.price - latest.price) / previous.price DESC
+            LIMIT $5
+            "#,
+            since,
+            store_id,
+            product_id,
+            brand,
+            DIGEST_SECTION_LIMIT,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        // a new low is a price inside the period lower than anything seen before it
+        let new_lows = sqlx::query_as!(
+            DigestEntryRow,
+            r#"
+            WITH period AS (
+                SELECT product_id, store_id, MIN(price) AS price
+                FROM product_prices
+                WHERE created_at >= $1 AND anomaly IS DISTINCT FROM 'suspected'
+                GROUP BY product_id, store_id
+            ), previous AS (
+                SELECT product_id, store_id, MIN(price) AS price
+                FROM product_prices
+                WHERE created_at < $1 AND anomaly IS DISTINCT FROM 'suspected'
+                GROUP BY product_id, store_id
+            )
+            SELECT
+                products.name AS "product!",
+                stores.name AS "store!",
+                previous.price AS "old_price!",
+                period.price AS "new_price!"
+            FROM period
+            JOIN previous USING (product_id, store_id)
+            JOIN products ON products.id = period.product_id
+            JOIN stores ON stores.id = period.store_id
+            WHERE period.price < previous.price
+            AND ($2::INT IS NULL OR period.store_id = $2)
+            AND ($3::INT IS NULL OR period.product_id = $3)
+            AND ($4::TEXT IS NULL OR LOWER(products.brand) = LOWER($4))
+            ORDER BY (previous.price - period.price) / previous.price DESC
+            LIMIT $5
+            "#,
+            since,
+            store_id,
+            product_id,
+            brand,
+            DIGEST_SECTION_LIMIT,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        let back_in_stock = sqlx::query_as!(
+            DigestEntryRow,
+            r#"
+            WITH latest AS (
+                SELECT DISTINCT ON (product_id, store_id) product_id, store_id, price, available
+                FROM product_prices
+                WHERE created_at >= $1 AND anomaly IS DISTINCT FROM 'suspected'
+                ORDER BY product_id, store_id, created_at DESC
+            ), previous AS (
+                SELECT DISTINCT ON (product_id, store_id) product_id, store_id, price, available
+                FROM product_prices
+                WHERE created_at < $1 AND anomaly IS DISTINCT FROM 'suspected'
+                ORDER BY product_id, store_id, created_at DESC
+            )
+            SELECT
+                products.name AS "product!",
+                stores.name AS "store!",
+                previous.price AS "old_price!",
+                latest.price AS "new_price!"
+            FROM latest
+            JOIN previous USING (product_id, store_id)
+            JOIN products ON products.id = latest.product_id
+            JOIN stores ON stores.id = latest.store_id
+            WHERE latest.available AND NOT previous.available
+            AND ($2::INT IS NULL OR latest.store_id = $2)
+            AND ($3::INT IS NULL OR latest.product_id = $3)
+            AND ($4::TEXT IS NULL OR LOWER(products.brand) = LOWER($4))
+            ORDER BY products.name
+            LIMIT $5
+            "#,
+            since,
+            store_id,
+            product_id,
+            brand,
+            DIGEST_SECTION_LIMIT,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        let failures = sqlx::query_as!(
+            DigestFailure,
+            r#"
+            SELECT stores.name AS "store!", COUNT(*) AS "failures!"
+            FROM scrape_failures
+            JOIN stores ON stores.id = scrape_failures.store_id
+            WHERE scrape_failures.created_at >= $1
+            AND ($2::INT IS NULL OR scrape_failures.store_id = $2)
+            GROUP BY stores.name
+            ORDER BY COUNT(*) DESC
+            "#,
+            since,
+            store_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(Digest {
+            since,
+            biggest_drops,
+            new_lows,
+            back_in_stock,
+            failures,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biggest_drops.is_empty()
+            && self.new_lows.is_empty()
+            && self.back_in_stock.is_empty()
+            && self.failures.is_empty()
+    }
+}
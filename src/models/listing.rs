@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::PgPool;
+use url::Url;
+use utoipa::ToSchema;
+
+use super::category::CategoryId;
+use super::product::ProductId;
+use super::store::StoreId;
+use crate::newtype_id;
+
+newtype_id! {
+    ListingId => listings
+}
+
+/// A product as offered by one store. A product has one listing for every store selling it,
+/// and every price is observed on a listing.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Listing {
+    pub id: ListingId,
+    #[serde(rename = "productId")]
+    pub product_id: ProductId,
+    #[serde(rename = "storeId")]
+    pub store_id: StoreId,
+    pub url: Url,
+    /// The store's own code for the product.
+    pub sku: Option<String>,
+    /// The product name as the store shows it.
+    pub title: String,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ListingRow {
+    pub id: i32,
+    pub product_id: i32,
+    pub store_id: i32,
+    pub url: String,
+    pub sku: Option<String>,
+    pub title: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<ListingRow> for Listing {
+    fn from(value: ListingRow) -> Self {
+        Self {
+            id: ListingId::new_unchecked(value.id),
+            product_id: ProductId::new_unchecked(value.product_id),
+            store_id: StoreId::new_unchecked(value.store_id),
+            url: Url::parse(&value.url).expect("url should be valid when querying the database"),
+            sku: value.sku,
+            title: value.title,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// What a scraper handler knows about a listing, it's only ever built from scraped data.
+#[derive(Debug)]
+pub struct UpsertListing {
+    pub product_id: ProductId,
+    pub store_id: StoreId,
+    pub url: Url,
+    pub sku: Option<String>,
+    pub title: String,
+}
+
+impl Listing {
+    /// Returns the active listings, only those of products in `category` when given.
+    pub async fn get_all(db: &PgPool, category: Option<CategoryId>) -> anyhow::Result<Option<Vec<Listing>>> {
+        let result = sqlx::query_as!(
+            ListingRow,
+            r#"
+            SELECT l.* FROM listings l
+            JOIN products p ON p.id = l.product_id
+            WHERE l.active = true AND ($1::INT IS NULL OR p.category_id IN (SELECT category_subtree($1)))
+            ORDER BY l.id ASC
+            "#,
+            category.map(|id| id.inner())
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(listings) => Ok(Some(listings.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: &PgPool, id: ListingId) -> anyhow::Result<Option<Listing>> {
+        let listing = sqlx::query_as!(
+            ListingRow,
+            "SELECT * FROM listings WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(listing)
+    }
+
+    pub async fn get_by_url(db: &PgPool, store_id: StoreId, url: &Url) -> anyhow::Result<Option<Listing>> {
+        let listing = sqlx::query_as!(
+            ListingRow,
+            "SELECT * FROM listings WHERE store_id = $1 AND url = $2 AND active = true",
+            store_id.inner(),
+            url.as_str()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(listing)
+    }
+
+    /// Returns the active listings of a product, none when it's not in `category`.
+    pub async fn get_by_product(
+        db: &PgPool,
+        product_id: ProductId,
+        category: Option<CategoryId>,
+    ) -> anyhow::Result<Vec<Listing>> {
+        let listings = sqlx::query_as!(
+            ListingRow,
+            r#"
+            SELECT l.* FROM listings l
+            JOIN products p ON p.id = l.product_id
+            WHERE l.product_id = $1 AND l.active = true
+            AND ($2::INT IS NULL OR p.category_id IN (SELECT category_subtree($2)))
+            ORDER BY l.store_id ASC
+            "#,
+            product_id.inner(),
+            category.map(|id| id.inner())
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(listings)
+    }
+
+    /// Creates the listing of `url` on the store, or updates it with what was just scraped. A
+    /// sku that is missing this time is kept from before.
+    pub async fn upsert(db: &PgPool, listing: UpsertListing) -> anyhow::Result<Listing> {
+        let listing = sqlx::query_as!(
+            ListingRow,
+            r#"
+            INSERT INTO listings (product_id, store_id, url, sku, title)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (store_id, url) DO UPDATE
+            SET product_id = EXCLUDED.product_id,
+                sku = COALESCE(EXCLUDED.sku, listings.sku),
+                title = EXCLUDED.title,
+                active = true,
+                deleted_at = NULL
+            RETURNING *
+            "#,
+            listing.product_id.inner(),
+            listing.store_id.inner(),
+            listing.url.as_str(),
+            listing.sku,
+            listing.title,
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(listing)
+    }
+}
@@ -0,0 +1,317 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::product::ProductId;
+use super::store::StoreId;
+use crate::newtype_id;
+
+newtype_id! {
+    NotificationChannelId => notification_channels
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum DigestSchedule {
+    Daily,
+    Weekly,
+}
+
+impl DigestSchedule {
+    pub fn inner(&self) -> &str {
+        match self {
+            DigestSchedule::Daily => "daily",
+            DigestSchedule::Weekly => "weekly",
+        }
+    }
+}
+
+impl TryFrom<String> for DigestSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            _ => anyhow::bail!("invalid digest schedule"),
+        }
+    }
+}
+
+/// A discord channel subscribed to price notifications.
+///
+/// Every filter (`store_id`, `product_id` and `brand`) is optional, a missing filter matches
+/// anything, so a channel without filters receives every notification. Channels with a `digest`
+/// schedule receive a periodic summary instead of one message per change.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NotificationChannel {
+    pub id: NotificationChannelId,
+    #[serde(rename = "guildId")]
+    pub guild_id: u64,
+    #[serde(rename = "channelId")]
+    pub channel_id: u64,
+    #[serde(rename = "storeId")]
+    pub store_id: Option<StoreId>,
+    #[serde(rename = "productId")]
+    pub product_id: Option<ProductId>,
+    pub brand: Option<String>,
+    pub digest: Option<DigestSchedule>,
+    #[serde(rename = "lastDigestAt")]
+    pub last_digest_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct NotificationChannelRow {
+    pub id: i32,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub store_id: Option<i32>,
+    pub product_id: Option<i32>,
+    pub brand: Option<String>,
+    pub digest: Option<String>,
+    pub last_digest_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<NotificationChannelRow> for NotificationChannel {
+    fn from(value: NotificationChannelRow) -> Self {
+        Self {
+            id: NotificationChannelId::new_unchecked(value.id),
+            // discord snowflakes fit in 63 bits, so they roundtrip through BIGINT untouched
+            guild_id: value.guild_id as u64,
+            channel_id: value.channel_id as u64,
+            store_id: value.store_id.map(StoreId::new_unchecked),
+            product_id: value.product_id.map(ProductId::new_unchecked),
+            brand: value.brand,
+            digest: value
+                .digest
+                .map(|digest| DigestSchedule::try_from(digest).expect("invalid digest schedule on the database")),
+            last_digest_at: value.last_digest_at,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateNotificationChannelPayload {
+    #[serde(rename = "guildId")]
+    pub guild_id: u64,
+    #[serde(rename = "channelId")]
+    pub channel_id: u64,
+    #[serde(rename = "storeId")]
+    pub store_id: Option<i32>,
+    #[serde(rename = "productId")]
+    pub product_id: Option<i32>,
+    #[validate(length(min = 1, max = 100, message = "brand must have between 1 and 100 characters"))]
+    pub brand: Option<String>,
+    pub digest: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ValidCreateNotificationChannelPayload {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub store_id: Option<StoreId>,
+    pub product_id: Option<ProductId>,
+    pub brand: Option<String>,
+    pub digest: Option<DigestSchedule>,
+}
+
+impl CreateNotificationChannelPayload {
+    pub async fn parse(self, db: &PgPool) -> anyhow::Result<ValidCreateNotificationChannelPayload> {
+        self.validate()?;
+
+        let store_id = match self.store_id {
+            Some(id) => Some(StoreId::new(db, id).await?),
+            None => None,
+        };
+
+        let product_id = match self.product_id {
+            Some(id) => Some(ProductId::new(db, id).await?),
+            None => None,
+        };
+
+        let digest = self.digest.map(DigestSchedule::try_from).transpose()?;
+
+        Ok(ValidCreateNotificationChannelPayload {
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            store_id,
+            product_id,
+            brand: self.brand,
+            digest,
+        })
+    }
+}
+
+impl NotificationChannel {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<NotificationChannel>>> {
+        let result = sqlx::query_as!(
+            NotificationChannelRow,
+            "SELECT * FROM notification_channels WHERE active = true"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(channels) => Ok(Some(channels.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(
+        db: impl PgExecutor<'_>,
+        id: NotificationChannelId,
+    ) -> anyhow::Result<Option<NotificationChannel>> {
+        let channel = sqlx::query_as!(
+            NotificationChannelRow,
+            "SELECT * FROM notification_channels WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(channel)
+    }
+
+    /// Returns every channel without a digest schedule whose filters match a price observed for
+    /// `product_id` with `brand` on `store_id`.
+    pub async fn get_matching(
+        db: &PgPool,
+        store_id: StoreId,
+        product_id: ProductId,
+        brand: &str,
+    ) -> anyhow::Result<Vec<NotificationChannel>> {
+        let channels = sqlx::query_as!(
+            NotificationChannelRow,
+            r#"
+            SELECT * FROM notification_channels
+            WHERE active = true
+            AND digest IS NULL
+            AND (store_id IS NULL OR store_id = $1)
+            AND (product_id IS NULL OR product_id = $2)
+            AND (brand IS NULL OR LOWER(brand) = LOWER($3))
+            "#,
+            store_id.inner(),
+            product_id.inner(),
+            brand,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(channels)
+    }
+
+    /// Returns every channel whose digest period has elapsed since the last one was sent.
+    pub async fn get_due_digests(db: &PgPool) -> anyhow::Result<Vec<NotificationChannel>> {
+        let channels = sqlx::query_as!(
+            NotificationChannelRow,
+            r#"
+            SELECT * FROM notification_channels
+            WHERE active = true
+            AND (
+                (digest = 'daily' AND COALESCE(last_digest_at, created_at) <= NOW() - INTERVAL '1 day')
+                OR (digest = 'weekly' AND COALESCE(last_digest_at, created_at) <= NOW() - INTERVAL '7 days')
+            )
+            "#
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(channels)
+    }
+
+    /// Returns the subscription of the same channel with the same filters and schedule, a
+    /// channel can only be subscribed once to each.
+    pub async fn get_duplicate(
+        db: &PgPool,
+        payload: &ValidCreateNotificationChannelPayload,
+    ) -> anyhow::Result<Option<NotificationChannel>> {
+        let channel = sqlx::query_as!(
+            NotificationChannelRow,
+            r#"
+            SELECT * FROM notification_channels
+            WHERE active = true
+            AND channel_id = $1
+            AND store_id IS NOT DISTINCT FROM $2
+            AND product_id IS NOT DISTINCT FROM $3
+            AND LOWER(brand) IS NOT DISTINCT FROM LOWER($4)
+            AND digest IS NOT DISTINCT FROM $5
+            "#,
+            payload.channel_id as i64,
+            payload.store_id.map(|id| id.inner()),
+            payload.product_id.map(|id| id.inner()),
+            payload.brand.as_ref(),
+            payload.digest.as_ref().map(|digest| digest.inner()),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(channel)
+    }
+
+    pub async fn mark_digest_sent(
+        db: &PgPool,
+        id: NotificationChannelId,
+        sent_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "UPDATE notification_channels SET last_digest_at = $1 WHERE id = $2",
+            sent_at,
+            id.inner()
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn create(
+        db: impl PgExecutor<'_>,
+        payload: ValidCreateNotificationChannelPayload,
+    ) -> anyhow::Result<NotificationChannel> {
+        let channel = sqlx::query_as!(
+            NotificationChannelRow,
+            r#"
+            INSERT INTO notification_channels (guild_id, channel_id, store_id, product_id, brand, digest)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            payload.guild_id as i64,
+            payload.channel_id as i64,
+            payload.store_id.map(|id| id.inner()),
+            payload.product_id.map(|id| id.inner()),
+            payload.brand.as_ref(),
+            payload.digest.as_ref().map(|digest| digest.inner()),
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(channel)
+    }
+}
@@ -1,18 +1,21 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use url::Url;
+use utoipa::ToSchema;
 use validator::Validate;
 
+use super::category::CategoryId;
 use super::store::StoreId;
+use crate::gtin::{self, Gtin};
 use crate::newtype_id;
 
 newtype_id! {
     PageId => pages
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
 pub enum PageHandler {
     KabumSearch,
     KabumProduct,
@@ -25,6 +28,14 @@ impl PageHandler {
             PageHandler::KabumProduct => "kabum_product",
         }
     }
+
+    /// The kind of page the handler knows how to scrap.
+    pub fn page_kind(&self) -> PageKind {
+        match self {
+            PageHandler::KabumSearch => PageKind::Search,
+            PageHandler::KabumProduct => PageKind::Details,
+        }
+    }
 }
 
 impl TryFrom<String> for PageHandler {
@@ -33,12 +44,13 @@ impl TryFrom<String> for PageHandler {
     fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_ref() {
             "kabum_search" => Ok(Self::KabumSearch),
+            "kabum_product" => Ok(Self::KabumProduct),
             _ => anyhow::bail!("invalid page handler"),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
 pub enum PageKind {
     Search,
     Details,
@@ -65,7 +77,7 @@ impl TryFrom<String> for PageKind {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Page {
     pub id: PageId,
     pub name: String,
@@ -77,6 +89,9 @@ pub struct Page {
     pub page_kind: PageKind,
     pub ean: Option<String>,
     pub gtin: Option<String>,
+    /// Products found through the page are put in this category.
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<CategoryId>,
     pub active: bool,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
@@ -96,13 +111,14 @@ struct PageRow {
     pub page_kind: String,
     pub ean: Option<String>,
     pub gtin: Option<String>,
+    pub category_id: Option<i32>,
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreatePagePayload {
     #[validate(length(min = 1, max = 100, message = "name of page must have between 1 and 100 characters"))]
     pub name: String,
@@ -113,6 +129,13 @@ pub struct CreatePagePayload {
     pub handler: String,
     #[serde(rename = "pageKind")]
     pub page_kind: String,
+    /// Identifies the product on detail pages, any GTIN-8, 12, 13 or 14.
+    #[validate(custom(function = "gtin::validate"))]
+    pub ean: Option<String>,
+    #[validate(custom(function = "gtin::validate"))]
+    pub gtin: Option<String>,
+    #[serde(rename = "categoryId", default)]
+    pub category_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -122,16 +145,33 @@ pub struct ValidCreatePagePayload {
     pub store_id: StoreId,
     pub handler: PageHandler,
     pub page_kind: PageKind,
+    pub ean: Option<String>,
+    pub gtin: Option<String>,
+    pub category_id: Option<CategoryId>,
 }
 
 impl CreatePagePayload {
-    pub async fn parse(self, db: &PgPool) -> anyhow::Result<ValidCreatePagePayload> {
+    pub async fn parse(self, conn: &mut PgConnection) -> anyhow::Result<ValidCreatePagePayload> {
         self.validate()?;
 
+        // Safety: we validated both identifiers above
+        let ean = self.ean.as_deref().map(|ean| Gtin::parse(ean).unwrap());
+        let gtin = self.gtin.as_deref().map(|gtin| Gtin::parse(gtin).unwrap());
+
+        if let (Some(ean), Some(gtin)) = (ean, gtin) {
+            if ean != gtin {
+                anyhow::bail!("ean {ean} and gtin {gtin} identify different products");
+            }
+        }
+
         let url = Url::parse(&self.url)?;
-        let store_id = StoreId::new(db, self.store_id).await?;
+        let store_id = StoreId::new(&mut *conn, self.store_id).await?;
         let handler = self.handler.try_into()?;
         let page_kind = self.page_kind.try_into()?;
+        let category_id = match self.category_id {
+            Some(id) => Some(CategoryId::new(&mut *conn, id).await?),
+            None => None,
+        };
 
         Ok(ValidCreatePagePayload {
             name: self.name,
@@ -139,6 +179,9 @@ impl CreatePagePayload {
             store_id,
             handler,
             page_kind,
+            ean: self.ean.as_deref().map(gtin::clean),
+            gtin: self.gtin.as_deref().map(gtin::clean),
+            category_id,
         })
     }
 }
@@ -155,6 +198,7 @@ impl From<PageRow> for Page {
             active: value.active,
             ean: value.ean,
             gtin: value.gtin,
+            category_id: value.category_id.map(CategoryId::new_unchecked),
             created_at: value.created_at,
             updated_at: value.updated_at,
             deleted_at: value.deleted_at,
@@ -190,7 +234,36 @@ impl Page {
         }
     }
 
-    pub async fn get_by_id(db: &PgPool, id: PageId) -> anyhow::Result<Option<Page>> {
+    pub async fn get_by_store(db: &PgPool, store_id: StoreId) -> anyhow::Result<Vec<Page>> {
+        let pages = sqlx::query_as!(
+            PageRow,
+            "SELECT * FROM pages WHERE store_id = $1 AND active = true",
+            store_id.inner()
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(pages)
+    }
+
+    pub async fn get_by_url(db: impl PgExecutor<'_>, store_id: StoreId, url: &Url) -> anyhow::Result<Option<Page>> {
+        let page = sqlx::query_as!(
+            PageRow,
+            "SELECT * FROM pages WHERE store_id = $1 AND url = $2 AND active = true",
+            store_id.inner(),
+            url.as_str()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(page)
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: PageId) -> anyhow::Result<Option<Page>> {
         let page = sqlx::query_as!(
             PageRow,
             "SELECT * FROM pages WHERE id = $1 AND active = true",
@@ -203,12 +276,26 @@ impl Page {
         Ok(page)
     }
 
-    pub async fn create(db: &PgPool, page: ValidCreatePagePayload) -> anyhow::Result<Page> {
+    /// Same as [`Page::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: PageId) -> anyhow::Result<Option<Page>> {
+        let page = sqlx::query_as!(
+            PageRow,
+            "SELECT * FROM pages WHERE id = $1 AND active = true FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(page)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, page: ValidCreatePagePayload) -> anyhow::Result<Page> {
         let page = sqlx::query_as!(
             PageRow,
             r#"
-            INSERT INTO pages (name, url, store_id, handler, page_kind)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO pages (name, url, store_id, handler, page_kind, ean, gtin, category_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             RETURNING *
             "#,
             &page.name,
@@ -216,6 +303,9 @@ impl Page {
             page.store_id.inner(),
             page.handler.inner(),
             page.page_kind.inner(),
+            page.ean.as_ref(),
+            page.gtin.as_ref(),
+            page.category_id.map(|id| id.inner()),
         )
         .fetch_one(db)
         .await?
@@ -223,4 +313,36 @@ impl Page {
 
         Ok(page)
     }
+
+    /// Replaces every field of a page, it's scraped with them from the next run on.
+    pub async fn update(
+        db: impl PgExecutor<'_>,
+        id: PageId,
+        page: ValidCreatePagePayload,
+    ) -> anyhow::Result<Option<Page>> {
+        let page = sqlx::query_as!(
+            PageRow,
+            r#"
+            UPDATE pages
+            SET name = $2, url = $3, store_id = $4, handler = $5, page_kind = $6, ean = $7, gtin = $8,
+                category_id = $9
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner(),
+            &page.name,
+            page.url.as_str(),
+            page.store_id.inner(),
+            page.handler.inner(),
+            page.page_kind.inner(),
+            page.ean.as_ref(),
+            page.gtin.as_ref(),
+            page.category_id.map(|id| id.inner()),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(page)
+    }
 }
@@ -1,25 +1,48 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use url::Url;
+use utoipa::ToSchema;
 use validator::Validate;
 
+use super::brand::BrandId;
+use super::category::CategoryId;
+use super::product_match::ProductMatch;
+use crate::gtin::{self, Gtin};
 use crate::newtype_id;
 
 newtype_id! {
     ProductId => products
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Product {
     pub id: ProductId,
     pub name: String,
+    /// The name of `brandId` when it has one, as it was scraped otherwise.
     pub brand: String,
+    #[serde(rename = "brandId")]
+    pub brand_id: Option<BrandId>,
     pub url: Option<String>,
     pub image: Option<String>,
     pub ean: Option<String>,
     pub gtin: Option<String>,
+    /// `gtin`, or `ean` when there is no gtin, padded to 14 digits. Unique among active products.
+    #[serde(rename = "normalizedGtin")]
+    pub normalized_gtin: Option<String>,
+    /// Content hash of the copy of `image` served at `/api/products/{id}/image`, missing until
+    /// it's downloaded.
+    #[serde(rename = "imageHash")]
+    pub image_hash: Option<String>,
+    /// Why `image` couldn't be downloaded.
+    #[serde(rename = "imageError")]
+    pub image_error: Option<String>,
+    /// Specifications as the stores list them, by name.
+    #[schema(value_type = Option<Object>)]
+    pub specs: Option<serde_json::Value>,
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<CategoryId>,
     pub active: bool,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
@@ -38,6 +61,12 @@ pub struct ProductRow {
     image: Option<String>,
     ean: Option<String>,
     gtin: Option<String>,
+    normalized_gtin: Option<String>,
+    image_hash: Option<String>,
+    image_error: Option<String>,
+    specs: Option<serde_json::Value>,
+    category_id: Option<i32>,
+    brand_id: Option<i32>,
     active: bool,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
@@ -51,9 +80,15 @@ impl From<ProductRow> for Product {
             name: product.name,
             url: product.url,
             brand: product.brand,
+            brand_id: product.brand_id.map(BrandId::new_unchecked),
             image: product.image,
             ean: product.ean,
             gtin: product.gtin,
+            normalized_gtin: product.normalized_gtin,
+            image_hash: product.image_hash,
+            image_error: product.image_error,
+            specs: product.specs,
+            category_id: product.category_id.map(CategoryId::new_unchecked),
             active: product.active,
             created_at: product.created_at,
             updated_at: product.updated_at,
@@ -62,7 +97,7 @@ impl From<ProductRow> for Product {
     }
 }
 
-#[derive(Debug, Validate, Serialize, Deserialize)]
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
 pub struct CreateProductPayload {
     #[validate(length(min = 1, message = "name must not be empty"))]
     pub name: String,
@@ -71,7 +106,11 @@ pub struct CreateProductPayload {
     #[validate(url(message = "url cannot be malformed"))]
     pub url: Option<String>,
     pub image: Option<String>,
+    /// Any GTIN-8, 12, 13 or 14 with a valid check digit.
+    #[validate(custom(function = "gtin::validate"))]
     pub ean: Option<String>,
+    /// Any GTIN-8, 12, 13 or 14 with a valid check digit, must match `ean` when both are given.
+    #[validate(custom(function = "gtin::validate"))]
     pub gtin: Option<String>,
 }
 
@@ -82,29 +121,49 @@ pub struct ValidCreateProductPayload {
     pub image: Option<String>,
     pub ean: Option<String>,
     pub gtin: Option<String>,
+    pub normalized_gtin: Option<Gtin>,
 }
 
 impl CreateProductPayload {
     pub fn parse(self) -> anyhow::Result<ValidCreateProductPayload> {
         self.validate()?;
 
+        // Safety: we validated both identifiers above
+        let ean = self.ean.as_deref().map(|ean| Gtin::parse(ean).unwrap());
+        let gtin = self.gtin.as_deref().map(|gtin| Gtin::parse(gtin).unwrap());
+
+        if let (Some(ean), Some(gtin)) = (ean.as_ref(), gtin.as_ref()) {
+            if ean != gtin {
+                anyhow::bail!("ean {ean} and gtin {gtin} identify different products");
+            }
+        }
+
         Ok(ValidCreateProductPayload {
             name: self.name,
             // Safety: we validated the url above
             url: self.url.map(|url| Url::parse(&url).unwrap()),
             brand: self.brand,
             image: self.image,
-            ean: self.ean,
-            gtin: self.gtin,
+            ean: self.ean.as_deref().map(gtin::clean),
+            gtin: self.gtin.as_deref().map(gtin::clean),
+            normalized_gtin: gtin.or(ean),
         })
     }
 }
 
 impl Product {
-    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Product>>> {
-        let result = sqlx::query_as!(ProductRow, "SELECT * FROM products WHERE active = true")
-            .fetch_all(db)
-            .await;
+    /// Returns every product, or only the ones in `category` and its subcategories.
+    pub async fn get_all(db: &PgPool, category: Option<CategoryId>) -> anyhow::Result<Option<Vec<Product>>> {
+        let result = sqlx::query_as!(
+            ProductRow,
+            r#"
+            SELECT * FROM products
+            WHERE active = true AND ($1::INT IS NULL OR category_id IN (SELECT category_subtree($1)))
+            "#,
+            category.map(|id| id.inner())
+        )
+        .fetch_all(db)
+        .await;
 
         match result {
             Ok(products) => Ok(Some(products.into_iter().map(Into::into).collect())),
@@ -113,7 +172,7 @@ impl Product {
         }
     }
 
-    pub async fn get_by_id(db: &PgPool, id: ProductId) -> anyhow::Result<Option<Product>> {
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: ProductId) -> anyhow::Result<Option<Product>> {
         let product = sqlx::query_as!(
             ProductRow,
             "SELECT * FROM products WHERE active = true AND id = $1",
@@ -126,11 +185,12 @@ impl Product {
         Ok(product)
     }
 
-    pub async fn get_by_ean(db: &PgPool, ean: &str) -> anyhow::Result<Option<Product>> {
+    /// Same as [`Product::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: ProductId) -> anyhow::Result<Option<Product>> {
         let product = sqlx::query_as!(
             ProductRow,
-            "SELECT * FROM products WHERE active = true AND ean = $1",
-            ean
+            "SELECT * FROM products WHERE active = true AND id = $1 FOR UPDATE",
+            id.inner()
         )
         .fetch_optional(db)
         .await?
@@ -139,11 +199,13 @@ impl Product {
         Ok(product)
     }
 
-    pub async fn get_by_gtin(db: &PgPool, gtin: &str) -> anyhow::Result<Option<Product>> {
+    /// Finds the product identified by `gtin`, whether it was registered through its ean or its
+    /// gtin.
+    pub async fn get_by_gtin(db: impl PgExecutor<'_>, gtin: &Gtin) -> anyhow::Result<Option<Product>> {
         let product = sqlx::query_as!(
             ProductRow,
-            "SELECT * FROM products WHERE active = true AND gtin = $1",
-            gtin
+            "SELECT * FROM products WHERE active = true AND normalized_gtin = $1",
+            gtin.inner()
         )
         .fetch_optional(db)
         .await?
@@ -152,7 +214,7 @@ impl Product {
         Ok(product)
     }
 
-    pub async fn get_by_url(db: &PgPool, url: &Url) -> anyhow::Result<Option<Product>> {
+    pub async fn get_by_url(db: impl PgExecutor<'_>, url: &Url) -> anyhow::Result<Option<Product>> {
         let product = sqlx::query_as!(
             ProductRow,
             "SELECT * FROM products WHERE active = true AND url = $1",
@@ -165,12 +227,183 @@ impl Product {
         Ok(product)
     }
 
-    pub async fn create(db: &PgPool, product: ValidCreateProductPayload) -> anyhow::Result<Product> {
+    /// Returns every active product of `brand`, known by that name or by one of its aliases,
+    /// oldest first.
+    pub async fn get_by_brand(db: &PgPool, brand: &str) -> anyhow::Result<Vec<Product>> {
+        let products = sqlx::query_as!(
+            ProductRow,
+            r#"
+            SELECT * FROM products
+            WHERE active = true
+            AND (
+                brand_key(brand) = brand_key($1)
+                OR brand_id IN (
+                    SELECT id FROM brands
+                    WHERE active = true
+                    AND brand_key($1) IN (SELECT brand_key(alias) FROM unnest(aliases) AS alias)
+                )
+            )
+            ORDER BY id ASC
+            "#,
+            brand
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(products)
+    }
+
+    /// Returns the oldest active product called `name` of `brand`, known by that name or by one
+    /// of its aliases.
+    pub async fn get_by_name(db: impl PgExecutor<'_>, brand: &str, name: &str) -> anyhow::Result<Option<Product>> {
+        let product = sqlx::query_as!(
+            ProductRow,
+            r#"
+            SELECT * FROM products
+            WHERE active = true AND name = $2
+            AND (
+                brand_key(brand) = brand_key($1)
+                OR brand_id IN (
+                    SELECT id FROM brands
+                    WHERE active = true
+                    AND brand_key($1) IN (SELECT brand_key(alias) FROM unnest(aliases) AS alias)
+                )
+            )
+            ORDER BY id ASC
+            LIMIT 1
+            "#,
+            brand,
+            name
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(product)
+    }
+
+    /// Merges `source` into `target`: listings, prices and subscriptions move over, identifiers
+    /// and images `target` lacks are taken from `source`, and `source` is deleted.
+    ///
+    /// Meant to run inside a transaction, so a merge that fails half way leaves nothing behind.
+    pub async fn merge(conn: &mut PgConnection, source: ProductId, target: ProductId) -> anyhow::Result<Product> {
+        if source == target {
+            anyhow::bail!("cannot merge product {} into itself", source.inner());
+        }
+
+        let source = sqlx::query_as!(
+            ProductRow,
+            r#"
+            UPDATE products
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            source.inner()
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("product {} does not exist", source.inner()))?;
+
+        // the source is inactive by now, so its identifier no longer conflicts with the target
+        let target = sqlx::query_as!(
+            ProductRow,
+            r#"
+            UPDATE products
+            SET url = COALESCE(url, $2),
+                image_hash = CASE WHEN image IS NULL THEN $7 ELSE image_hash END,
+                image_error = CASE WHEN image IS NULL THEN $8 ELSE image_error END,
+                image = COALESCE(image, $3),
+                ean = COALESCE(ean, $4),
+                gtin = COALESCE(gtin, $5),
+                normalized_gtin = COALESCE(normalized_gtin, $6),
+                specs = COALESCE($9, '{}'::JSONB) || COALESCE(specs, '{}'::JSONB),
+                category_id = COALESCE(category_id, $10),
+                brand_id = COALESCE(brand_id, $11)
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            target.inner(),
+            source.url,
+            source.image,
+            source.ean,
+            source.gtin,
+            source.normalized_gtin,
+            source.image_hash,
+            source.image_error,
+            source.specs,
+            source.category_id,
+            source.brand_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("product {} does not exist", target.inner()))?;
+
+        sqlx::query!(
+            "UPDATE product_prices SET product_id = $2 WHERE product_id = $1",
+            source.id,
+            target.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE listings SET product_id = $2 WHERE product_id = $1",
+            source.id,
+            target.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // a channel subscribed to both products would be subscribed twice with the same filters,
+        // the subscription to the target is kept
+        sqlx::query!(
+            r#"
+            UPDATE notification_channels nc
+            SET active = false, deleted_at = NOW()
+            WHERE nc.product_id = $1 AND nc.active = true
+            AND EXISTS (
+                SELECT 1 FROM notification_channels other
+                WHERE other.active = true
+                AND other.product_id = $2
+                AND other.channel_id = nc.channel_id
+                AND other.store_id IS NOT DISTINCT FROM nc.store_id
+                AND LOWER(other.brand) IS NOT DISTINCT FROM LOWER(nc.brand)
+                AND other.digest IS NOT DISTINCT FROM nc.digest
+            )
+            "#,
+            source.id,
+            target.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE notification_channels SET product_id = $2 WHERE product_id = $1",
+            source.id,
+            target.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        ProductMatch::reject_pending_for(&mut *conn, ProductId::new_unchecked(source.id)).await?;
+
+        Ok(target.into())
+    }
+
+    /// Creates a product, its brand is resolved to a known one by name or alias, so products
+    /// of the same brand share its spelling. Unknown brands are created along with it.
+    pub async fn create(db: impl PgExecutor<'_>, product: ValidCreateProductPayload) -> anyhow::Result<Product> {
         let product = sqlx::query_as!(
             ProductRow,
             r#"
-            INSERT INTO products (name, brand, url, image, ean, gtin)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO products (name, brand, brand_id, url, image, ean, gtin, normalized_gtin)
+            SELECT $1, COALESCE(b.name, $2), resolved.id, $3, $4, $5, $6, $7
+            FROM (SELECT resolve_brand($2) AS id) AS resolved
+            LEFT JOIN brands b ON b.id = resolved.id
             RETURNING *
             "#,
             &product.name,
@@ -179,6 +412,7 @@ impl Product {
             product.image.as_ref(),
             product.ean.as_ref(),
             product.gtin.as_ref(),
+            product.normalized_gtin.as_ref().map(Gtin::inner),
         )
         .fetch_one(db)
         .await?
@@ -186,4 +420,192 @@ impl Product {
 
         Ok(product)
     }
+
+    /// Records what a store's detail page says about a product. The image and the category of
+    /// the page are only taken when the product has none, specs are merged into the ones other
+    /// stores listed.
+    pub async fn update_details(
+        db: &PgPool,
+        id: ProductId,
+        image: Option<&str>,
+        specs: Option<&serde_json::Value>,
+        category: Option<CategoryId>,
+    ) -> anyhow::Result<Product> {
+        let product = sqlx::query_as!(
+            ProductRow,
+            r#"
+            UPDATE products
+            SET image = COALESCE(image, $2),
+                specs = CASE WHEN $3::JSONB IS NULL THEN specs ELSE COALESCE(specs, '{}'::JSONB) || $3 END,
+                category_id = COALESCE(category_id, $4)
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id.inner(),
+            image,
+            specs,
+            category.map(|id| id.inner()),
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(product)
+    }
+
+    /// Returns products with an image that was neither downloaded nor failed to for good, oldest
+    /// first. Those waiting for a retry are left out until it's due.
+    pub async fn get_pending_images(db: &PgPool, limit: i64) -> anyhow::Result<Vec<Product>> {
+        let products = sqlx::query_as!(
+            ProductRow,
+            r#"
+            SELECT * FROM products
+            WHERE active = true AND image IS NOT NULL AND image_hash IS NULL AND image_error IS NULL
+            AND NOT EXISTS (SELECT 1 FROM image_retries r WHERE r.product_id = products.id AND r.retry_at > NOW())
+            ORDER BY id ASC
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(products)
+    }
+
+    /// Moves a product to another category, or out of any with `None`.
+    pub async fn set_category(
+        db: impl PgExecutor<'_>,
+        id: ProductId,
+        category: Option<CategoryId>,
+    ) -> anyhow::Result<Option<Product>> {
+        let product = sqlx::query_as!(
+            ProductRow,
+            r#"
+            UPDATE products
+            SET category_id = $2
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner(),
+            category.map(|id| id.inner()),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(product)
+    }
+
+    pub async fn set_image_hash(db: &PgPool, id: ProductId, hash: &str) -> anyhow::Result<()> {
+        sqlx::query!(
+            "UPDATE products SET image_hash = $2, image_error = NULL WHERE id = $1",
+            id.inner(),
+            hash
+        )
+        .execute(db)
+        .await?;
+
+        Self::clear_image_retry(db, id).await
+    }
+
+    /// Records why the image can't be downloaded, it won't be tried again.
+    pub async fn set_image_error(db: &PgPool, id: ProductId, error: &str) -> anyhow::Result<()> {
+        sqlx::query!("UPDATE products SET image_error = $2 WHERE id = $1", id.inner(), error)
+            .execute(db)
+            .await?;
+
+        Self::clear_image_retry(db, id).await
+    }
+
+    /// Puts the image download off for `delay`, doubled for every attempt that already failed.
+    /// Returns how many attempts failed so far, this one included.
+    pub async fn schedule_image_retry(db: &PgPool, id: ProductId, delay: chrono::Duration) -> anyhow::Result<i32> {
+        let attempts = sqlx::query_scalar!(
+            r#"
+            INSERT INTO image_retries (product_id, attempts, retry_at)
+            VALUES ($1, 1, NOW() + $2::INTERVAL)
+            ON CONFLICT (product_id) DO UPDATE
+            SET attempts = image_retries.attempts + 1,
+                retry_at = NOW() + $2::INTERVAL * POWER(2, image_retries.attempts)
+            RETURNING attempts
+            "#,
+            id.inner(),
+            sqlx::postgres::types::PgInterval::try_from(delay).map_err(|e| anyhow::anyhow!(e))?,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(attempts)
+    }
+
+    async fn clear_image_retry(db: &PgPool, id: ProductId) -> anyhow::Result<()> {
+        sqlx::query!("DELETE FROM image_retries WHERE product_id = $1", id.inner())
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn subscribe(db: &PgPool, product_id: i32, channel_id: i64) -> anyhow::Result<i32> {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO notification_channels (guild_id, channel_id, product_id) VALUES (1, $1, $2) RETURNING id",
+            channel_id,
+            product_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(id)
+    }
+
+    #[sqlx::test]
+    async fn merge_keeps_one_subscription_per_channel(db: PgPool) -> anyhow::Result<()> {
+        let target = sqlx::query_scalar!("INSERT INTO products (name, brand) VALUES ('Target', 'Brand') RETURNING id")
+            .fetch_one(&db)
+            .await?;
+        let source = sqlx::query_scalar!("INSERT INTO products (name, brand) VALUES ('Source', 'Brand') RETURNING id")
+            .fetch_one(&db)
+            .await?;
+
+        // channel 10 is subscribed to both, channel 20 only to the source
+        let kept = subscribe(&db, target, 10).await?;
+        let duplicate = subscribe(&db, source, 10).await?;
+        let moved = subscribe(&db, source, 20).await?;
+
+        let mut tx = db.begin().await?;
+        Product::merge(
+            &mut tx,
+            ProductId::new_unchecked(source),
+            ProductId::new_unchecked(target),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let channels = sqlx::query!("SELECT id, product_id, active FROM notification_channels ORDER BY id")
+            .fetch_all(&db)
+            .await?
+            .into_iter()
+            .map(|row| (row.id, row.product_id, row.active))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            channels,
+            vec![
+                (kept, Some(target), true),
+                (duplicate, Some(target), false),
+                (moved, Some(target), true),
+            ]
+        );
+
+        Ok(())
+    }
 }
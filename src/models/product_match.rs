@@ -0,0 +1,268 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use utoipa::ToSchema;
+
+use super::product::ProductId;
+use crate::newtype_id;
+
+newtype_id! {
+    ProductMatchId => product_matches
+}
+
+/// How the matching engine decided two products are the same.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum MatchMethod {
+    /// Same brand and model number, with names similar enough to back it up.
+    Model,
+    /// Same brand and very similar names.
+    Name,
+}
+
+impl MatchMethod {
+    pub fn inner(&self) -> &str {
+        match self {
+            MatchMethod::Model => "model",
+            MatchMethod::Name => "name",
+        }
+    }
+}
+
+impl TryFrom<String> for MatchMethod {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "model" => Ok(Self::Model),
+            "name" => Ok(Self::Name),
+            _ => anyhow::bail!("invalid match method"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum MatchStatus {
+    /// Waiting for someone to review it.
+    Pending,
+    /// The products were merged.
+    Accepted,
+    Rejected,
+}
+
+impl MatchStatus {
+    pub fn inner(&self) -> &str {
+        match self {
+            MatchStatus::Pending => "pending",
+            MatchStatus::Accepted => "accepted",
+            MatchStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl TryFrom<String> for MatchStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "pending" => Ok(Self::Pending),
+            "accepted" => Ok(Self::Accepted),
+            "rejected" => Ok(Self::Rejected),
+            _ => anyhow::bail!("invalid match status"),
+        }
+    }
+}
+
+/// A suggestion that `product_id` is a duplicate of `matched_product_id`, accepting it merges the
+/// former into the latter.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductMatch {
+    pub id: ProductMatchId,
+    #[serde(rename = "productId")]
+    pub product_id: ProductId,
+    #[serde(rename = "productName")]
+    pub product_name: String,
+    #[serde(rename = "matchedProductId")]
+    pub matched_product_id: ProductId,
+    #[serde(rename = "matchedProductName")]
+    pub matched_product_name: String,
+    pub method: MatchMethod,
+    /// Similarity between both names, from 0 to 1.
+    pub score: f64,
+    pub status: MatchStatus,
+    #[serde(rename = "reviewedAt")]
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ProductMatchRow {
+    pub id: i32,
+    pub product_id: i32,
+    pub product_name: String,
+    pub matched_product_id: i32,
+    pub matched_product_name: String,
+    pub method: String,
+    pub score: f64,
+    pub status: String,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<ProductMatchRow> for ProductMatch {
+    fn from(value: ProductMatchRow) -> Self {
+        Self {
+            id: ProductMatchId::new_unchecked(value.id),
+            product_id: ProductId::new_unchecked(value.product_id),
+            product_name: value.product_name,
+            matched_product_id: ProductId::new_unchecked(value.matched_product_id),
+            matched_product_name: value.matched_product_name,
+            method: MatchMethod::try_from(value.method).expect("invalid match method on the database"),
+            score: value.score,
+            status: MatchStatus::try_from(value.status).expect("invalid match status on the database"),
+            reviewed_at: value.reviewed_at,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+impl ProductMatch {
+    /// Returns every match with `status`, or every match when it's missing, most recent first.
+    pub async fn get_all(db: &PgPool, status: Option<MatchStatus>) -> anyhow::Result<Vec<ProductMatch>> {
+        let matches = sqlx::query_as!(
+            ProductMatchRow,
+            r#"
+            SELECT m.*, p.name AS product_name, mp.name AS matched_product_name
+            FROM product_matches m
+            JOIN products p ON p.id = m.product_id
+            JOIN products mp ON mp.id = m.matched_product_id
+            WHERE m.active = true AND ($1::TEXT IS NULL OR m.status = $1)
+            ORDER BY m.created_at DESC, m.id DESC
+            "#,
+            status.as_ref().map(MatchStatus::inner),
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(matches)
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: ProductMatchId) -> anyhow::Result<Option<ProductMatch>> {
+        let product_match = sqlx::query_as!(
+            ProductMatchRow,
+            r#"
+            SELECT m.*, p.name AS product_name, mp.name AS matched_product_name
+            FROM product_matches m
+            JOIN products p ON p.id = m.product_id
+            JOIN products mp ON mp.id = m.matched_product_id
+            WHERE m.id = $1 AND m.active = true
+            "#,
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(product_match)
+    }
+
+    /// Same as [`ProductMatch::get_by_id`], but locks the match until the transaction ends so it can only be reviewed once.
+    pub async fn get_by_id_for_update(
+        db: impl PgExecutor<'_>,
+        id: ProductMatchId,
+    ) -> anyhow::Result<Option<ProductMatch>> {
+        let product_match = sqlx::query_as!(
+            ProductMatchRow,
+            r#"
+            SELECT m.*, p.name AS product_name, mp.name AS matched_product_name
+            FROM product_matches m
+            JOIN products p ON p.id = m.product_id
+            JOIN products mp ON mp.id = m.matched_product_id
+            WHERE m.id = $1 AND m.active = true
+            FOR UPDATE OF m
+            "#,
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(product_match)
+    }
+
+    /// Records a pending suggestion, returning whether it's new. A pair that was already
+    /// suggested keeps its status, so rejected suggestions are not brought back.
+    pub async fn suggest(
+        db: &PgPool,
+        product_id: ProductId,
+        matched_product_id: ProductId,
+        method: MatchMethod,
+        score: f64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO product_matches (product_id, matched_product_id, method, score)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (product_id, matched_product_id) DO NOTHING
+            "#,
+            product_id.inner(),
+            matched_product_id.inner(),
+            method.inner(),
+            score,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn review(db: impl PgExecutor<'_>, id: ProductMatchId, status: MatchStatus) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE product_matches
+            SET status = $2, reviewed_at = NOW()
+            WHERE id = $1
+            "#,
+            id.inner(),
+            status.inner(),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Rejects every pending suggestion involving `product_id`, used once it's merged away as
+    /// they no longer apply. Scanning again suggests them against the product it was merged into.
+    pub async fn reject_pending_for(db: impl PgExecutor<'_>, product_id: ProductId) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE product_matches
+            SET status = $2, reviewed_at = NOW()
+            WHERE status = $3 AND (product_id = $1 OR matched_product_id = $1)
+            "#,
+            product_id.inner(),
+            MatchStatus::Rejected.inner(),
+            MatchStatus::Pending.inner(),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
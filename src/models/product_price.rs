@@ -1,11 +1,16 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use num_traits::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use sqlx::types::BigDecimal;
 use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
 
+use super::category::CategoryId;
+use super::listing::ListingId;
 use super::product::ProductId;
 use super::store::StoreId;
 use crate::newtype_id;
@@ -14,14 +19,56 @@ newtype_id! {
     ProductPriceId => product_prices
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Set on prices far off the recent ones of their product on the store, eg: a store glitching
+/// to R$ 1,00.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum PriceAnomaly {
+    /// Left out of alerts and aggregates until the price is seen again.
+    Suspected,
+    /// Seen again, either on a later scrape or when the page was scraped once more to check it.
+    Confirmed,
+}
+
+impl PriceAnomaly {
+    pub fn inner(&self) -> &str {
+        match self {
+            PriceAnomaly::Suspected => "suspected",
+            PriceAnomaly::Confirmed => "confirmed",
+        }
+    }
+}
+
+impl TryFrom<String> for PriceAnomaly {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "suspected" => Ok(Self::Suspected),
+            "confirmed" => Ok(Self::Confirmed),
+            _ => anyhow::bail!("invalid price anomaly"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProductPrice {
     pub id: ProductPriceId,
     #[serde(rename = "productId")]
     pub product_id: ProductId,
     #[serde(rename = "storeId")]
     pub store_id: StoreId,
+    /// Where the price was seen, missing on prices recorded before listings existed.
+    #[serde(rename = "listingId")]
+    pub listing_id: Option<ListingId>,
     pub price: f64,
+    pub available: bool,
+    /// Whether the other observations of its day were dropped by the retention job, this one
+    /// being the day's lowest, highest or last price.
+    pub downsampled: bool,
+    pub anomaly: Option<PriceAnomaly>,
+    /// How far off the price was, in robust standard deviations of the recent prices.
+    #[serde(rename = "anomalyScore")]
+    pub anomaly_score: Option<f64>,
     pub active: bool,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
@@ -36,7 +83,12 @@ pub struct ProductPriceRow {
     pub id: i32,
     pub product_id: i32,
     pub store_id: i32,
+    pub listing_id: Option<i32>,
     pub price: BigDecimal,
+    pub available: bool,
+    pub downsampled: bool,
+    pub anomaly: Option<String>,
+    pub anomaly_score: Option<f64>,
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -49,7 +101,14 @@ impl From<ProductPriceRow> for ProductPrice {
             id: ProductPriceId::new_unchecked(product_price.id),
             product_id: ProductId::new_unchecked(product_price.product_id),
             store_id: StoreId::new_unchecked(product_price.store_id),
+            listing_id: product_price.listing_id.map(ListingId::new_unchecked),
             price: product_price.price.to_f64().unwrap_or_default(),
+            available: product_price.available,
+            downsampled: product_price.downsampled,
+            anomaly: product_price
+                .anomaly
+                .map(|anomaly| PriceAnomaly::try_from(anomaly).expect("invalid price anomaly on the database")),
+            anomaly_score: product_price.anomaly_score,
             active: product_price.active,
             created_at: product_price.created_at,
             updated_at: product_price.updated_at,
@@ -62,35 +121,141 @@ impl From<ProductPriceRow> for ProductPrice {
 pub struct CreateProductPricePayload {
     pub product_id: i32,
     pub store_id: i32,
+    pub listing_id: Option<i32>,
     #[validate(range(min = 0.0, max = f64::MAX, message = "price cannot be negative"))]
     pub price: f64,
+    pub available: bool,
+    pub anomaly: Option<PriceAnomaly>,
+    pub anomaly_score: Option<f64>,
 }
 
 pub struct ValidCreateProductPricePayload {
     product_id: ProductId,
     store_id: StoreId,
+    listing_id: Option<ListingId>,
     price: BigDecimal,
+    available: bool,
+    anomaly: Option<PriceAnomaly>,
+    anomaly_score: Option<f64>,
 }
 
 impl CreateProductPricePayload {
     pub async fn parse(self, db: &PgPool) -> anyhow::Result<ValidCreateProductPricePayload> {
         let product_id = ProductId::new(db, self.product_id).await?;
         let store_id = StoreId::new(db, self.store_id).await?;
+        let listing_id = match self.listing_id {
+            Some(id) => Some(ListingId::new(db, id).await?),
+            None => None,
+        };
         let price = BigDecimal::from_f64(self.price).unwrap();
 
         Ok(ValidCreateProductPricePayload {
             product_id,
             store_id,
+            listing_id,
             price,
+            available: self.available,
+            anomaly: self.anomaly,
+            anomaly_score: self.anomaly_score,
         })
     }
 }
 
+/// A price joined with the names of its product and store, as exported for analysis.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PriceHistoryEntry {
+    pub id: i32,
+    #[serde(rename = "productId")]
+    pub product_id: i32,
+    #[serde(rename = "productName")]
+    pub product_name: String,
+    pub brand: String,
+    #[serde(rename = "storeId")]
+    pub store_id: i32,
+    #[serde(rename = "storeName")]
+    pub store_name: String,
+    pub price: f64,
+    pub available: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct PriceHistoryEntryRow {
+    pub id: i32,
+    pub product_id: i32,
+    pub product_name: String,
+    pub brand: String,
+    pub store_id: i32,
+    pub store_name: String,
+    pub price: BigDecimal,
+    pub available: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PriceHistoryEntryRow> for PriceHistoryEntry {
+    fn from(value: PriceHistoryEntryRow) -> Self {
+        Self {
+            id: value.id,
+            product_id: value.product_id,
+            product_name: value.product_name,
+            brand: value.brand,
+            store_id: value.store_id,
+            store_name: value.store_name,
+            price: value.price.to_f64().unwrap_or_default(),
+            available: value.available,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// Narrows down the price history, every filter is optional.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PriceHistoryFilter {
+    /// Only prices observed at or after this instant.
+    pub from: Option<DateTime<Utc>>,
+    /// Only prices observed before this instant.
+    pub to: Option<DateTime<Utc>>,
+    #[serde(rename = "productId")]
+    #[param(rename = "productId")]
+    pub product_id: Option<i32>,
+    #[serde(rename = "storeId")]
+    #[param(rename = "storeId")]
+    pub store_id: Option<i32>,
+    /// Matched ignoring case.
+    pub brand: Option<String>,
+    /// Subcategories included.
+    #[serde(rename = "categoryId")]
+    #[param(rename = "categoryId")]
+    pub category_id: Option<i32>,
+}
+
+/// What a round of downsampling did.
+#[derive(Debug, Default)]
+pub struct Downsampled {
+    /// Days of a product on a store that were downsampled.
+    pub days: i64,
+    /// Prices dropped.
+    pub removed: i64,
+    /// Prices kept, at most three per day.
+    pub kept: i64,
+}
+
 impl ProductPrice {
-    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ProductPrice>>> {
-        let result = sqlx::query_as!(ProductPriceRow, "SELECT * FROM product_prices")
-            .fetch_all(db)
-            .await;
+    /// Returns every price, or only the ones of products in `category` and its subcategories.
+    pub async fn get_all(db: &PgPool, category: Option<CategoryId>) -> anyhow::Result<Option<Vec<ProductPrice>>> {
+        let result = sqlx::query_as!(
+            ProductPriceRow,
+            r#"
+            SELECT pp.* FROM product_prices pp
+            JOIN products p ON p.id = pp.product_id
+            WHERE $1::INT IS NULL OR p.category_id IN (SELECT category_subtree($1))
+            "#,
+            category.map(|id| id.inner())
+        )
+        .fetch_all(db)
+        .await;
 
         match result {
             Ok(prices) => Ok(Some(prices.into_iter().map(Into::into).collect())),
@@ -112,17 +277,154 @@ impl ProductPrice {
         Ok(store)
     }
 
+    /// Returns every price observed for a product, oldest first, suspected anomalies left out.
+    pub async fn get_by_product(db: &PgPool, product_id: ProductId) -> anyhow::Result<Vec<ProductPrice>> {
+        let prices = sqlx::query_as!(
+            ProductPriceRow,
+            r#"
+            SELECT * FROM product_prices
+            WHERE product_id = $1 AND anomaly IS DISTINCT FROM 'suspected'
+            ORDER BY created_at ASC
+            "#,
+            product_id.inner()
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(prices)
+    }
+
+    /// Returns the most recent price observed for a product on a given store, suspected
+    /// anomalies left out.
+    pub async fn get_latest(
+        db: &PgPool,
+        product_id: ProductId,
+        store_id: StoreId,
+    ) -> anyhow::Result<Option<ProductPrice>> {
+        let price = sqlx::query_as!(
+            ProductPriceRow,
+            r#"
+            SELECT * FROM product_prices
+            WHERE product_id = $1 AND store_id = $2 AND anomaly IS DISTINCT FROM 'suspected'
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            product_id.inner(),
+            store_id.inner(),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(price)
+    }
+
+    /// Returns the lowest price a product was ever available for on a given store, suspected
+    /// anomalies left out.
+    pub async fn get_lowest(db: &PgPool, product_id: ProductId, store_id: StoreId) -> anyhow::Result<Option<f64>> {
+        let price = sqlx::query_scalar!(
+            r#"
+            SELECT MIN(price) FROM product_prices
+            WHERE product_id = $1 AND store_id = $2 AND available = true
+            AND anomaly IS DISTINCT FROM 'suspected'
+            "#,
+            product_id.inner(),
+            store_id.inner(),
+        )
+        .fetch_one(db)
+        .await?
+        .and_then(|price| price.to_f64());
+
+        Ok(price)
+    }
+
+    /// Returns the prices observed for a product on a given store since `since`, most recent
+    /// first and suspected anomalies left out.
+    pub async fn get_recent_prices(
+        db: &PgPool,
+        product_id: ProductId,
+        store_id: StoreId,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<f64>> {
+        let prices = sqlx::query_scalar!(
+            r#"
+            SELECT price FROM product_prices
+            WHERE product_id = $1 AND store_id = $2 AND created_at >= $3
+            AND anomaly IS DISTINCT FROM 'suspected'
+            ORDER BY created_at DESC
+            LIMIT $4
+            "#,
+            product_id.inner(),
+            store_id.inner(),
+            since,
+            limit,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .filter_map(|price| price.to_f64())
+        .collect();
+
+        Ok(prices)
+    }
+
+    /// Confirms the suspected anomalies of a product on a given store that had `price` since
+    /// `since`, returning whether the store had it flagged before, confirmed or not. `price` is
+    /// rounded to the 4 decimals prices are stored with.
+    pub async fn confirm_anomalies(
+        db: &PgPool,
+        product_id: ProductId,
+        store_id: StoreId,
+        price: f64,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let seen = sqlx::query_scalar!(
+            r#"
+            WITH seen AS (
+                SELECT id, anomaly
+                FROM product_prices
+                WHERE product_id = $1 AND store_id = $2 AND price = ROUND($3::NUMERIC, 4)
+                    AND created_at >= $4 AND anomaly IN ($5, $6)
+            ),
+            confirmed AS (
+                UPDATE product_prices
+                SET anomaly = $5
+                WHERE id IN (SELECT id FROM seen WHERE anomaly = $6)
+            )
+            SELECT EXISTS (SELECT 1 FROM seen) AS "seen!"
+            "#,
+            product_id.inner(),
+            store_id.inner(),
+            BigDecimal::from_f64(price),
+            since,
+            PriceAnomaly::Confirmed.inner(),
+            PriceAnomaly::Suspected.inner(),
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(seen)
+    }
+
     pub async fn create(db: &PgPool, payload: ValidCreateProductPricePayload) -> anyhow::Result<ProductPrice> {
         let product = sqlx::query_as!(
             ProductPriceRow,
             r#"
-            INSERT INTO product_prices (product_id, store_id, price)
-            VALUES ($1, $2, $3)
+            INSERT INTO product_prices (product_id, store_id, listing_id, price, available, anomaly, anomaly_score)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING *
             "#,
             &payload.product_id.inner(),
             &payload.store_id.inner(),
+            payload.listing_id.map(|id| id.inner()),
             &payload.price,
+            payload.available,
+            payload.anomaly.as_ref().map(PriceAnomaly::inner),
+            payload.anomaly_score,
         )
         .fetch_one(db)
         .await?
@@ -130,4 +432,274 @@ impl ProductPrice {
 
         Ok(product)
     }
+
+    /// Streams every price matching `filter`, oldest first, without loading them all at once.
+    pub fn stream_history<'a>(
+        db: &'a PgPool,
+        filter: &'a PriceHistoryFilter,
+    ) -> BoxStream<'a, anyhow::Result<PriceHistoryEntry>> {
+        sqlx::query_as!(
+            PriceHistoryEntryRow,
+            r#"
+            SELECT
+                pp.id,
+                pp.product_id,
+                p.name AS product_name,
+                p.brand,
+                pp.store_id,
+                s.name AS store_name,
+                pp.price,
+                pp.available,
+                pp.created_at
+            FROM product_prices pp
+            JOIN products p ON p.id = pp.product_id
+            JOIN stores s ON s.id = pp.store_id
+            WHERE ($1::TIMESTAMPTZ IS NULL OR pp.created_at >= $1)
+            AND ($2::TIMESTAMPTZ IS NULL OR pp.created_at < $2)
+            AND ($3::INT IS NULL OR pp.product_id = $3)
+            AND ($4::INT IS NULL OR pp.store_id = $4)
+            AND ($5::TEXT IS NULL OR LOWER(p.brand) = LOWER($5))
+            AND ($6::INT IS NULL OR p.category_id IN (SELECT category_subtree($6)))
+            ORDER BY pp.created_at ASC, pp.id ASC
+            "#,
+            filter.from,
+            filter.to,
+            filter.product_id,
+            filter.store_id,
+            filter.brand,
+            filter.category_id,
+        )
+        .fetch(db)
+        .map_ok(PriceHistoryEntry::from)
+        .map_err(anyhow::Error::from)
+        .boxed()
+    }
+
+    /// Makes sure there is a partition for the month `day` is in. Prices observed in a month
+    /// without one still land on a catch-all partition, they are moved over once it's created.
+    pub async fn create_partition(db: &PgPool, day: NaiveDate) -> anyhow::Result<()> {
+        sqlx::query!("SELECT create_product_prices_partition($1)", day)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the months with prices on the catch-all partition, they belong on partitions of
+    /// their own.
+    pub async fn get_unpartitioned_months(db: &PgPool) -> anyhow::Result<Vec<NaiveDate>> {
+        let months = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT date_trunc('month', created_at AT TIME ZONE 'UTC')::DATE AS "month!"
+            FROM product_prices_default
+            "#
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(months)
+    }
+
+    /// Downsamples up to `limit` days of prices observed before `before`, a day of a product on a
+    /// store keeps only its lowest, highest and last price. Unavailable prices only count when
+    /// the product wasn't available at all that day, suspected anomalies only when there was
+    /// nothing else.
+    ///
+    /// Returns how many days were downsampled, none once every day before `before` is done.
+    pub async fn downsample(db: &PgPool, before: DateTime<Utc>, limit: i64) -> anyhow::Result<Downsampled> {
+        let result = sqlx::query_as!(
+            Downsampled,
+            r#"
+            WITH days AS (
+                SELECT DISTINCT product_id, store_id, date_trunc('day', created_at AT TIME ZONE 'UTC') AS day
+                FROM product_prices
+                WHERE created_at < $1 AND downsampled = false
+                LIMIT $2
+            ), ranked AS (
+                SELECT
+                    pp.id,
+                    pp.created_at,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY pp.product_id, pp.store_id, d.day
+                        ORDER BY pp.anomaly IS DISTINCT FROM 'suspected' DESC, pp.available DESC, pp.price ASC, pp.created_at DESC
+                    ) AS lowest,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY pp.product_id, pp.store_id, d.day
+                        ORDER BY pp.anomaly IS DISTINCT FROM 'suspected' DESC, pp.available DESC, pp.price DESC, pp.created_at DESC
+                    ) AS highest,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY pp.product_id, pp.store_id, d.day
+                        ORDER BY pp.created_at DESC, pp.id DESC
+                    ) AS last
+                FROM product_prices pp
+                JOIN days d ON pp.product_id = d.product_id
+                    AND pp.store_id = d.store_id
+                    AND pp.created_at >= d.day AT TIME ZONE 'UTC'
+                    AND pp.created_at < (d.day + INTERVAL '1 day') AT TIME ZONE 'UTC'
+            ), removed AS (
+                DELETE FROM product_prices pp
+                USING ranked r
+                WHERE pp.id = r.id AND pp.created_at = r.created_at
+                AND r.lowest > 1 AND r.highest > 1 AND r.last > 1
+                RETURNING pp.id
+            ), kept AS (
+                UPDATE product_prices pp
+                SET downsampled = true
+                FROM ranked r
+                WHERE pp.id = r.id AND pp.created_at = r.created_at
+                AND (r.lowest = 1 OR r.highest = 1 OR r.last = 1)
+                AND pp.downsampled = false
+                RETURNING pp.id
+            )
+            SELECT
+                (SELECT COUNT(*) FROM days) AS "days!",
+                (SELECT COUNT(*) FROM removed) AS "removed!",
+                (SELECT COUNT(*) FROM kept) AS "kept!"
+            "#,
+            before,
+            limit,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    async fn insert_price(db: &PgPool, price: i32, available: bool, at: DateTime<Utc>) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO product_prices (product_id, store_id, price, available, created_at)
+            SELECT p.id, s.id, $1, $2, $3
+            FROM products p, stores s
+            "#,
+            BigDecimal::from(price),
+            available,
+            at,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The prices left of the product, oldest first. Those of the days downsampled must be
+    /// marked as such.
+    async fn remaining(db: &PgPool) -> anyhow::Result<Vec<(DateTime<Utc>, i32, bool)>> {
+        let rows = sqlx::query!(
+            r#"SELECT created_at, price::INT AS "price!", available, downsampled FROM product_prices ORDER BY created_at"#
+        )
+        .fetch_all(db)
+        .await?;
+
+        assert!(rows.iter().all(|row| row.downsampled || row.created_at >= day(3, 0)));
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.created_at, row.price, row.available))
+            .collect())
+    }
+
+    fn day(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, day, hour, 0, 0).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn downsample_keeps_lowest_highest_and_last(db: PgPool) -> anyhow::Result<()> {
+        sqlx::query!("INSERT INTO stores (name, url) VALUES ('Store', 'https://store.example.com')")
+            .execute(&db)
+            .await?;
+        sqlx::query!("INSERT INTO products (name, brand) VALUES ('Product', 'Brand')")
+            .execute(&db)
+            .await?;
+
+        // the lowest price shows up twice, the later one is kept. an unavailable price lower
+        // than all of them doesn't count while the product was available that day
+        insert_price(&db, 100, true, day(1, 10)).await?;
+        insert_price(&db, 90, true, day(1, 11)).await?;
+        insert_price(&db, 90, true, day(1, 12)).await?;
+        insert_price(&db, 120, true, day(1, 13)).await?;
+        insert_price(&db, 80, false, day(1, 14)).await?;
+        insert_price(&db, 110, true, day(1, 15)).await?;
+
+        // only unavailable prices, they are the ones kept. the last price ties with another one
+        insert_price(&db, 50, false, day(2, 10)).await?;
+        insert_price(&db, 70, false, day(2, 11)).await?;
+        insert_price(&db, 60, false, day(2, 12)).await?;
+        insert_price(&db, 60, false, day(2, 13)).await?;
+
+        // after `before`, left alone
+        insert_price(&db, 10, true, day(3, 10)).await?;
+        insert_price(&db, 20, true, day(3, 11)).await?;
+
+        let result = ProductPrice::downsample(&db, day(3, 0), 100).await?;
+        assert_eq!((result.days, result.removed, result.kept), (2, 4, 6));
+
+        assert_eq!(
+            remaining(&db).await?,
+            vec![
+                (day(1, 12), 90, true),
+                (day(1, 13), 120, true),
+                (day(1, 15), 110, true),
+                (day(2, 10), 50, false),
+                (day(2, 11), 70, false),
+                (day(2, 13), 60, false),
+                (day(3, 10), 10, true),
+                (day(3, 11), 20, true),
+            ]
+        );
+
+        // days already downsampled are not looked at again
+        let result = ProductPrice::downsample(&db, day(3, 0), 100).await?;
+        assert_eq!((result.days, result.removed, result.kept), (0, 0, 0));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn confirm_anomalies_matches_stored_price(db: PgPool) -> anyhow::Result<()> {
+        let store_id = sqlx::query_scalar!(
+            "INSERT INTO stores (name, url) VALUES ('Store', 'https://store.example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await?;
+        let product_id =
+            sqlx::query_scalar!("INSERT INTO products (name, brand) VALUES ('Product', 'Brand') RETURNING id")
+                .fetch_one(&db)
+                .await?;
+        sqlx::query!(
+            "INSERT INTO product_prices (product_id, store_id, price, anomaly) VALUES ($1, $2, 0.3, $3)",
+            product_id,
+            store_id,
+            PriceAnomaly::Suspected.inner(),
+        )
+        .execute(&db)
+        .await?;
+
+        // 0.1 + 0.2 is 0.30000000000000004 as a float
+        let (product_id, store_id) = (ProductId::new_unchecked(product_id), StoreId::new_unchecked(store_id));
+        let since = Utc::now() - chrono::Duration::days(1);
+        assert!(ProductPrice::confirm_anomalies(&db, product_id, store_id, 0.1 + 0.2, since).await?);
+
+        // the row confirmed above still confirms the price when it's seen again
+        assert!(ProductPrice::confirm_anomalies(&db, product_id, store_id, 0.3, since).await?);
+        assert!(!ProductPrice::confirm_anomalies(&db, product_id, store_id, 0.4, since).await?);
+        assert!(!ProductPrice::confirm_anomalies(&db, product_id, store_id, 0.3, Utc::now()).await?);
+
+        let anomaly = sqlx::query_scalar!(
+            "SELECT anomaly FROM product_prices WHERE product_id = $1",
+            product_id.inner()
+        )
+        .fetch_one(&db)
+        .await?;
+        assert_eq!(anomaly.as_deref(), Some(PriceAnomaly::Confirmed.inner()));
+
+        Ok(())
+    }
 }
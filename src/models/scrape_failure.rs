@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::PgPool;
+use url::Url;
+use utoipa::ToSchema;
+
+use super::page::PageHandler;
+use super::store::StoreId;
+use crate::newtype_id;
+
+newtype_id! {
+    ScrapeFailureId => scrape_failures
+}
+
+/// A page the scraper could not handle, kept around so failures can be reported later.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScrapeFailure {
+    pub id: ScrapeFailureId,
+    #[serde(rename = "storeId")]
+    pub store_id: StoreId,
+    pub url: Url,
+    pub handler: PageHandler,
+    pub error: String,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ScrapeFailureRow {
+    pub id: i32,
+    pub store_id: i32,
+    pub url: String,
+    pub handler: String,
+    pub error: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<ScrapeFailureRow> for ScrapeFailure {
+    fn from(value: ScrapeFailureRow) -> Self {
+        Self {
+            id: ScrapeFailureId::new_unchecked(value.id),
+            store_id: StoreId::new_unchecked(value.store_id),
+            url: Url::parse(&value.url).expect("url should be valid when querying the database"),
+            handler: PageHandler::try_from(value.handler).expect("invalid page handler on the database"),
+            error: value.error,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+impl ScrapeFailure {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ScrapeFailure>>> {
+        let result = sqlx::query_as!(
+            ScrapeFailureRow,
+            "SELECT * FROM scrape_failures WHERE active = true ORDER BY created_at DESC"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(failures) => Ok(Some(failures.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: &PgPool, id: ScrapeFailureId) -> anyhow::Result<Option<ScrapeFailure>> {
+        let failure = sqlx::query_as!(
+            ScrapeFailureRow,
+            "SELECT * FROM scrape_failures WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(failure)
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        store_id: StoreId,
+        url: &Url,
+        handler: PageHandler,
+        error: &str,
+    ) -> anyhow::Result<ScrapeFailure> {
+        let failure = sqlx::query_as!(
+            ScrapeFailureRow,
+            r#"
+            INSERT INTO scrape_failures (store_id, url, handler, error)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            store_id.inner(),
+            url.as_str(),
+            handler.inner(),
+            error,
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(failure)
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::newtype_id;
+
+newtype_id! {
+    ScrapeRunId => scrape_runs
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum ScrapeRunStatus {
+    Running,
+    Finished,
+    Failed,
+    /// The application shut down before the run could finish.
+    Interrupted,
+}
+
+impl ScrapeRunStatus {
+    pub fn inner(&self) -> &str {
+        match self {
+            ScrapeRunStatus::Running => "running",
+            ScrapeRunStatus::Finished => "finished",
+            ScrapeRunStatus::Failed => "failed",
+            ScrapeRunStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl TryFrom<String> for ScrapeRunStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "running" => Ok(Self::Running),
+            "finished" => Ok(Self::Finished),
+            "failed" => Ok(Self::Failed),
+            "interrupted" => Ok(Self::Interrupted),
+            _ => anyhow::bail!("invalid scrape run status"),
+        }
+    }
+}
+
+/// A single pass of the scraper over a set of pages.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScrapeRun {
+    pub id: ScrapeRunId,
+    pub status: ScrapeRunStatus,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Last time the process doing the run reported it was still going.
+    #[serde(rename = "heartbeatAt")]
+    pub heartbeat_at: DateTime<Utc>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ScrapeRunRow {
+    pub id: i32,
+    pub status: String,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub heartbeat_at: DateTime<Utc>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<ScrapeRunRow> for ScrapeRun {
+    fn from(value: ScrapeRunRow) -> Self {
+        Self {
+            id: ScrapeRunId::new_unchecked(value.id),
+            status: ScrapeRunStatus::try_from(value.status).expect("invalid scrape run status on the database"),
+            finished_at: value.finished_at,
+            heartbeat_at: value.heartbeat_at,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+impl ScrapeRun {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<ScrapeRun>>> {
+        let result = sqlx::query_as!(
+            ScrapeRunRow,
+            "SELECT * FROM scrape_runs WHERE active = true ORDER BY created_at DESC"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(runs) => Ok(Some(runs.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: &PgPool, id: ScrapeRunId) -> anyhow::Result<Option<ScrapeRun>> {
+        let run = sqlx::query_as!(
+            ScrapeRunRow,
+            "SELECT * FROM scrape_runs WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(run)
+    }
+
+    pub async fn create(db: &PgPool) -> anyhow::Result<ScrapeRun> {
+        let run = sqlx::query_as!(ScrapeRunRow, "INSERT INTO scrape_runs DEFAULT VALUES RETURNING *")
+            .fetch_one(db)
+            .await?
+            .into();
+
+        Ok(run)
+    }
+
+    pub async fn finish(db: &PgPool, id: ScrapeRunId, status: ScrapeRunStatus) -> anyhow::Result<ScrapeRun> {
+        let run = sqlx::query_as!(
+            ScrapeRunRow,
+            r#"
+            UPDATE scrape_runs
+            SET status = $1, finished_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+            status.inner(),
+            id.inner()
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(run)
+    }
+
+    /// Records that the run is still going.
+    pub async fn heartbeat(db: &PgPool, id: ScrapeRunId) -> anyhow::Result<()> {
+        sqlx::query!("UPDATE scrape_runs SET heartbeat_at = NOW() WHERE id = $1", id.inner())
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks runs left as running by a process that died without shutting down as interrupted,
+    /// those without a heartbeat for `stale_after`. Runs of other processes that are still going
+    /// keep beating, so they're left alone.
+    pub async fn interrupt_stale(db: &PgPool, stale_after: Duration) -> anyhow::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE scrape_runs
+            SET status = $1, finished_at = NOW()
+            WHERE status = $2 AND heartbeat_at < NOW() - make_interval(secs => $3)
+            "#,
+            ScrapeRunStatus::Interrupted.inner(),
+            ScrapeRunStatus::Running.inner(),
+            stale_after.as_secs_f64(),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
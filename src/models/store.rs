@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use sqlx::types::chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use url::Url;
+use utoipa::ToSchema;
 use validator::Validate;
 
 use crate::error::AppError;
@@ -12,7 +13,7 @@ newtype_id! {
     StoreId => stores
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Store {
     pub id: StoreId,
     pub name: String,
@@ -51,7 +52,7 @@ impl From<StoreRow> for Store {
     }
 }
 
-#[derive(Debug, Validate, Deserialize)]
+#[derive(Debug, Validate, Deserialize, ToSchema)]
 pub struct CreateStorePayload {
     #[validate(url(message = "url must be a valid url"))]
     pub url: String,
@@ -89,7 +90,7 @@ impl Store {
         }
     }
 
-    pub async fn get_by_id(db: &PgPool, id: StoreId) -> anyhow::Result<Option<Store>> {
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: StoreId) -> anyhow::Result<Option<Store>> {
         let store = sqlx::query_as!(StoreRow, "SELECT * FROM stores WHERE id = $1", id.inner())
             .fetch_optional(db)
             .await?
@@ -98,7 +99,20 @@ impl Store {
         Ok(store)
     }
 
-    pub async fn create(db: &PgPool, page: ValidCreateStorePayload) -> anyhow::Result<Store> {
+    pub async fn get_by_url(db: impl PgExecutor<'_>, url: &Url) -> anyhow::Result<Option<Store>> {
+        let store = sqlx::query_as!(
+            StoreRow,
+            "SELECT * FROM stores WHERE url = $1 AND active = true",
+            url.as_str()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(store)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, page: ValidCreateStorePayload) -> anyhow::Result<Store> {
         let store = sqlx::query_as!(
             StoreRow,
             r#"
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use url::Url;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::error::AppError;
+use crate::newtype_id;
+
+newtype_id! {
+    WebhookId => webhooks
+}
+
+/// Something a webhook can subscribe to.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A product got a different price on a store than the last time it was scraped.
+    PriceChanged,
+    /// A product got the lowest price ever seen on a store.
+    NewLow,
+    /// A product was unavailable on a store and is now available again.
+    BackInStock,
+    /// The scraper failed to handle a page.
+    ScrapeFailed,
+}
+
+impl WebhookEvent {
+    pub fn inner(&self) -> &str {
+        match self {
+            WebhookEvent::PriceChanged => "price_changed",
+            WebhookEvent::NewLow => "new_low",
+            WebhookEvent::BackInStock => "back_in_stock",
+            WebhookEvent::ScrapeFailed => "scrape_failed",
+        }
+    }
+}
+
+impl TryFrom<String> for WebhookEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "price_changed" => Ok(Self::PriceChanged),
+            "new_low" => Ok(Self::NewLow),
+            "back_in_stock" => Ok(Self::BackInStock),
+            "scrape_failed" => Ok(Self::ScrapeFailed),
+            _ => anyhow::bail!("invalid webhook event"),
+        }
+    }
+}
+
+/// A url that receives a signed POST for every event it subscribed to.
+///
+/// The secret used to sign deliveries is only shown when the webhook is created.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: WebhookId,
+    pub url: Url,
+    pub events: Vec<WebhookEvent>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct WebhookRow {
+    pub id: i32,
+    pub url: String,
+    // only read by the delivery worker, which selects it on its own query
+    #[allow(dead_code)]
+    pub secret: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(value: WebhookRow) -> Self {
+        Self {
+            id: WebhookId::new_unchecked(value.id),
+            url: Url::parse(&value.url).expect("url should be valid when querying the database"),
+            events: value
+                .events
+                .into_iter()
+                .map(|event| WebhookEvent::try_from(event).expect("invalid webhook event on the database"))
+                .collect(),
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// A freshly created webhook, the only time its secret is available.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+/// Used both to create and to replace a webhook.
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateWebhookPayload {
+    #[validate(url(message = "webhook url must be valid"))]
+    pub url: String,
+    /// Any of `price_changed`, `new_low`, `back_in_stock` and `scrape_failed`.
+    #[validate(length(min = 1, message = "webhook must subscribe to at least one event"))]
+    pub events: Vec<String>,
+    /// Generated when missing on creation, kept as is when missing on updates.
+    #[validate(length(min = 16, max = 256, message = "secret must have between 16 and 256 characters"))]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ValidCreateWebhookPayload {
+    pub url: Url,
+    pub events: Vec<WebhookEvent>,
+    pub secret: Option<String>,
+}
+
+impl CreateWebhookPayload {
+    pub fn parse(self) -> anyhow::Result<ValidCreateWebhookPayload, AppError> {
+        self.validate().map_err(AppError::ValidationError)?;
+
+        let mut events = self
+            .events
+            .into_iter()
+            .map(WebhookEvent::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| {
+                let mut errors = ValidationErrors::new();
+                errors.add(
+                    "events",
+                    ValidationError::new("invalid").with_message(e.to_string().into()),
+                );
+                AppError::ValidationError(errors)
+            })?;
+        events.sort();
+        events.dedup();
+
+        Ok(ValidCreateWebhookPayload {
+            url: Url::parse(&self.url).unwrap(),
+            events,
+            secret: self.secret,
+        })
+    }
+}
+
+fn events_to_db(events: &[WebhookEvent]) -> Vec<String> {
+    events.iter().map(|event| event.inner().to_string()).collect()
+}
+
+impl Webhook {
+    pub async fn get_all(db: &PgPool) -> anyhow::Result<Option<Vec<Webhook>>> {
+        let result = sqlx::query_as!(
+            WebhookRow,
+            "SELECT * FROM webhooks WHERE active = true ORDER BY created_at DESC"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(webhooks) => Ok(Some(webhooks.into_iter().map(Into::into).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_by_id(db: impl PgExecutor<'_>, id: WebhookId) -> anyhow::Result<Option<Webhook>> {
+        let webhook = sqlx::query_as!(
+            WebhookRow,
+            "SELECT * FROM webhooks WHERE id = $1 AND active = true",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(webhook)
+    }
+
+    /// Same as [`Webhook::get_by_id`], but the row stays locked until the transaction ends.
+    pub async fn get_by_id_for_update(db: impl PgExecutor<'_>, id: WebhookId) -> anyhow::Result<Option<Webhook>> {
+        let webhook = sqlx::query_as!(
+            WebhookRow,
+            "SELECT * FROM webhooks WHERE id = $1 AND active = true FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(webhook)
+    }
+
+    pub async fn create(db: impl PgExecutor<'_>, payload: ValidCreateWebhookPayload) -> anyhow::Result<CreatedWebhook> {
+        let secret = payload.secret.unwrap_or_else(|| {
+            rand::random::<[u8; 32]>()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect()
+        });
+
+        let webhook: Webhook = sqlx::query_as!(
+            WebhookRow,
+            r#"
+            INSERT INTO webhooks (url, secret, events)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            payload.url.as_str(),
+            secret,
+            &events_to_db(&payload.events),
+        )
+        .fetch_one(db)
+        .await?
+        .into();
+
+        Ok(CreatedWebhook { webhook, secret })
+    }
+
+    pub async fn update(
+        db: impl PgExecutor<'_>,
+        id: WebhookId,
+        payload: ValidCreateWebhookPayload,
+    ) -> anyhow::Result<Option<Webhook>> {
+        let webhook = sqlx::query_as!(
+            WebhookRow,
+            r#"
+            UPDATE webhooks
+            SET url = $2, events = $3, secret = COALESCE($4, secret)
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner(),
+            payload.url.as_str(),
+            &events_to_db(&payload.events),
+            payload.secret,
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(webhook)
+    }
+
+    pub async fn delete(db: impl PgExecutor<'_>, id: WebhookId) -> anyhow::Result<Option<Webhook>> {
+        let webhook = sqlx::query_as!(
+            WebhookRow,
+            r#"
+            UPDATE webhooks
+            SET active = false, deleted_at = NOW()
+            WHERE id = $1 AND active = true
+            RETURNING *
+            "#,
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(webhook)
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use sqlx::{PgExecutor, PgPool};
+use url::Url;
+use utoipa::ToSchema;
+
+use super::webhook::{WebhookEvent, WebhookId};
+use crate::newtype_id;
+
+newtype_id! {
+    WebhookDeliveryId => webhook_deliveries
+}
+
+/// How many deliveries the log returns, older ones stay on the database.
+const LOG_LIMIT: i64 = 100;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    /// Waiting for its first attempt, or for a retry.
+    Pending,
+    Delivered,
+    /// Ran out of attempts.
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn inner(&self) -> &str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for WebhookDeliveryStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "failed" => Ok(Self::Failed),
+            _ => anyhow::bail!("invalid webhook delivery status"),
+        }
+    }
+}
+
+/// One event to be sent to one webhook, along with how sending it went so far.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: WebhookDeliveryId,
+    #[serde(rename = "webhookId")]
+    pub webhook_id: WebhookId,
+    pub event: WebhookEvent,
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    #[serde(rename = "nextAttemptAt")]
+    pub next_attempt_at: DateTime<Utc>,
+    #[serde(rename = "lastStatusCode")]
+    pub last_status_code: Option<i32>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "deliveredAt")]
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct WebhookDeliveryRow {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookDeliveryRow> for WebhookDelivery {
+    fn from(value: WebhookDeliveryRow) -> Self {
+        Self {
+            id: WebhookDeliveryId::new_unchecked(value.id),
+            webhook_id: WebhookId::new_unchecked(value.webhook_id),
+            event: WebhookEvent::try_from(value.event).expect("invalid webhook event on the database"),
+            payload: value.payload,
+            status: WebhookDeliveryStatus::try_from(value.status).expect("invalid delivery status on the database"),
+            attempts: value.attempts,
+            next_attempt_at: value.next_attempt_at,
+            last_status_code: value.last_status_code,
+            last_error: value.last_error,
+            delivered_at: value.delivered_at,
+            active: value.active,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+/// A delivery due for an attempt, with what is needed to send it.
+#[derive(Debug)]
+pub struct DueDelivery {
+    pub id: WebhookDeliveryId,
+    pub event: WebhookEvent,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: Url,
+    pub secret: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DueDeliveryRow {
+    pub id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+impl From<DueDeliveryRow> for DueDelivery {
+    fn from(value: DueDeliveryRow) -> Self {
+        Self {
+            id: WebhookDeliveryId::new_unchecked(value.id),
+            event: WebhookEvent::try_from(value.event).expect("invalid webhook event on the database"),
+            payload: value.payload,
+            attempts: value.attempts,
+            url: Url::parse(&value.url).expect("url should be valid when querying the database"),
+            secret: value.secret,
+        }
+    }
+}
+
+impl WebhookDelivery {
+    /// Queues `payload` for every active webhook subscribed to `event`, returning how many
+    /// deliveries were queued.
+    pub async fn enqueue(db: &PgPool, event: WebhookEvent, payload: &serde_json::Value) -> anyhow::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload)
+            SELECT id, $1, $2 FROM webhooks
+            WHERE active = true AND $1 = ANY(events)
+            "#,
+            event.inner(),
+            payload,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Locks the delivery until the transaction ends, so it isn't redelivered twice at once.
+    pub async fn get_by_id_for_update(
+        db: impl PgExecutor<'_>,
+        id: WebhookDeliveryId,
+    ) -> anyhow::Result<Option<WebhookDelivery>> {
+        let delivery = sqlx::query_as!(
+            WebhookDeliveryRow,
+            "SELECT * FROM webhook_deliveries WHERE id = $1 FOR UPDATE",
+            id.inner()
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(delivery)
+    }
+
+    /// Returns the deliveries of a webhook, most recent first.
+    pub async fn get_by_webhook(db: &PgPool, webhook_id: WebhookId) -> anyhow::Result<Vec<WebhookDelivery>> {
+        let deliveries = sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#,
+            webhook_id.inner(),
+            LOG_LIMIT,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(deliveries)
+    }
+
+    /// Returns up to `limit` pending deliveries whose next attempt is due, oldest first.
+    ///
+    /// Deliveries of webhooks deleted in the meantime are left behind, they are never sent.
+    pub async fn get_due(db: &PgPool, limit: i64) -> anyhow::Result<Vec<DueDelivery>> {
+        let deliveries = sqlx::query_as!(
+            DueDeliveryRow,
+            r#"
+            SELECT d.id, d.event, d.payload, d.attempts, w.url, w.secret
+            FROM webhook_deliveries d
+            JOIN webhooks w ON w.id = d.webhook_id
+            WHERE d.status = $1 AND d.next_attempt_at <= NOW() AND w.active = true
+            ORDER BY d.next_attempt_at ASC
+            LIMIT $2
+            "#,
+            WebhookDeliveryStatus::Pending.inner(),
+            limit,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(deliveries)
+    }
+
+    /// Schedules a delivery of `webhook_id` to be sent again right away, whatever happened to
+    /// it before. Its attempts start over so it gets every retry again.
+    pub async fn redeliver(
+        db: impl PgExecutor<'_>,
+        webhook_id: WebhookId,
+        id: WebhookDeliveryId,
+    ) -> anyhow::Result<Option<WebhookDelivery>> {
+        let delivery = sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $3, attempts = 0, next_attempt_at = NOW(), delivered_at = NULL
+            WHERE id = $1 AND webhook_id = $2
+            RETURNING *
+            "#,
+            id.inner(),
+            webhook_id.inner(),
+            WebhookDeliveryStatus::Pending.inner(),
+        )
+        .fetch_optional(db)
+        .await?
+        .map(Into::into);
+
+        Ok(delivery)
+    }
+
+    /// Fails every pending delivery of `webhook_id`, which was deleted and won't be sent to
+    /// anymore. Returns how many deliveries were failed.
+    pub async fn fail_pending(db: impl PgExecutor<'_>, webhook_id: WebhookId) -> anyhow::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2, last_error = 'the webhook was deleted'
+            WHERE webhook_id = $1 AND status = $3
+            "#,
+            webhook_id.inner(),
+            WebhookDeliveryStatus::Failed.inner(),
+            WebhookDeliveryStatus::Pending.inner(),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn mark_delivered(db: &PgPool, id: WebhookDeliveryId, status_code: u16) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2,
+                attempts = attempts + 1,
+                last_status_code = $3,
+                last_error = NULL,
+                delivered_at = NOW()
+            WHERE id = $1
+            "#,
+            id.inner(),
+            WebhookDeliveryStatus::Delivered.inner(),
+            i32::from(status_code),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt, scheduling another one after `retry_in` unless the delivery
+    /// already had `max_attempts`, in which case it's given up on.
+    pub async fn mark_attempt_failed(
+        db: &PgPool,
+        id: WebhookDeliveryId,
+        status_code: Option<u16>,
+        error: &str,
+        max_attempts: i32,
+        retry_in: chrono::Duration,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN attempts + 1 >= $4 THEN 'failed' ELSE 'pending' END,
+                attempts = attempts + 1,
+                last_status_code = $2,
+                last_error = $3,
+                next_attempt_at = NOW() + $5::INTERVAL
+            WHERE id = $1
+            "#,
+            id.inner(),
+            status_code.map(i32::from),
+            error,
+            max_attempts,
+            sqlx::postgres::types::PgInterval::try_from(retry_in).map_err(|e| anyhow::anyhow!(e))?,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
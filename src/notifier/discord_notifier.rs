@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use poise::serenity_prelude::{ChannelId, CreateAttachment, CreateMessage, Http};
+
+use super::{Notifier, TaskNotification};
+
+/// Posts notifications to the discord channels subscribed to them.
+///
+/// Only the REST client is needed to send messages, so this works regardless of the bot gateway
+/// connection being up.
+pub struct DiscordNotifier {
+    http: Arc<Http>,
+}
+
+impl std::fmt::Debug for DiscordNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiscordNotifier").finish_non_exhaustive()
+    }
+}
+
+impl DiscordNotifier {
+    pub fn new(token: &str) -> Self {
+        Self {
+            http: Arc::new(Http::new(token)),
+        }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    async fn notify(&self, notification: &TaskNotification) -> anyhow::Result<()> {
+        let content = format!("**{}**\n{}", notification.title, notification.message);
+
+        for channel in notification.channels.iter() {
+            let mut message = CreateMessage::new().content(&content);
+
+            if let Some(image) = notification.image.as_ref() {
+                message = message.add_file(CreateAttachment::bytes(image.as_slice(), "chart.png"));
+            }
+
+            if let Err(why) = ChannelId::new(*channel).send_message(&self.http, message).await {
+                tracing::error!("failed to send message to discord channel {channel}: {why:?}");
+            }
+        }
+
+        Ok(())
+    }
+}
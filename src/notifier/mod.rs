@@ -0,0 +1,161 @@
+#[cfg(feature = "discord")]
+pub mod discord_notifier;
+pub mod email_notifier;
+#[cfg(test)]
+mod stand_in;
+pub mod telegram_notifier;
+pub mod webhook_notifier;
+
+#[cfg(feature = "discord")]
+use discord_notifier::DiscordNotifier;
+use email_notifier::EmailNotifier;
+use serde::Serialize;
+use telegram_notifier::TelegramNotifier;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+use webhook_notifier::WebhookNotifier;
+
+use crate::config::{DiscordConfig, NotifierConfig};
+
+/// A message produced by background tasks that should reach every configured notifier.
+#[cfg_attr(not(feature = "discord"), allow(dead_code))]
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskNotification {
+    pub title: String,
+    pub message: String,
+    /// Discord channels subscribed to this notification, other backends deliver to the
+    /// destination they were configured with.
+    #[serde(skip)]
+    pub channels: Vec<u64>,
+    /// PNG chart attached to discord messages.
+    #[serde(skip)]
+    pub image: Option<Vec<u8>>,
+    /// Only deliver to the subscribed `channels`, skipping backends with a fixed destination.
+    #[serde(skip)]
+    pub subscribers_only: bool,
+}
+
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, notification: &TaskNotification) -> anyhow::Result<()>;
+}
+
+#[derive(Debug)]
+pub enum NotifierBackend {
+    #[cfg(feature = "discord")]
+    Discord(DiscordNotifier),
+    Webhook(WebhookNotifier),
+    Email(EmailNotifier),
+    Telegram(TelegramNotifier),
+}
+
+impl NotifierBackend {
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "discord")]
+            NotifierBackend::Discord(_) => "discord",
+            NotifierBackend::Webhook(_) => "webhook",
+            NotifierBackend::Email(_) => "email",
+            NotifierBackend::Telegram(_) => "telegram",
+        }
+    }
+
+    /// Whether this backend delivers to the channels subscribed to a notification instead of a
+    /// fixed destination.
+    pub fn delivers_to_subscribers(&self) -> bool {
+        match self {
+            #[cfg(feature = "discord")]
+            NotifierBackend::Discord(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Builds every backend that is configured.
+    pub fn from_config(config: &NotifierConfig, discord: &DiscordConfig) -> anyhow::Result<Vec<NotifierBackend>> {
+        let mut backends = vec![];
+
+        #[cfg(feature = "discord")]
+        if let Some(token) = discord.token.as_ref() {
+            backends.push(NotifierBackend::Discord(DiscordNotifier::new(token.expose())));
+        }
+
+        #[cfg(not(feature = "discord"))]
+        if discord.token.is_some() {
+            tracing::warn!("discord token is set but promor was built without the discord feature");
+        }
+
+        if let Some(webhook) = config.webhook.as_ref() {
+            backends.push(NotifierBackend::Webhook(WebhookNotifier::new(webhook.url.clone())));
+        }
+
+        if let Some(email) = config.email.as_ref() {
+            backends.push(NotifierBackend::Email(EmailNotifier::from_config(email)?));
+        }
+
+        if let Some(telegram) = config.telegram.as_ref() {
+            backends.push(NotifierBackend::Telegram(TelegramNotifier::new(
+                &telegram.api_url,
+                telegram.token.expose(),
+                telegram.chat_id.clone(),
+            )?));
+        }
+
+        Ok(backends)
+    }
+}
+
+#[cfg(test)]
+impl TaskNotification {
+    pub fn sample() -> Self {
+        Self {
+            title: "price change: RTX 4070".to_string(),
+            message: "went from R$ 4999.99 to R$ 4499.99".to_string(),
+            channels: vec![],
+            image: None,
+            subscribers_only: false,
+        }
+    }
+}
+
+impl Notifier for NotifierBackend {
+    async fn notify(&self, notification: &TaskNotification) -> anyhow::Result<()> {
+        match self {
+            #[cfg(feature = "discord")]
+            NotifierBackend::Discord(notifier) => notifier.notify(notification).await,
+            NotifierBackend::Webhook(notifier) => notifier.notify(notification).await,
+            NotifierBackend::Email(notifier) => notifier.notify(notification).await,
+            NotifierBackend::Telegram(notifier) => notifier.notify(notification).await,
+        }
+    }
+}
+
+/// Delivers notifications until every sender is dropped, the returned handle finishes once the
+/// last notification was delivered.
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    backends: Vec<NotifierBackend>,
+    mut task_receiver: UnboundedReceiver<TaskNotification>,
+) -> anyhow::Result<JoinHandle<()>> {
+    if backends.is_empty() {
+        tracing::warn!("no notifier configured, notifications will be dropped");
+    }
+
+    for backend in backends.iter() {
+        tracing::info!("notifying through {}", backend.name());
+    }
+
+    let handle = tokio::spawn(async move {
+        while let Some(notification) = task_receiver.recv().await {
+            for backend in backends.iter() {
+                if notification.subscribers_only && !backend.delivers_to_subscribers() {
+                    continue;
+                }
+
+                if let Err(e) = backend.notify(&notification).await {
+                    tracing::error!("failed to notify through {}: {e}", backend.name());
+                }
+            }
+        }
+    });
+
+    Ok(handle)
+}
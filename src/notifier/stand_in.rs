@@ -0,0 +1,49 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::Router;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use url::Url;
+
+/// A request received by a [`StandIn`].
+#[derive(Debug)]
+pub struct Received {
+    pub path: String,
+    pub body: serde_json::Value,
+}
+
+/// Local http server standing in for the destination of a notifier, it answers every request
+/// with the same status and keeps what it received.
+pub struct StandIn {
+    pub url: Url,
+    received: UnboundedReceiver<Received>,
+}
+
+impl StandIn {
+    /// Listens on a random port of the loopback interface.
+    pub async fn start(status: StatusCode) -> StandIn {
+        let (tx, received) = mpsc::unbounded_channel();
+        let app = Router::new().fallback(receive).with_state((tx, status));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        StandIn { url, received }
+    }
+
+    pub async fn next(&mut self) -> Received {
+        self.received.recv().await.expect("stand-in server stopped")
+    }
+}
+
+async fn receive(State((tx, status)): State<(UnboundedSender<Received>, StatusCode)>, request: Request) -> StatusCode {
+    let path = request.uri().path().to_string();
+    let body = axum::body::to_bytes(request.into_body(), usize::MAX).await.unwrap();
+
+    _ = tx.send(Received {
+        path,
+        body: serde_json::from_slice(&body).unwrap_or_default(),
+    });
+
+    status
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use url::Url;
+
+use super::{Notifier, TaskNotification};
+
+/// Sends notifications to a telegram chat through the Bot API.
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    url: Url,
+    chat_id: String,
+}
+
+impl std::fmt::Debug for TelegramNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the bot token is part of the url, so it must never be printed
+        f.debug_struct("TelegramNotifier")
+            .field("chat_id", &self.chat_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SendMessage<'a> {
+    chat_id: &'a str,
+    text: String,
+}
+
+impl TelegramNotifier {
+    /// `api_url` is the Bot API base url, which can point to a local stand-in server.
+    pub fn new(api_url: &Url, token: &str, chat_id: String) -> anyhow::Result<Self> {
+        // tokens look like `123456:ABC`, without the leading `./` that would be a url scheme
+        let url = api_url.join(&format!("./bot{token}/sendMessage"))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            chat_id,
+        })
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, notification: &TaskNotification) -> anyhow::Result<()> {
+        let body = SendMessage {
+            chat_id: &self.chat_id,
+            text: format!("{}\n{}", notification.title, notification.message),
+        };
+
+        // reqwest errors include the request url, which carries the bot token
+        self.client
+            .post(self.url.clone())
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.without_url())?
+            .error_for_status()
+            .map_err(|e| e.without_url())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::notifier::stand_in::StandIn;
+
+    const TOKEN: &str = "123456:secret-token";
+
+    #[tokio::test]
+    async fn sends_message_to_chat() {
+        let mut server = StandIn::start(StatusCode::OK).await;
+        let notifier = TelegramNotifier::new(&server.url, TOKEN, "42".to_string()).unwrap();
+
+        notifier.notify(&TaskNotification::sample()).await.unwrap();
+
+        let received = server.next().await;
+        assert_eq!(received.path, format!("/bot{TOKEN}/sendMessage"));
+        assert_eq!(received.body["chat_id"], "42");
+        assert_eq!(
+            received.body["text"],
+            "price change: RTX 4070\nwent from R$ 4999.99 to R$ 4499.99"
+        );
+    }
+
+    #[tokio::test]
+    async fn error_status_does_not_leak_token() {
+        let server = StandIn::start(StatusCode::UNAUTHORIZED).await;
+        let notifier = TelegramNotifier::new(&server.url, TOKEN, "42".to_string()).unwrap();
+
+        let error = notifier.notify(&TaskNotification::sample()).await.unwrap_err();
+
+        assert!(error.to_string().contains("401"));
+        assert!(!format!("{error:?}").contains(TOKEN));
+    }
+
+    #[tokio::test]
+    async fn connection_error_does_not_leak_token() {
+        // nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+
+        let notifier = TelegramNotifier::new(&url, TOKEN, "42".to_string()).unwrap();
+        let error = notifier.notify(&TaskNotification::sample()).await.unwrap_err();
+
+        assert!(!format!("{error:?}").contains(TOKEN));
+    }
+}
@@ -0,0 +1,66 @@
+use url::Url;
+
+use super::{Notifier, TaskNotification};
+
+/// POSTs every notification as JSON to a single configured url.
+#[derive(Debug)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, notification: &TaskNotification) -> anyhow::Result<()> {
+        self.client
+            .post(self.url.clone())
+            .json(notification)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::notifier::stand_in::StandIn;
+
+    #[tokio::test]
+    async fn posts_notification_as_json() {
+        let mut server = StandIn::start(StatusCode::NO_CONTENT).await;
+        let notifier = WebhookNotifier::new(server.url.join("hooks/promor").unwrap());
+
+        notifier.notify(&TaskNotification::sample()).await.unwrap();
+
+        let received = server.next().await;
+        assert_eq!(received.path, "/hooks/promor");
+        assert_eq!(
+            received.body,
+            serde_json::json!({
+                "title": "price change: RTX 4070",
+                "message": "went from R$ 4999.99 to R$ 4499.99",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status() {
+        let server = StandIn::start(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let notifier = WebhookNotifier::new(server.url.clone());
+
+        assert!(notifier.notify(&TaskNotification::sample()).await.is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{Days, Months, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::RetentionConfig;
+use crate::models::product_price::{Downsampled, ProductPrice};
+
+/// How many days of a product on a store are downsampled at once, so a large backlog doesn't
+/// end up in a single huge transaction.
+const BATCH_SIZE: i64 = 1000;
+
+/// Keeps `product_prices` in shape until `shutdown` is cancelled: partitions are created before
+/// the months they hold start, and old prices are downsampled when enabled.
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    db: PgPool,
+    config: RetentionConfig,
+    shutdown: CancellationToken,
+) -> anyhow::Result<JoinHandle<()>> {
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval());
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            if let Err(e) = create_partitions(&db, config.partitions_ahead).await {
+                tracing::error!("failed to create price partitions: {e}");
+            }
+
+            if config.downsample {
+                match downsample(&db, config.downsample_after_days, &shutdown).await {
+                    Ok(done) if done.days > 0 => tracing::info!(
+                        "downsampled {} days of prices, {} prices removed and {} kept",
+                        done.days,
+                        done.removed,
+                        done.kept
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("failed to downsample prices: {e}"),
+                }
+            }
+        }
+
+        tracing::info!("retention job stopped");
+    });
+
+    Ok(handle)
+}
+
+/// Creates the partitions of the current month and of the `ahead` following ones, along with the
+/// ones missing for prices observed before partitions were created, eg: imported history.
+async fn create_partitions(db: &PgPool, ahead: u32) -> anyhow::Result<()> {
+    for month in ProductPrice::get_unpartitioned_months(db).await? {
+        ProductPrice::create_partition(db, month).await?;
+    }
+
+    let today = Utc::now().date_naive();
+
+    for month in 0..=ahead {
+        let day = today
+            .checked_add_months(Months::new(month))
+            .ok_or_else(|| anyhow::anyhow!("month out of range"))?;
+        ProductPrice::create_partition(db, day).await?;
+    }
+
+    Ok(())
+}
+
+/// Downsamples every whole day older than `after_days` days, a batch at a time until none is
+/// left or `shutdown` is cancelled.
+async fn downsample(db: &PgPool, after_days: u32, shutdown: &CancellationToken) -> anyhow::Result<Downsampled> {
+    let before = Utc::now()
+        .date_naive()
+        .checked_sub_days(Days::new(after_days.into()))
+        .ok_or_else(|| anyhow::anyhow!("retention.downsample_after_days is too large"))?
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc();
+
+    let mut total = Downsampled::default();
+
+    while !shutdown.is_cancelled() {
+        let done = ProductPrice::downsample(db, before, BATCH_SIZE).await?;
+        total.days += done.days;
+        total.removed += done.removed;
+        total.kept += done.kept;
+
+        if done.days < BATCH_SIZE {
+            break;
+        }
+    }
+
+    Ok(total)
+}
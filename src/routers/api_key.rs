@@ -0,0 +1,110 @@
+use axum::extract::Path;
+use axum::routing::{delete, get, post};
+use axum::{middleware, Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::models::api_key::{ApiKey, CreateApiKeyPayload, IssuedApiKey};
+use crate::{auth, handlers};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, revoke))]
+pub struct ApiKeyApi;
+
+pub fn api_key_routes() -> Router {
+    Router::new()
+        .route("/api_keys", get(get_all))
+        .route("/api_keys", post(create))
+        .route("/api_keys/{id}", get(get_one))
+        .route("/api_keys/{id}", delete(revoke))
+        .route_layer(middleware::from_fn(auth::require_admin))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api_keys",
+    tag = "api_keys",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the keys that were not revoked, newest first, without their secrets", body = HttpResponse<Option<Vec<ApiKey>>>),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<ApiKey>>>>, AppError> {
+    let response = handlers::api_key::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api_keys/{id}",
+    tag = "api_keys",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the key, null when it was revoked", body = HttpResponse<Option<ApiKey>>),
+        (status = 400, description = "no api key has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ApiKey>>>, AppError> {
+    let response = handlers::api_key::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api_keys",
+    tag = "api_keys",
+    request_body = CreateApiKeyPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new key, the only response carrying its secret", body = HttpResponse<IssuedApiKey>),
+        (status = 400, description = "the payload fails validation or has an unknown scope"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn create(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Json(payload): Json<CreateApiKeyPayload>,
+) -> Result<Json<HttpResponse<IssuedApiKey>>, AppError> {
+    let response = handlers::api_key::create(&db, &api_key, payload).await?;
+    Ok(Json(HttpResponse::created(response)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api_keys/{id}",
+    tag = "api_keys",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the revoked key, null when it was already revoked", body = HttpResponse<Option<ApiKey>>),
+        (status = 400, description = "no api key has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn revoke(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ApiKey>>>, AppError> {
+    let response = handlers::api_key::revoke(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
@@ -0,0 +1,66 @@
+use axum::extract::{Path, Query};
+use axum::routing::get;
+use axum::{middleware, Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::models::audit_log::{AuditFilter, AuditLog};
+use crate::{auth, handlers};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one))]
+pub struct AuditLogApi;
+
+/// Entries hold records as they were, api keys and webhooks included, so only admins see them.
+pub fn audit_log_routes() -> Router {
+    Router::new()
+        .route("/audit", get(get_all))
+        .route("/audit/{id}", get(get_one))
+        .route_layer(middleware::from_fn(auth::require_admin))
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditFilter),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the matching changes, most recent first", body = HttpResponse<Vec<AuditLog>>),
+        (status = 400, description = "invalid filter"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+    Query(filter): Query<AuditFilter>,
+) -> anyhow::Result<Json<HttpResponse<Vec<AuditLog>>>, AppError> {
+    let response = handlers::audit_log::get_all(&db, filter).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit/{id}",
+    tag = "audit",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the entry, entries are never deleted", body = HttpResponse<Option<AuditLog>>),
+        (status = 400, description = "no entry has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<AuditLog>>>, AppError> {
+    let response = handlers::audit_log::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
@@ -0,0 +1,183 @@
+use axum::extract::{Path, Query};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::handlers::brand::MergeBrandPayload;
+use crate::models::api_key::ApiKey;
+use crate::models::brand::{Brand, BrandStats, CreateBrandPayload};
+use crate::models::category::CategoryFilter;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, update, remove, merge, get_stats))]
+pub struct BrandApi;
+
+pub fn brand_routes() -> Router {
+    Router::new()
+        .route("/brands", get(get_all))
+        .route("/brands", post(create))
+        .route("/brands/{id}", get(get_one))
+        .route("/brands/{id}", put(update))
+        .route("/brands/{id}", delete(remove))
+        .route("/brands/{id}/merge", post(merge))
+        .route("/brands/{id}/stats", get(get_stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/brands",
+    tag = "brands",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active brands, by name", body = HttpResponse<Option<Vec<Brand>>>),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(Extension(db): Extension<PgPool>) -> anyhow::Result<Json<HttpResponse<Option<Vec<Brand>>>>, AppError> {
+    let response = handlers::brand::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/brands/{id}",
+    tag = "brands",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the brand, null when it was deleted", body = HttpResponse<Option<Brand>>),
+        (status = 400, description = "no brand has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Brand>>>, AppError> {
+    let response = handlers::brand::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/brands",
+    tag = "brands",
+    request_body = CreateBrandPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new brand", body = HttpResponse<Brand>),
+        (status = 400, description = "the payload fails validation or a name or alias is already used by another brand"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn create(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Json(payload): Json<CreateBrandPayload>,
+) -> Result<Json<HttpResponse<Brand>>, AppError> {
+    let response = handlers::brand::create(&db, &api_key, payload).await?;
+    Ok(Json(HttpResponse::created(response)))
+}
+
+/// Replaces the name and aliases of a brand, its products are renamed along with it.
+#[utoipa::path(
+    put,
+    path = "/brands/{id}",
+    tag = "brands",
+    params(("id" = i32, Path)),
+    request_body = CreateBrandPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the updated brand, null when it was deleted", body = HttpResponse<Option<Brand>>),
+        (status = 400, description = "no brand has this id, the payload fails validation or a name or alias is already used by another brand"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn update(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateBrandPayload>,
+) -> Result<Json<HttpResponse<Option<Brand>>>, AppError> {
+    let response = handlers::brand::update(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/brands/{id}",
+    tag = "brands",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the deleted brand, null when it was already deleted", body = HttpResponse<Option<Brand>>),
+        (status = 400, description = "no brand has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn remove(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Brand>>>, AppError> {
+    let response = handlers::brand::delete(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+/// Makes the name and aliases of a brand aliases of another one, moves its products over and
+/// deletes it. A name taken by another brand can only become an alias this way.
+#[utoipa::path(
+    post,
+    path = "/brands/{id}/merge",
+    tag = "brands",
+    params(("id" = i32, Path, description = "the brand to merge away")),
+    request_body = MergeBrandPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the brand merged into", body = HttpResponse<Brand>),
+        (status = 400, description = "either id belongs to no brand, one of them was deleted or both are the same brand"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn merge(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<MergeBrandPayload>,
+) -> Result<Json<HttpResponse<Brand>>, AppError> {
+    let response = handlers::brand::merge(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/brands/{id}/stats",
+    tag = "brands",
+    params(("id" = i32, Path), CategoryFilter),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "prices of the products of the brand", body = HttpResponse<BrandStats>),
+        (status = 400, description = "no brand or category has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_stats(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+    Query(filter): Query<CategoryFilter>,
+) -> anyhow::Result<Json<HttpResponse<BrandStats>>, AppError> {
+    let response = handlers::brand::get_stats(&db, id, filter).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
@@ -0,0 +1,134 @@
+use axum::extract::Path;
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::models::api_key::ApiKey;
+use crate::models::category::{Category, CreateCategoryPayload};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, update, remove))]
+pub struct CategoryApi;
+
+pub fn category_routes() -> Router {
+    Router::new()
+        .route("/categories", get(get_all))
+        .route("/categories", post(create))
+        .route("/categories/{id}", get(get_one))
+        .route("/categories/{id}", put(update))
+        .route("/categories/{id}", delete(remove))
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "categories",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active categories, top level ones first, then by name", body = HttpResponse<Option<Vec<Category>>>),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<Category>>>>, AppError> {
+    let response = handlers::category::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the category, null when it was deleted", body = HttpResponse<Option<Category>>),
+        (status = 400, description = "no category has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Category>>>, AppError> {
+    let response = handlers::category::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/categories",
+    tag = "categories",
+    request_body = CreateCategoryPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new category", body = HttpResponse<Category>),
+        (status = 400, description = "the payload fails validation, the slug is taken or the parent does not exist"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn create(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Json(payload): Json<CreateCategoryPayload>,
+) -> Result<Json<HttpResponse<Category>>, AppError> {
+    let response = handlers::category::create(&db, &api_key, payload).await?;
+    Ok(Json(HttpResponse::created(response)))
+}
+
+/// Renames or moves a category, its subcategories and products move along with it.
+#[utoipa::path(
+    put,
+    path = "/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path)),
+    request_body = CreateCategoryPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the updated category, null when it was deleted", body = HttpResponse<Option<Category>>),
+        (status = 400, description = "no category has this id, the payload fails validation, the slug is taken or the parent is inside the category"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn update(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateCategoryPayload>,
+) -> Result<Json<HttpResponse<Option<Category>>>, AppError> {
+    let response = handlers::category::update(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+/// Deletes a category, its subcategories, pages and products move up to its parent.
+#[utoipa::path(
+    delete,
+    path = "/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the deleted category, null when it was already deleted", body = HttpResponse<Option<Category>>),
+        (status = 400, description = "no category has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn remove(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Category>>>, AppError> {
+    let response = handlers::category::delete(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
@@ -0,0 +1,56 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::response::sse::{self, KeepAlive, Sse};
+use axum::routing::get;
+use axum::{Extension, Router};
+use futures::{Stream, StreamExt};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_util::sync::CancellationToken;
+use utoipa::OpenApi;
+
+use crate::events::EventBus;
+
+#[derive(OpenApi)]
+#[openapi(paths(events))]
+pub struct EventsApi;
+
+pub fn events_routes() -> Router {
+    Router::new().route("/events", get(events))
+}
+
+/// Events are sent as they happen, nothing that happened before connecting is replayed. Only
+/// served when the scraper runs in the same process, `serve` alone has nothing to stream. The
+/// stream ends on shutdown, otherwise an open dashboard would hold the server up until its deadline.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "a `text/event-stream` of `price`, `price_drop` and `scrape_run` events, \
+            whose data is the JSON of the price, the drop or the run. A `lagged` event carries how many events \
+            a slow client missed", content_type = "text/event-stream"),
+    ),
+)]
+#[axum::debug_handler]
+async fn events(
+    Extension(events): Extension<EventBus>,
+    Extension(shutdown): Extension<CancellationToken>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let stream = BroadcastStream::new(events.subscribe()).map(|event| {
+        let event = match event {
+            Ok(event) => sse::Event::default().event(event.name()).json_data(&event),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                Ok(sse::Event::default().event("lagged").data(missed.to_string()))
+            }
+        };
+
+        // every event serializes fine, this only guards against a future one that doesn't
+        Ok(event.unwrap_or_else(|e| sse::Event::default().event("error").data(e.to_string())))
+    });
+    let stream = stream.take_until(shutdown.cancelled_owned());
+
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::HttpResponse;
+use crate::config::BrowserConfig;
+use crate::discord::{BotState, BotStatus};
+
+pub fn health_routes() -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/version", get(version))
+}
+
+/// What the readiness probe checks besides the database.
+///
+/// `browser` is only set when this process runs the scraper, an api-only process has no use for
+/// chrome and shouldn't be taken out of rotation for lacking it.
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    pub browser: Option<BrowserConfig>,
+}
+
+/// The process is alive whatever state the optional discord bot is in, it's only reported here,
+/// `/ready` is where a disconnected bot fails.
+#[derive(Debug, Serialize)]
+pub struct Health {
+    status: &'static str,
+    discord: BotState,
+}
+
+#[axum::debug_handler]
+async fn health(Extension(bot_status): Extension<BotStatus>) -> Json<HttpResponse<Health>> {
+    let health = Health {
+        status: "ok",
+        discord: bot_status.get(),
+    };
+
+    Json(HttpResponse::ok(health))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl Check {
+    fn pass() -> Self {
+        Self {
+            ok: true,
+            message: None,
+        }
+    }
+
+    /// Passes without checking anything, for what this process doesn't run.
+    fn disabled() -> Self {
+        Self {
+            ok: true,
+            message: Some("disabled".into()),
+        }
+    }
+
+    fn fail(message: impl ToString) -> Self {
+        Self {
+            ok: false,
+            message: Some(message.to_string()),
+        }
+    }
+}
+
+impl From<anyhow::Result<()>> for Check {
+    fn from(value: anyhow::Result<()>) -> Self {
+        match value {
+            Ok(()) => Check::pass(),
+            Err(e) => Check::fail(e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ready {
+    database: Check,
+    migrations: Check,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    browser: Option<Check>,
+    discord: Check,
+}
+
+impl Ready {
+    fn is_ready(&self) -> bool {
+        self.database.ok && self.migrations.ok && self.browser.as_ref().is_none_or(|check| check.ok) && self.discord.ok
+    }
+}
+
+/// Responds with 503 when any check fails so the proxy stops routing to this process. The discord
+/// bot only fails it when it's enabled.
+#[axum::debug_handler]
+async fn ready(
+    Extension(db): Extension<PgPool>,
+    Extension(bot_status): Extension<BotStatus>,
+    Extension(readiness): Extension<Readiness>,
+) -> (StatusCode, Json<HttpResponse<Ready>>) {
+    let database = check_database(&db).await;
+    let migrations = match database.ok {
+        true => check_migrations(&db).await.into(),
+        false => Check::fail("database is unreachable"),
+    };
+
+    let ready = Ready {
+        database,
+        migrations,
+        browser: readiness.browser.as_ref().map(|config| check_browser(config).into()),
+        discord: check_bot(bot_status.get()),
+    };
+
+    let status = match ready.is_ready() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(HttpResponse::new(status.is_success(), ready, status)))
+}
+
+async fn check_database(db: &PgPool) -> Check {
+    match sqlx::query("SELECT 1").execute(db).await {
+        Ok(_) => Check::pass(),
+        Err(e) => Check::fail(e),
+    }
+}
+
+async fn check_migrations(db: &PgPool) -> anyhow::Result<()> {
+    // the table belongs to sqlx and only exists once migrations ran, so it isn't checked at compile time
+    let applied = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success = true")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let pending = crate::MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .count();
+
+    if pending > 0 {
+        anyhow::bail!("{pending} migrations were not applied");
+    }
+
+    Ok(())
+}
+
+/// Spawning chrome on every probe would be too expensive, so we only make sure there is an
+/// executable to launch.
+fn check_browser(config: &BrowserConfig) -> anyhow::Result<()> {
+    match &config.path {
+        Some(path) if path.is_file() => Ok(()),
+        Some(path) => anyhow::bail!("chrome executable not found at {}", path.display()),
+        None => headless_chrome::browser::default_executable()
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!(e)),
+    }
+}
+
+fn check_bot(state: BotState) -> Check {
+    match state {
+        BotState::Disabled => Check::disabled(),
+        BotState::Connected => Check::pass(),
+        state => Check::fail(format!("discord bot is {}", state.inner())),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Version {
+    name: &'static str,
+    version: &'static str,
+    #[serde(rename = "gitSha")]
+    git_sha: Option<&'static str>,
+    profile: &'static str,
+    features: Vec<&'static str>,
+}
+
+#[axum::debug_handler]
+async fn version() -> Json<HttpResponse<Version>> {
+    let version = Version {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: option_env!("PROMOR_GIT_SHA"),
+        profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        features: [cfg!(feature = "discord").then_some("discord")]
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+
+    Json(HttpResponse::ok(version))
+}
@@ -0,0 +1,124 @@
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::handlers::import::{ImportReport, Importable};
+use crate::models::api_key::ApiKey;
+use crate::models::page::CreatePagePayload;
+use crate::models::product::CreateProductPayload;
+use crate::models::store::CreateStorePayload;
+
+#[derive(OpenApi)]
+#[openapi(paths(import_stores, import_pages, import_products))]
+pub struct ImportApi;
+
+pub fn import_routes() -> Router {
+    Router::new()
+        .route("/stores/import", post(import_stores))
+        .route("/pages/import", post(import_pages))
+        .route("/products/import", post(import_products))
+}
+
+#[utoipa::path(
+    post,
+    path = "/stores/import",
+    tag = "stores",
+    request_body(
+        description = "stores to insert, either as a JSON array or a CSV headed by the payload field names",
+        content((Vec<CreateStorePayload> = "application/json"), (String = "text/csv")),
+    ),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "every row was inserted", body = HttpResponse<ImportReport>),
+        (status = 422, description = "nothing was inserted, the report lists the failing rows", body = HttpResponse<ImportReport>),
+        (status = 400, description = "the body is not a CSV or a JSON array"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn import_stores(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<HttpResponse<ImportReport>>), AppError> {
+    import::<CreateStorePayload>(&db, &api_key, &headers, &body).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/pages/import",
+    tag = "pages",
+    request_body(
+        description = "pages to insert, either as a JSON array or a CSV headed by the payload field names",
+        content((Vec<CreatePagePayload> = "application/json"), (String = "text/csv")),
+    ),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "every row was inserted", body = HttpResponse<ImportReport>),
+        (status = 422, description = "nothing was inserted, the report lists the failing rows", body = HttpResponse<ImportReport>),
+        (status = 400, description = "the body is not a CSV or a JSON array"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn import_pages(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<HttpResponse<ImportReport>>), AppError> {
+    import::<CreatePagePayload>(&db, &api_key, &headers, &body).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/products/import",
+    tag = "products",
+    request_body(
+        description = "products to insert, either as a JSON array or a CSV headed by the payload field names",
+        content((Vec<CreateProductPayload> = "application/json"), (String = "text/csv")),
+    ),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "every row was inserted", body = HttpResponse<ImportReport>),
+        (status = 422, description = "nothing was inserted, the report lists the failing rows", body = HttpResponse<ImportReport>),
+        (status = 400, description = "the body is not a CSV or a JSON array"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn import_products(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<HttpResponse<ImportReport>>), AppError> {
+    import::<CreateProductPayload>(&db, &api_key, &headers, &body).await
+}
+
+async fn import<P: Importable>(
+    db: &PgPool,
+    api_key: &ApiKey,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(StatusCode, Json<HttpResponse<ImportReport>>), AppError> {
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    let report = handlers::import::import_body::<P>(db, api_key, content_type, body).await?;
+
+    let status = match report.is_ok() {
+        true => StatusCode::OK,
+        false => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+
+    Ok((status, Json(HttpResponse::new(report.is_ok(), report, status))))
+}
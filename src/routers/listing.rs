@@ -0,0 +1,84 @@
+use axum::extract::{Path, Query};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::models::category::CategoryFilter;
+use crate::models::listing::Listing;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, get_by_product))]
+pub struct ListingApi;
+
+pub fn listing_routes() -> Router {
+    Router::new()
+        .route("/listings", get(get_all))
+        .route("/listings/{id}", get(get_one))
+        .route("/products/{id}/listings", get(get_by_product))
+}
+
+#[utoipa::path(
+    get,
+    path = "/listings",
+    tag = "listings",
+    params(CategoryFilter),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active listings, by id", body = HttpResponse<Option<Vec<Listing>>>),
+        (status = 400, description = "no category has the given id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+    Query(filter): Query<CategoryFilter>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<Listing>>>>, AppError> {
+    let response = handlers::listing::get_all(&db, filter).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/listings/{id}",
+    tag = "listings",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the listing", body = HttpResponse<Option<Listing>>),
+        (status = 400, description = "no listing has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Listing>>>, AppError> {
+    let response = handlers::listing::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+/// Every store currently selling a product.
+#[utoipa::path(
+    get,
+    path = "/products/{id}/listings",
+    tag = "listings",
+    params(("id" = i32, Path, description = "product id"), CategoryFilter),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the product's active listings, by store, none when it's not in the category", body = HttpResponse<Vec<Listing>>),
+        (status = 400, description = "no product or category has the given id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_by_product(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+    Query(filter): Query<CategoryFilter>,
+) -> anyhow::Result<Json<HttpResponse<Vec<Listing>>>, AppError> {
+    let response = handlers::listing::get_by_product(&db, id, filter).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
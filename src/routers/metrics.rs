@@ -0,0 +1,17 @@
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Router};
+use metrics_exporter_prometheus::PrometheusHandle;
+
+pub fn metrics_routes() -> Router {
+    Router::new().route("/metrics", get(metrics))
+}
+
+#[axum::debug_handler]
+async fn metrics(Extension(handle): Extension<PrometheusHandle>) -> impl IntoResponse {
+    // there is no exporter task draining histograms for us, scrapes are frequent enough to do it
+    handle.run_upkeep();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], handle.render())
+}
@@ -1,12 +1,28 @@
+pub mod api_key;
+pub mod audit_log;
+pub mod brand;
+pub mod category;
+pub mod events;
+pub mod health;
+pub mod import;
+pub mod listing;
+pub mod metrics;
+pub mod notification_channel;
+pub mod openapi;
 pub mod page;
 pub mod product;
+pub mod product_match;
 pub mod product_price;
+pub mod scrape_failure;
+pub mod scrape_run;
 pub mod store;
+pub mod webhook;
 
 use reqwest::StatusCode;
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct HttpResponse<T> {
     status: u16,
     ok: bool,
@@ -0,0 +1,83 @@
+use axum::extract::Path;
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::models::api_key::ApiKey;
+use crate::models::notification_channel::{CreateNotificationChannelPayload, NotificationChannel};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create))]
+pub struct NotificationChannelApi;
+
+pub fn notification_channel_routes() -> Router {
+    Router::new()
+        .route("/notification_channels", get(get_all))
+        .route("/notification_channels", post(create))
+        .route("/notification_channels/{id}", get(get_one))
+}
+
+#[utoipa::path(
+    get,
+    path = "/notification_channels",
+    tag = "notification_channels",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active subscriptions, in no particular order", body = HttpResponse<Option<Vec<NotificationChannel>>>),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<NotificationChannel>>>>, AppError> {
+    let response = handlers::notification_channel::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/notification_channels/{id}",
+    tag = "notification_channels",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the subscription, null when it was dropped as a duplicate", body = HttpResponse<Option<NotificationChannel>>),
+        (status = 400, description = "no subscription has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<NotificationChannel>>>, AppError> {
+    let response = handlers::notification_channel::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/notification_channels",
+    tag = "notification_channels",
+    request_body = CreateNotificationChannelPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new subscription", body = HttpResponse<NotificationChannel>),
+        (status = 400, description = "the payload fails validation, has an unknown digest schedule, or names a store or product that does not exist"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+        (status = 409, description = "the channel is already subscribed with the same filters"),
+    ),
+)]
+#[axum::debug_handler]
+async fn create(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Json(payload): Json<CreateNotificationChannelPayload>,
+) -> Result<Json<HttpResponse<NotificationChannel>>, AppError> {
+    let response = handlers::notification_channel::create(&db, &api_key, payload).await?;
+    Ok(Json(HttpResponse::created(response)))
+}
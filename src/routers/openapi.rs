@@ -0,0 +1,69 @@
+use axum::routing::get;
+use axum::{Json, Router};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_scalar::{Scalar, Servable};
+
+use super::api_key::ApiKeyApi;
+use super::audit_log::AuditLogApi;
+use super::brand::BrandApi;
+use super::category::CategoryApi;
+use super::events::EventsApi;
+use super::import::ImportApi;
+use super::listing::ListingApi;
+use super::notification_channel::NotificationChannelApi;
+use super::page::PageApi;
+use super::product::ProductApi;
+use super::product_match::ProductMatchApi;
+use super::product_price::ProductPriceApi;
+use super::scrape_failure::ScrapeFailureApi;
+use super::scrape_run::ScrapeRunApi;
+use super::store::StoreApi;
+use super::webhook::WebhookApi;
+
+/// Describes every route under `/api`, each router documents its own routes.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "promor"),
+    modifiers(&ApiKeySecurity),
+    nest(
+        (path = "/api", api = StoreApi),
+        (path = "/api", api = PageApi),
+        (path = "/api", api = ProductApi),
+        (path = "/api", api = CategoryApi),
+        (path = "/api", api = BrandApi),
+        (path = "/api", api = ListingApi),
+        (path = "/api", api = ProductPriceApi),
+        (path = "/api", api = ProductMatchApi),
+        (path = "/api", api = NotificationChannelApi),
+        (path = "/api", api = ScrapeFailureApi),
+        (path = "/api", api = ScrapeRunApi),
+        (path = "/api", api = ApiKeyApi),
+        (path = "/api", api = AuditLogApi),
+        (path = "/api", api = ImportApi),
+        (path = "/api", api = EventsApi),
+        (path = "/api", api = WebhookApi),
+    )
+)]
+pub struct ApiDoc;
+
+struct ApiKeySecurity;
+
+impl Modify for ApiKeySecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+/// Serves the document at `/openapi.json` and a UI to browse it at `/docs`.
+pub fn openapi_routes() -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .merge(Scalar::with_url("/docs", ApiDoc::openapi()))
+}
+
+#[axum::debug_handler]
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
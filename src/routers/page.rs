@@ -1,26 +1,53 @@
 use axum::extract::Path;
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::{Extension, Json, Router};
 use sqlx::PgPool;
+use utoipa::OpenApi;
 
 use super::HttpResponse;
 use crate::error::AppError;
 use crate::handlers;
+use crate::models::api_key::ApiKey;
 use crate::models::page::{CreatePagePayload, Page};
 
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, update))]
+pub struct PageApi;
+
 pub fn page_routes() -> Router {
     Router::new()
         .route("/pages", get(get_all))
         .route("/pages", post(create))
         .route("/pages/{id}", get(get_one))
+        .route("/pages/{id}", put(update))
 }
 
+#[utoipa::path(
+    get,
+    path = "/pages",
+    tag = "pages",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active pages, in no particular order", body = HttpResponse<Option<Vec<Page>>>),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_all(Extension(db): Extension<PgPool>) -> anyhow::Result<Json<HttpResponse<Option<Vec<Page>>>>, AppError> {
     let response = handlers::page::get_all(&db).await?;
     Ok(Json(HttpResponse::ok(response)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/pages/{id}",
+    tag = "pages",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the page", body = HttpResponse<Option<Page>>),
+        (status = 400, description = "no page has this id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_one(
     Extension(db): Extension<PgPool>,
@@ -30,11 +57,50 @@ async fn get_one(
     Ok(Json(HttpResponse::ok(response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/pages",
+    tag = "pages",
+    request_body = CreatePagePayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new page", body = HttpResponse<Page>),
+        (status = 400, description = "the payload fails validation, has an unknown handler or page kind, or names a store or category that does not exist"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
 #[axum::debug_handler]
 async fn create(
     Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
     Json(payload): Json<CreatePagePayload>,
 ) -> Result<Json<HttpResponse<Page>>, AppError> {
-    let response = handlers::page::create(&db, payload).await?;
+    let response = handlers::page::create(&db, &api_key, payload).await?;
     Ok(Json(HttpResponse::created(response)))
 }
+
+#[utoipa::path(
+    put,
+    path = "/pages/{id}",
+    tag = "pages",
+    params(("id" = i32, Path)),
+    request_body = CreatePagePayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the updated page", body = HttpResponse<Option<Page>>),
+        (status = 400, description = "no page has this id, or the payload fails validation, has an unknown handler or page kind, or names a store or category that does not exist"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn update(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreatePagePayload>,
+) -> Result<Json<HttpResponse<Option<Page>>>, AppError> {
+    let response = handlers::page::update(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
@@ -1,28 +1,65 @@
-use axum::extract::Path;
-use axum::routing::{get, post};
+use axum::extract::{Path, Query};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
 use axum::{Extension, Json, Router};
 use sqlx::PgPool;
+use utoipa::OpenApi;
 
 use super::HttpResponse;
 use crate::error::AppError;
 use crate::handlers;
+use crate::handlers::product::{MergeProductPayload, SetCategoryPayload};
+use crate::images::ImageStore;
+use crate::models::api_key::ApiKey;
+use crate::models::category::CategoryFilter;
 use crate::models::product::{CreateProductPayload, Product};
 
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, merge, set_category, image))]
+pub struct ProductApi;
+
 pub fn product_routes() -> Router {
     Router::new()
         .route("/products", get(get_all))
         .route("/products", post(create))
         .route("/products/{id}", get(get_one))
+        .route("/products/{id}/merge", post(merge))
+        .route("/products/{id}/category", put(set_category))
+        .route("/products/{id}/image", get(image))
 }
 
+#[utoipa::path(
+    get,
+    path = "/products",
+    tag = "products",
+    params(CategoryFilter),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active products, in no particular order", body = HttpResponse<Option<Vec<Product>>>),
+        (status = 400, description = "no category has the given id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_all(
     Extension(db): Extension<PgPool>,
+    Query(filter): Query<CategoryFilter>,
 ) -> anyhow::Result<Json<HttpResponse<Option<Vec<Product>>>>, AppError> {
-    let body = handlers::product::get_all(&db).await?;
+    let body = handlers::product::get_all(&db, filter).await?;
     Ok(Json(HttpResponse::ok(body)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/products/{id}",
+    tag = "products",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the product, null when it was deleted or merged into another", body = HttpResponse<Option<Product>>),
+        (status = 400, description = "no product has this id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_one(
     Extension(db): Extension<PgPool>,
@@ -32,11 +69,122 @@ async fn get_one(
     Ok(Json(HttpResponse::ok(body)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/products",
+    tag = "products",
+    request_body = CreateProductPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new product", body = HttpResponse<Product>),
+        (status = 400, description = "the payload fails validation, its ean and gtin differ or another product has the same gtin"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+        (status = 409, description = "another product was created with the same gtin while this one was"),
+    ),
+)]
 #[axum::debug_handler]
 async fn create(
     Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
     Json(payload): Json<CreateProductPayload>,
 ) -> Result<Json<HttpResponse<Product>>, AppError> {
-    let body = handlers::product::create(&db, payload).await?;
+    let body = handlers::product::create(&db, &api_key, payload).await?;
     Ok(Json(HttpResponse::created(body)))
 }
+
+/// Moves the listings, prices and subscriptions of a product into another one and deletes it,
+/// for duplicates the matching engine did not catch.
+#[utoipa::path(
+    post,
+    path = "/products/{id}/merge",
+    tag = "products",
+    params(("id" = i32, Path, description = "the product to merge away")),
+    request_body = MergeProductPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the product merged into", body = HttpResponse<Product>),
+        (status = 400, description = "either id belongs to no product or both are the same product"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn merge(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<MergeProductPayload>,
+) -> Result<Json<HttpResponse<Product>>, AppError> {
+    let body = handlers::product::merge(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(body)))
+}
+
+/// Products found by the scraper get the category of the search page that found them, this
+/// moves them elsewhere.
+#[utoipa::path(
+    put,
+    path = "/products/{id}/category",
+    tag = "products",
+    params(("id" = i32, Path)),
+    request_body = SetCategoryPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the product with its new category, null when it was deleted", body = HttpResponse<Option<Product>>),
+        (status = 400, description = "no product or category has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn set_category(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<SetCategoryPayload>,
+) -> Result<Json<HttpResponse<Option<Product>>>, AppError> {
+    let response = handlers::product::set_category(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+/// The product's image as downloaded from the store. The etag is the image's content hash, so
+/// clients can revalidate without downloading it again.
+#[utoipa::path(
+    get,
+    path = "/products/{id}/image",
+    tag = "products",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the image, in the format the store serves it", content_type = "image/*"),
+        (status = 304, description = "the image matches the If-None-Match header"),
+        (status = 404, description = "the product has no downloaded image"),
+    ),
+)]
+#[axum::debug_handler]
+async fn image(
+    Extension(db): Extension<PgPool>,
+    Extension(images): Extension<ImageStore>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Some(image) = handlers::product::image(&db, &images, id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let etag = format!("\"{}\"", image.hash);
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes())
+    {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let headers = [
+        (header::CONTENT_TYPE, image.content_type.to_string()),
+        (header::ETAG, etag),
+        (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
+    ];
+
+    Ok((headers, image.bytes).into_response())
+}
@@ -0,0 +1,148 @@
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use serde::Deserialize;
+use sqlx::PgPool;
+use utoipa::{IntoParams, OpenApi};
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::handlers::product_match::ScanStarted;
+use crate::models::api_key::ApiKey;
+use crate::models::product::Product;
+use crate::models::product_match::ProductMatch;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, accept, reject, scan))]
+pub struct ProductMatchApi;
+
+pub fn product_match_routes() -> Router {
+    Router::new()
+        .route("/product_matches", get(get_all))
+        .route("/product_matches/scan", post(scan))
+        .route("/product_matches/{id}", get(get_one))
+        .route("/product_matches/{id}/accept", post(accept))
+        .route("/product_matches/{id}/reject", post(reject))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MatchQuery {
+    /// One of `pending`, `accepted` or `rejected`, every match when missing.
+    status: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/product_matches",
+    tag = "product_matches",
+    params(MatchQuery),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "every match with the given status, most recent first", body = HttpResponse<Vec<ProductMatch>>),
+        (status = 400, description = "invalid status"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+    Query(MatchQuery { status }): Query<MatchQuery>,
+) -> anyhow::Result<Json<HttpResponse<Vec<ProductMatch>>>, AppError> {
+    let body = handlers::product_match::get_all(&db, status).await?;
+    Ok(Json(HttpResponse::ok(body)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/product_matches/{id}",
+    tag = "product_matches",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the match, whatever its status", body = HttpResponse<Option<ProductMatch>>),
+        (status = 400, description = "no match has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ProductMatch>>>, AppError> {
+    let body = handlers::product_match::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(body)))
+}
+
+/// Merges the suggested product into the one it matched.
+#[utoipa::path(
+    post,
+    path = "/product_matches/{id}/accept",
+    tag = "product_matches",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the product merged into", body = HttpResponse<Product>),
+        (status = 400, description = "no match has this id or it was already reviewed"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn accept(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Product>>, AppError> {
+    let body = handlers::product_match::accept(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(body)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/product_matches/{id}/reject",
+    tag = "product_matches",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the rejected match, it won't be suggested again", body = HttpResponse<Option<ProductMatch>>),
+        (status = 400, description = "no match has this id or it was already reviewed"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn reject(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ProductMatch>>>, AppError> {
+    let body = handlers::product_match::reject(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(body)))
+}
+
+/// Compares every product with the others of its brand and suggests the duplicates found. The
+/// scan runs in the background and is recorded on the audit log once it made suggestions.
+#[utoipa::path(
+    post,
+    path = "/product_matches/scan",
+    tag = "product_matches",
+    security(("api_key" = [])),
+    responses(
+        (status = 202, description = "the scan started", body = HttpResponse<ScanStarted>),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+        (status = 409, description = "a scan is already running"),
+    ),
+)]
+#[axum::debug_handler]
+async fn scan(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+) -> anyhow::Result<(StatusCode, Json<HttpResponse<ScanStarted>>), AppError> {
+    let body = handlers::product_match::scan(&db, &api_key).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(HttpResponse::new(true, body, StatusCode::ACCEPTED)),
+    ))
+}
@@ -1,27 +1,61 @@
-use axum::extract::Path;
+use axum::extract::{Path, Query};
+use axum::http::header;
+use axum::response::IntoResponse;
 use axum::routing::get;
 use axum::{Extension, Json, Router};
+use serde::Deserialize;
 use sqlx::PgPool;
+use utoipa::{IntoParams, OpenApi};
 
 use super::HttpResponse;
 use crate::error::AppError;
 use crate::handlers;
-use crate::models::product_price::ProductPrice;
+use crate::handlers::product_price::ExportFormat;
+use crate::models::category::CategoryFilter;
+use crate::models::product_price::{PriceHistoryEntry, PriceHistoryFilter, ProductPrice};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, export))]
+pub struct ProductPriceApi;
 
 pub fn product_price_routes() -> Router {
     Router::new()
         .route("/product_prices", get(get_all))
+        .route("/product_prices/export", get(export))
         .route("/product_prices/{id}", get(get_one))
 }
 
+#[utoipa::path(
+    get,
+    path = "/product_prices",
+    tag = "product_prices",
+    params(CategoryFilter),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "every recorded price, in no particular order", body = HttpResponse<Option<Vec<ProductPrice>>>),
+        (status = 400, description = "no category has the given id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_all(
     Extension(db): Extension<PgPool>,
+    Query(filter): Query<CategoryFilter>,
 ) -> anyhow::Result<Json<HttpResponse<Option<Vec<ProductPrice>>>>, AppError> {
-    let response = handlers::product_price::get_all(&db).await?;
+    let response = handlers::product_price::get_all(&db, filter).await?;
     Ok(Json(HttpResponse::ok(response)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/product_prices/{id}",
+    tag = "product_prices",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the price", body = HttpResponse<Option<ProductPrice>>),
+        (status = 400, description = "no price has this id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_one(
     Extension(db): Extension<PgPool>,
@@ -30,3 +64,42 @@ async fn get_one(
     let response = handlers::product_price::get_one(&db, id).await?;
     Ok(Json(HttpResponse::ok(response)))
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportQuery {
+    /// Defaults to csv.
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/product_prices/export",
+    tag = "product_prices",
+    params(PriceHistoryFilter, ExportQuery),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "every matching price, oldest first", content(
+            (PriceHistoryEntry = "application/x-ndjson"),
+            (String = "text/csv"),
+        )),
+    ),
+)]
+#[axum::debug_handler]
+async fn export(
+    Extension(db): Extension<PgPool>,
+    Query(filter): Query<PriceHistoryFilter>,
+    Query(ExportQuery { format }): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let body = handlers::product_price::export(db, filter, format);
+    let disposition = format!("attachment; filename=\"price_history.{}\"", format.extension());
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+}
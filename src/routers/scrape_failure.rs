@@ -0,0 +1,57 @@
+use axum::extract::Path;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::models::scrape_failure::ScrapeFailure;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one))]
+pub struct ScrapeFailureApi;
+
+pub fn scrape_failure_routes() -> Router {
+    Router::new()
+        .route("/scrape_failures", get(get_all))
+        .route("/scrape_failures/{id}", get(get_one))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape_failures",
+    tag = "scrape_failures",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the recorded failures, newest first", body = HttpResponse<Option<Vec<ScrapeFailure>>>),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<ScrapeFailure>>>>, AppError> {
+    let response = handlers::scrape_failure::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape_failures/{id}",
+    tag = "scrape_failures",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the failure", body = HttpResponse<Option<ScrapeFailure>>),
+        (status = 400, description = "no failure has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ScrapeFailure>>>, AppError> {
+    let response = handlers::scrape_failure::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
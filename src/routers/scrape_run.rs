@@ -0,0 +1,57 @@
+use axum::extract::Path;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::handlers;
+use crate::models::scrape_run::ScrapeRun;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one))]
+pub struct ScrapeRunApi;
+
+pub fn scrape_run_routes() -> Router {
+    Router::new()
+        .route("/scrape_runs", get(get_all))
+        .route("/scrape_runs/{id}", get(get_one))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape_runs",
+    tag = "scrape_runs",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the scrape runs, newest first", body = HttpResponse<Option<Vec<ScrapeRun>>>),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<ScrapeRun>>>>, AppError> {
+    let response = handlers::scrape_run::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape_runs/{id}",
+    tag = "scrape_runs",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the run", body = HttpResponse<Option<ScrapeRun>>),
+        (status = 400, description = "no run has this id"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<ScrapeRun>>>, AppError> {
+    let response = handlers::scrape_run::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
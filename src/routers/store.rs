@@ -2,12 +2,18 @@ use axum::extract::Path;
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use sqlx::PgPool;
+use utoipa::OpenApi;
 
 use super::HttpResponse;
 use crate::error::AppError;
 use crate::handlers;
+use crate::models::api_key::ApiKey;
 use crate::models::store::{CreateStorePayload, Store};
 
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create))]
+pub struct StoreApi;
+
 pub fn store_routes() -> Router {
     Router::new()
         .route("/stores", get(get_all))
@@ -15,12 +21,32 @@ pub fn store_routes() -> Router {
         .route("/stores/{id}", get(get_one))
 }
 
+#[utoipa::path(
+    get,
+    path = "/stores",
+    tag = "stores",
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the active stores, in no particular order", body = HttpResponse<Option<Vec<Store>>>),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_all(Extension(db): Extension<PgPool>) -> anyhow::Result<Json<HttpResponse<Option<Vec<Store>>>>, AppError> {
     let response = handlers::store::get_all(&db).await?;
     Ok(Json(HttpResponse::ok(response)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/stores/{id}",
+    tag = "stores",
+    params(("id" = i32, Path)),
+    security((), ("api_key" = [])),
+    responses(
+        (status = 200, description = "the store, deleted stores included", body = HttpResponse<Option<Store>>),
+        (status = 400, description = "no store has this id"),
+    ),
+)]
 #[axum::debug_handler]
 async fn get_one(
     Extension(db): Extension<PgPool>,
@@ -30,11 +56,25 @@ async fn get_one(
     Ok(Json(HttpResponse::ok(response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/stores",
+    tag = "stores",
+    request_body = CreateStorePayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new store", body = HttpResponse<Store>),
+        (status = 400, description = "the payload fails validation"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the write scope"),
+    ),
+)]
 #[axum::debug_handler]
 async fn create(
     Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
     Json(payload): Json<CreateStorePayload>,
 ) -> Result<Json<HttpResponse<Store>>, AppError> {
-    let response = handlers::store::create(&db, payload).await?;
+    let response = handlers::store::create(&db, &api_key, payload).await?;
     Ok(Json(HttpResponse::created(response)))
 }
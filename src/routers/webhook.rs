@@ -0,0 +1,186 @@
+use axum::extract::Path;
+use axum::routing::{delete, get, post, put};
+use axum::{middleware, Extension, Json, Router};
+use sqlx::PgPool;
+use utoipa::OpenApi;
+
+use super::HttpResponse;
+use crate::error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::webhook::{CreateWebhookPayload, CreatedWebhook, Webhook};
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::{auth, handlers};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all, get_one, create, update, remove, get_deliveries, redeliver))]
+pub struct WebhookApi;
+
+pub fn webhook_routes() -> Router {
+    Router::new()
+        .route("/webhooks", get(get_all))
+        .route("/webhooks", post(create))
+        .route("/webhooks/{id}", get(get_one))
+        .route("/webhooks/{id}", put(update))
+        .route("/webhooks/{id}", delete(remove))
+        .route("/webhooks/{id}/deliveries", get(get_deliveries))
+        .route("/webhooks/{id}/deliveries/{delivery_id}/redeliver", post(redeliver))
+        .route_layer(middleware::from_fn(auth::require_admin))
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the active webhooks, newest first, without their secrets", body = HttpResponse<Option<Vec<Webhook>>>),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_all(
+    Extension(db): Extension<PgPool>,
+) -> anyhow::Result<Json<HttpResponse<Option<Vec<Webhook>>>>, AppError> {
+    let response = handlers::webhook::get_all(&db).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the webhook, null when it was deleted", body = HttpResponse<Option<Webhook>>),
+        (status = 400, description = "no webhook has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_one(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Webhook>>>, AppError> {
+    let response = handlers::webhook::get_one(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the new webhook, the only response carrying its secret", body = HttpResponse<CreatedWebhook>),
+        (status = 400, description = "the payload fails validation"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn create(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Json(payload): Json<CreateWebhookPayload>,
+) -> Result<Json<HttpResponse<CreatedWebhook>>, AppError> {
+    let response = handlers::webhook::create(&db, &api_key, payload).await?;
+    Ok(Json(HttpResponse::created(response)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = i32, Path)),
+    request_body = CreateWebhookPayload,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the updated webhook, null when it was deleted", body = HttpResponse<Option<Webhook>>),
+        (status = 400, description = "no webhook has this id or the payload fails validation"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn update(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateWebhookPayload>,
+) -> Result<Json<HttpResponse<Option<Webhook>>>, AppError> {
+    let response = handlers::webhook::update(&db, &api_key, id, payload).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the deleted webhook, null when it was already deleted", body = HttpResponse<Option<Webhook>>),
+        (status = 400, description = "no webhook has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn remove(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Option<Webhook>>>, AppError> {
+    let response = handlers::webhook::delete(&db, &api_key, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+/// The most recent deliveries of a webhook, pending ones included.
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the latest 100 deliveries, most recent first", body = HttpResponse<Vec<WebhookDelivery>>),
+        (status = 400, description = "no webhook has this id"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_deliveries(
+    Extension(db): Extension<PgPool>,
+    Path(id): Path<i32>,
+) -> anyhow::Result<Json<HttpResponse<Vec<WebhookDelivery>>>, AppError> {
+    let response = handlers::webhook::get_deliveries(&db, id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks/{id}/deliveries/{delivery_id}/redeliver",
+    tag = "webhooks",
+    params(("id" = i32, Path), ("delivery_id" = i32, Path)),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "the delivery, queued again, null when it belongs to another webhook", body = HttpResponse<Option<WebhookDelivery>>),
+        (status = 400, description = "no webhook or delivery has these ids, or the webhook was deleted"),
+        (status = 401, description = "missing or invalid api key"),
+        (status = 403, description = "the api key lacks the admin scope"),
+    ),
+)]
+#[axum::debug_handler]
+async fn redeliver(
+    Extension(db): Extension<PgPool>,
+    Extension(api_key): Extension<ApiKey>,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+) -> anyhow::Result<Json<HttpResponse<Option<WebhookDelivery>>>, AppError> {
+    let response = handlers::webhook::redeliver(&db, &api_key, id, delivery_id).await?;
+    Ok(Json(HttpResponse::ok(response)))
+}
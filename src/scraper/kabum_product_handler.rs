@@ -3,22 +3,132 @@ use std::sync::Arc;
 use headless_chrome::Tab;
 use serde::Deserialize;
 use sqlx::PgPool;
+use tokio::sync::mpsc::UnboundedSender;
 use url::Url;
 
-use super::{QueuePage, ScrapHandler};
-use crate::models::product::{CreateProductPayload, Product};
-use crate::models::product_price::{CreateProductPricePayload, ProductPrice};
+use super::{notify_price_change, QueuePage, ScrapHandler};
+use crate::config::AnomalyConfig;
+use crate::events::{Event, EventBus, PriceDrop};
+use crate::gtin::Gtin;
+use crate::models::listing::{Listing, UpsertListing};
+use crate::models::product::{CreateProductPayload, Product, ProductId};
+use crate::models::product_price::{CreateProductPricePayload, PriceAnomaly, ProductPrice};
+use crate::models::store::StoreId;
+use crate::notifier::TaskNotification;
+use crate::{anomaly, matching, telemetry, webhooks};
 
 #[derive(Debug)]
 pub struct KabumProductHandler {
     api: Url,
     db: PgPool,
+    notifier: UnboundedSender<TaskNotification>,
+    events: EventBus,
+    anomaly: AnomalyConfig,
+}
+
+/// A scraped price after being compared with the recent ones, it may come from scraping the
+/// product once more.
+#[derive(Debug)]
+struct CheckedPrice {
+    price: f64,
+    available: bool,
+    anomaly: Option<PriceAnomaly>,
+    score: Option<f64>,
 }
 
 impl KabumProductHandler {
-    pub fn new(db: PgPool) -> Self {
+    pub fn new(
+        db: PgPool,
+        notifier: UnboundedSender<TaskNotification>,
+        events: EventBus,
+        anomaly: AnomalyConfig,
+    ) -> Self {
         let url = Url::parse("https://servicespub.prod.api.aws.grupokabum.com.br/descricao/v1/descricao/produto/");
-        Self { api: url.unwrap(), db }
+        Self {
+            api: url.unwrap(),
+            db,
+            notifier,
+            events,
+            anomaly,
+        }
+    }
+
+    async fn fetch(&self, id: &str) -> anyhow::Result<KabumProductDescription> {
+        let mut raw_url = self.api.to_string();
+        raw_url.push_str(id);
+        let url = Url::parse(&raw_url).unwrap();
+
+        let response = reqwest::get(url).await?;
+        let body = response.text().await?;
+        Ok(serde_json::from_str::<KabumProductDescription>(&body)?)
+    }
+
+    /// Flags `price` when it's an anomaly. It's confirmed right away when the store had it
+    /// flagged within the anomaly window, otherwise the product is scraped once more to confirm it when enabled.
+    async fn check_price(
+        &self,
+        id: &str,
+        product_id: ProductId,
+        store_id: StoreId,
+        price: f64,
+        available: bool,
+    ) -> anyhow::Result<CheckedPrice> {
+        let unchecked = CheckedPrice {
+            price,
+            available,
+            anomaly: None,
+            score: None,
+        };
+
+        if !self.anomaly.enabled {
+            return Ok(unchecked);
+        }
+
+        let Some(score) = anomaly::check(&self.db, &self.anomaly, product_id, store_id, price).await? else {
+            return Ok(unchecked);
+        };
+
+        let flagged = |anomaly| CheckedPrice {
+            anomaly: Some(anomaly),
+            score: Some(score),
+            ..unchecked
+        };
+
+        let since = anomaly::since(&self.anomaly)?;
+        if ProductPrice::confirm_anomalies(&self.db, product_id, store_id, price, since).await? {
+            return Ok(flagged(PriceAnomaly::Confirmed));
+        }
+
+        if !self.anomaly.confirm {
+            return Ok(flagged(PriceAnomaly::Suspected));
+        }
+
+        tokio::time::sleep(self.anomaly.confirm_delay()).await;
+        let again = match self.fetch(id).await {
+            Ok(again) => again,
+            Err(e) => {
+                // the price is still stored, flagged until it's seen again
+                tracing::warn!("failed to scrape product {id} again to confirm its price: {e}");
+                return Ok(flagged(PriceAnomaly::Suspected));
+            }
+        };
+
+        if again.price == price {
+            return Ok(CheckedPrice {
+                available: again.availability,
+                ..flagged(PriceAnomaly::Confirmed)
+            });
+        }
+
+        // the price changed in the meantime, the new one is only checked
+        let score = anomaly::check(&self.db, &self.anomaly, product_id, store_id, again.price).await?;
+
+        Ok(CheckedPrice {
+            price: again.price,
+            available: again.availability,
+            anomaly: score.map(|_| PriceAnomaly::Suspected),
+            score,
+        })
     }
 }
 
@@ -34,16 +144,86 @@ pub struct KabumProductDescription {
     pub manufacturer: KabumManufacturer,
     #[serde(rename = "preco")]
     pub price: f64,
-    #[serde(rename = "preco_antigo")]
-    pub old_price: f64,
-    #[serde(rename = "preco_desconto")]
-    pub discount_price: f64,
+    /// Image urls, kept loose as the api has sent both a plain list and lists by size.
+    #[serde(rename = "fotos", default)]
+    pub photos: serde_json::Value,
+    /// Html with one `name: value` specification per line.
+    #[serde(rename = "ficha_tecnica", default)]
+    pub technical_sheet: serde_json::Value,
+}
+
+impl KabumProductDescription {
+    /// The first image url, which is the one kabum shows first.
+    fn main_image(&self) -> Option<String> {
+        fn first_url(value: &serde_json::Value) -> Option<&str> {
+            match value {
+                serde_json::Value::String(url) if url.starts_with("http") => Some(url),
+                serde_json::Value::Array(values) => values.iter().find_map(first_url),
+                serde_json::Value::Object(values) => values.values().find_map(first_url),
+                _ => None,
+            }
+        }
+
+        first_url(&self.photos).map(str::to_string)
+    }
+
+    fn specs(&self) -> Option<serde_json::Value> {
+        let specs = parse_specs(self.technical_sheet.as_str()?);
+        (!specs.is_empty()).then_some(serde_json::Value::Object(specs))
+    }
+}
+
+/// Turns the html of a technical sheet into `name: value` pairs, lines without both are
+/// headings or notes and are skipped.
+fn parse_specs(html: &str) -> serde_json::Map<String, serde_json::Value> {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut tag = String::new();
+
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag
+                    .trim_start_matches('/')
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                if matches!(name.to_lowercase().as_str(), "br" | "br/" | "p" | "li" | "div" | "tr") {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => text.push(c),
+        }
+    }
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">");
+
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '•', '*']).trim();
+            let (name, value) = line.split_once(':')?;
+            let (name, value) = (name.trim(), value.trim());
+
+            // long "names" are sentences that happen to have a colon
+            let valid = !name.is_empty() && !value.is_empty() && name.chars().count() <= 60;
+            valid.then(|| (name.to_string(), serde_json::Value::String(value.to_string())))
+        })
+        .collect()
 }
 
 #[derive(Debug, Deserialize)]
 pub struct KabumManufacturer {
-    #[serde(rename = "codigo")]
-    pub id: u64,
     #[serde(rename = "nome")]
     pub name: String,
 }
@@ -61,32 +241,35 @@ impl ScrapHandler for KabumProductHandler {
 
         tracing::error!("so, we got here");
 
-        let mut raw_url = self.api.to_string();
-        raw_url.push_str(product_id);
-        let url = Url::parse(&raw_url).unwrap();
+        let body = self.fetch(product_id).await?;
 
-        let response = reqwest::get(url).await?;
-        let body = response.text().await?;
-        let body = serde_json::from_str::<KabumProductDescription>(&body)?;
+        let identifiers = PageIdentifiers::new(&page);
 
-        let product = match (page.ean.as_ref(), page.gtin.as_ref()) {
-            (Some(ean), _) => Product::get_by_ean(&self.db, ean).await?,
-            (_, Some(gtin)) => Product::get_by_gtin(&self.db, gtin).await?,
+        // a known listing already says which product this is, the gtin only matters when the
+        // store lists the same product under a new url
+        let listing = Listing::get_by_url(&self.db, page.store_id, &page.url).await?;
+        let product = match (listing, identifiers.normalized.as_ref()) {
+            (Some(listing), _) => Product::get_by_id(&self.db, listing.product_id).await?,
+            (None, Some(gtin)) => Product::get_by_gtin(&self.db, gtin).await?,
+            // products scraped before listings existed are only known by their url
             (None, None) => Product::get_by_url(&self.db, &page.url).await?,
         };
 
+        let image = body.main_image();
+        let specs = body.specs();
+
         let product = match product {
             Some(product) => product,
             None => {
-                Product::create(
+                matching::link_or_create(
                     &self.db,
                     CreateProductPayload {
                         name: page.name,
                         brand: body.manufacturer.name,
                         url: Some(page.url.to_string()),
-                        image: None,
-                        ean: page.ean.clone(),
-                        gtin: page.ean.clone(),
+                        image: image.clone(),
+                        ean: identifiers.ean,
+                        gtin: identifiers.gtin,
                     }
                     .parse()?,
                 )
@@ -94,16 +277,194 @@ impl ScrapHandler for KabumProductHandler {
             }
         };
 
+        // products found by their listing or gtin may have been created without these
+        let product =
+            Product::update_details(&self.db, product.id, image.as_deref(), specs.as_ref(), page.category_id).await?;
+
+        let listing = Listing::upsert(
+            &self.db,
+            UpsertListing {
+                product_id: product.id,
+                store_id: page.store_id,
+                url: page.url.clone(),
+                sku: Some(body.id.to_string()),
+                title: body.name,
+            },
+        )
+        .await?;
+
+        let checked = self
+            .check_price(product_id, product.id, page.store_id, body.price, body.availability)
+            .await?;
+
         let payload = CreateProductPricePayload {
             product_id: product.id.inner(),
             store_id: page.store_id.inner(),
-            price: body.price,
+            listing_id: Some(listing.id.inner()),
+            price: checked.price,
+            available: checked.available,
+            anomaly: checked.anomaly,
+            anomaly_score: checked.score,
         };
 
         let payload = payload.parse(&self.db).await?;
 
-        ProductPrice::create(&self.db, payload).await?;
+        let previous = ProductPrice::get_latest(&self.db, product.id, page.store_id).await?;
+        let lowest = ProductPrice::get_lowest(&self.db, product.id, page.store_id).await?;
+        let price = ProductPrice::create(&self.db, payload).await?;
+        telemetry::price_inserted(page.store_id);
+
+        // nobody should be alerted of a glitch, the price is kept to be confirmed later
+        if price.anomaly == Some(PriceAnomaly::Suspected) {
+            tracing::warn!(
+                "price {:.2} of product {} on store {} looks like an anomaly, not alerting",
+                price.price,
+                product.id.inner(),
+                page.store_id.inner()
+            );
+            self.events.publish(Event::PriceInserted(price));
+            return Ok(());
+        }
+
+        // the price is already stored, failing to queue its webhooks shouldn't fail the page
+        if let Err(e) = webhooks::price_observed(&self.db, &product, previous.as_ref(), lowest, &price).await {
+            tracing::error!("failed to queue price webhooks: {e}");
+        }
+
+        if let Some(previous) = previous.as_ref().filter(|previous| previous.price > price.price) {
+            self.events.publish(Event::PriceDropped(PriceDrop {
+                product_id: product.id,
+                store_id: page.store_id,
+                previous_price: previous.price,
+                price: price.price,
+            }));
+        }
+        self.events.publish(Event::PriceInserted(price));
+
+        notify_price_change(
+            &self.db,
+            &self.notifier,
+            &product,
+            page.store_id,
+            previous.as_ref(),
+            checked.price,
+        )
+        .await?;
 
         Ok(())
     }
 }
+
+/// The identifiers of a page worth trusting.
+///
+/// Pages created before identifiers were validated may hold anything, so an invalid one is
+/// logged and left out instead of failing the page. So is an ean naming another product than
+/// the gtin, the gtin wins.
+struct PageIdentifiers {
+    ean: Option<String>,
+    gtin: Option<String>,
+    normalized: Option<Gtin>,
+}
+
+impl PageIdentifiers {
+    fn new(page: &QueuePage) -> Self {
+        let parse = |kind: &str, value: Option<&String>| {
+            let value = value?;
+            match Gtin::parse(value) {
+                Ok(gtin) => Some((value.clone(), gtin)),
+                Err(e) => {
+                    tracing::warn!("ignoring {kind} {value} of page {}: {e}", page.url);
+                    None
+                }
+            }
+        };
+
+        let ean = parse("ean", page.ean.as_ref());
+        let gtin = parse("gtin", page.gtin.as_ref());
+
+        let ean = match (ean, gtin.as_ref()) {
+            (Some(ean), Some(gtin)) if ean.1 != gtin.1 => {
+                tracing::warn!(
+                    "ignoring ean {} of page {}, its gtin {} is another product",
+                    ean.0,
+                    page.url,
+                    gtin.0
+                );
+                None
+            }
+            (ean, _) => ean,
+        };
+
+        Self {
+            normalized: gtin.as_ref().or(ean.as_ref()).map(|(_, normalized)| normalized.clone()),
+            ean: ean.map(|(value, _)| value),
+            gtin: gtin.map(|(value, _)| value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(json: &str) -> KabumProductDescription {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn main_image_is_the_first_of_a_list() {
+        let product = fixture(include_str!("fixtures/kabum_product.json"));
+        assert_eq!(
+            product.main_image().as_deref(),
+            Some(
+                "https://images.kabum.com.br/produtos/fotos/461699/placa-de-video-asus-dual-rtx-4070_1681147328_gg.jpg"
+            )
+        );
+    }
+
+    #[test]
+    fn main_image_skips_empty_sizes_and_relative_urls() {
+        let product = fixture(include_str!("fixtures/kabum_product_sized_photos.json"));
+        assert_eq!(
+            product.main_image().as_deref(),
+            Some("https://images.kabum.com.br/produtos/fotos/172370/memoria-kingston-fury-beast_1634581542_gg.jpg")
+        );
+    }
+
+    #[test]
+    fn main_image_is_none_without_photos() {
+        let product = fixture(
+            r#"{"codigo": 1, "nome": "x", "disponibilidade": true, "fabricante": {"nome": "x"}, "preco": 1.0}"#,
+        );
+        assert_eq!(product.main_image(), None);
+    }
+
+    #[test]
+    fn specs_are_read_from_the_technical_sheet() {
+        let product = fixture(include_str!("fixtures/kabum_product.json"));
+        let specs = product.specs().unwrap();
+        assert_eq!(
+            specs,
+            serde_json::json!({
+                "Marca": "ASUS",
+                "Modelo": "DUAL-RTX4070-O12G",
+                "Tamanho": "12GB GDDR6X",
+                "Interface": "192-bit",
+                "Conectores": "1x HDMI 2.1 & 3x DisplayPort 1.4a",
+            })
+        );
+    }
+
+    #[test]
+    fn empty_technical_sheet_has_no_specs() {
+        let product = fixture(include_str!("fixtures/kabum_product_sized_photos.json"));
+        assert_eq!(product.specs(), None);
+    }
+
+    #[test]
+    fn specs_keep_values_with_colons() {
+        let specs = parse_specs("Horário: 10:00 às 18:00<br/>Título sem valor:<br>: sem nome");
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs["Horário"], "10:00 às 18:00");
+    }
+}
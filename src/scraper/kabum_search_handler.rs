@@ -3,6 +3,7 @@ use std::sync::Arc;
 use headless_chrome::Tab;
 
 use super::{QueuePage, ScrapHandler};
+use crate::models::category::CategoryId;
 use crate::models::page::PageHandler;
 use crate::models::store::{Store, StoreId};
 
@@ -11,11 +12,17 @@ pub struct KabumSearchHandler {
     store_id: StoreId,
     ean: Option<String>,
     gtin: Option<String>,
+    category_id: Option<CategoryId>,
 }
 
 impl KabumSearchHandler {
-    pub fn new(store_id: StoreId, ean: Option<String>, gtin: Option<String>) -> Self {
-        Self { store_id, ean, gtin }
+    pub fn new(store_id: StoreId, ean: Option<String>, gtin: Option<String>, category_id: Option<CategoryId>) -> Self {
+        Self {
+            store_id,
+            ean,
+            gtin,
+            category_id,
+        }
     }
 }
 
@@ -57,6 +64,7 @@ impl ScrapHandler for KabumSearchHandler {
                 handler: PageHandler::KabumProduct,
                 ean: self.ean.clone(),
                 gtin: self.gtin.clone(),
+                category_id: self.category_id,
             })
         }
 
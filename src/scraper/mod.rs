@@ -4,17 +4,38 @@ pub mod page_scraper;
 pub mod queue_scraper;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use headless_chrome::{Browser, Tab};
 use kabum_search_handler::KabumSearchHandler;
 use page_scraper::PageScraper;
 use queue_scraper::QueueScraper;
 use sqlx::PgPool;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
-use crate::models::page::{Page, PageHandler};
+use crate::chart;
+use crate::config::{BrowserConfig, ScraperConfig};
+use crate::events::{Event, EventBus};
+use crate::models::category::CategoryId;
+use crate::models::notification_channel::NotificationChannel;
+use crate::models::page::{Page, PageHandler, PageId, PageKind};
+use crate::models::product::Product;
+use crate::models::product_price::ProductPrice;
+use crate::models::scrape_failure::ScrapeFailure;
+use crate::models::scrape_run::{ScrapeRun, ScrapeRunStatus};
 use crate::models::store::StoreId;
+use crate::notifier::TaskNotification;
+use crate::shutdown::{self, Shutdown};
+use crate::{telemetry, webhooks};
+
+/// How often a run reports it's still going.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Runs without a heartbeat for this long were left behind by a process that died.
+const STALE_RUN_AFTER: Duration = Duration::from_secs(5 * 60);
 
 pub trait ScrapHandler: Send {
     type Input;
@@ -31,66 +52,278 @@ pub struct QueuePage {
     pub handler: PageHandler,
     pub ean: Option<String>,
     pub gtin: Option<String>,
+    pub category_id: Option<CategoryId>,
+}
+
+impl From<Page> for QueuePage {
+    fn from(page: Page) -> Self {
+        Self {
+            name: page.name,
+            url: page.url,
+            store_id: page.store_id,
+            handler: page.handler,
+            ean: page.ean,
+            gtin: page.gtin,
+            category_id: page.category_id,
+        }
+    }
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn start_thread(db: PgPool) -> anyhow::Result<()> {
-    tokio::spawn(async move {
-        const INTERVAL_SECS: u64 = 60 * 60 * 24;
-        const CONCURRENT_LIMIT: usize = 2;
+pub async fn start_thread(
+    db: PgPool,
+    notifier: UnboundedSender<TaskNotification>,
+    events: EventBus,
+    config: ScraperConfig,
+    browser_config: BrowserConfig,
+    shutdown: Shutdown,
+) -> anyhow::Result<JoinHandle<()>> {
+    let launch_options = browser_config.launch_options()?;
 
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(INTERVAL_SECS));
-        let semaphore = Arc::new(Semaphore::new(CONCURRENT_LIMIT));
+    match ScrapeRun::interrupt_stale(&db, STALE_RUN_AFTER).await {
+        Ok(0) => {}
+        Ok(stale) => tracing::warn!("marked {stale} stale scrape runs as interrupted"),
+        Err(e) => tracing::error!("failed to mark stale scrape runs as interrupted: {e}"),
+    }
+
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval());
+        let semaphore = Arc::new(Semaphore::new(config.search_concurrency));
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.token.cancelled() => break,
+            }
+
             tracing::info!("starting scraper routine");
 
-            let browser = match Browser::default() {
+            let browser = match Browser::new(launch_options.clone()) {
                 Ok(browser) => browser,
-                Err(_) => return,
+                Err(e) => {
+                    tracing::error!("failed to launch browser: {e}");
+                    return;
+                }
             };
 
-            let urls = match scrap_search_pages(&db, &browser, &semaphore).await {
-                Ok(ScrapResult::Finished(result)) => result,
-                Ok(ScrapResult::Skip) => {
-                    tracing::warn!("skipping scraper routine");
-                    continue;
-                }
-                Err(_) => continue,
+            let Ok(Some(pages)) = Page::get_all_search_pages(&db).await else {
+                tracing::error!("failed to fetch pages from database");
+                tracing::warn!("skipping scraper routine");
+                continue;
             };
 
-            match QueueScraper::new(db.clone()).run(&browser, urls).await {
-                Ok(_) => tracing::info!("finished queue handler"),
-                Err(_) => todo!(),
+            match scrap_run(&db, browser, &semaphore, &notifier, &events, &config, pages, &shutdown).await {
+                Ok(status) => tracing::info!("scraper routine {}", status.inner()),
+                Err(e) => tracing::error!("scraper routine failed: {e}"),
             }
         }
+
+        tracing::info!("scraper stopped");
     });
-    Ok(())
+
+    Ok(handle)
 }
 
-pub enum ScrapResult<T> {
-    Finished(T),
-    Skip,
+/// What a single scraping pass should run over.
+#[derive(Debug, Clone, Copy)]
+pub enum ScrapTarget {
+    Page(PageId),
+    Store(StoreId),
 }
 
+/// Runs a single scraping pass over `target` and returns once every page was handled.
 #[tracing::instrument(skip_all)]
-async fn scrap_search_pages(
+pub async fn scrap_once(
+    db: &PgPool,
+    notifier: &UnboundedSender<TaskNotification>,
+    events: &EventBus,
+    config: &ScraperConfig,
+    browser_config: &BrowserConfig,
+    target: ScrapTarget,
+    shutdown: &Shutdown,
+) -> anyhow::Result<ScrapeRunStatus> {
+    let pages = match target {
+        ScrapTarget::Page(id) => Page::get_by_id(db, id).await?.into_iter().collect::<Vec<_>>(),
+        ScrapTarget::Store(id) => Page::get_by_store(db, id).await?,
+    };
+
+    if pages.is_empty() {
+        anyhow::bail!("no active pages to scrap for {target:?}");
+    }
+
+    // search and details pages are scraped apart, a handler on the wrong kind of page can't run
+    if let Some(page) = pages.iter().find(|page| page.handler.page_kind() != page.page_kind) {
+        anyhow::bail!(
+            "page {} is a {} page but its handler {} scraps {} pages",
+            page.id.inner(),
+            page.page_kind.inner(),
+            page.handler.inner(),
+            page.handler.page_kind().inner()
+        );
+    }
+
+    let browser = Browser::new(browser_config.launch_options()?)?;
+    let semaphore = Arc::new(Semaphore::new(config.search_concurrency));
+
+    scrap_run(db, browser, &semaphore, notifier, events, config, pages, shutdown).await
+}
+
+/// Scraps `pages` while keeping track of it as a [`ScrapeRun`].
+///
+/// Once a shutdown is requested no new page is started, pages already being scraped get until
+/// the shutdown deadline to finish and the run is marked as interrupted. The browser is closed
+/// before returning either way.
+#[allow(clippy::too_many_arguments)]
+async fn scrap_run(
+    db: &PgPool,
+    browser: Browser,
+    semaphore: &Arc<Semaphore>,
+    notifier: &UnboundedSender<TaskNotification>,
+    events: &EventBus,
+    config: &ScraperConfig,
+    pages: Vec<Page>,
+    shutdown: &Shutdown,
+) -> anyhow::Result<ScrapeRunStatus> {
+    let run = ScrapeRun::create(db).await?;
+    let start = Instant::now();
+    events.publish(Event::ScrapeRunChanged(run.clone()));
+
+    let heartbeat = tokio::spawn({
+        let db = db.clone();
+        let id = run.id;
+
+        async move {
+            let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = ScrapeRun::heartbeat(&db, id).await {
+                    tracing::warn!("failed to record heartbeat of scrape run {}: {e}", id.inner());
+                }
+            }
+        }
+    });
+
+    let result = tokio::select! {
+        result = scrap_pages(db, &browser, semaphore, notifier, events, config, pages, &shutdown.token) => result,
+        _ = shutdown::deadline(shutdown.token.clone(), shutdown.timeout) => {
+            Err(anyhow::anyhow!("pages still being scraped after the shutdown deadline were dropped"))
+        }
+    };
+
+    heartbeat.abort();
+
+    // dropping the last handle to the browser kills the chrome process
+    drop(browser);
+    telemetry::browser_tabs_closed();
+
+    let status = match &result {
+        _ if shutdown.token.is_cancelled() => ScrapeRunStatus::Interrupted,
+        Ok(_) => ScrapeRunStatus::Finished,
+        Err(_) => ScrapeRunStatus::Failed,
+    };
+
+    if let Err(e) = result {
+        tracing::error!("scraper run {} did not finish cleanly: {e}", run.id.inner());
+    }
+
+    telemetry::run_finished(status, start.elapsed());
+    let run = ScrapeRun::finish(db, run.id, status).await?;
+    events.publish(Event::ScrapeRunChanged(run));
+
+    Ok(status)
+}
+
+/// Scraps every search page in `pages` for the product pages they list, which are then handled
+/// together with the details pages in `pages`.
+#[allow(clippy::too_many_arguments)]
+async fn scrap_pages(
     db: &PgPool,
     browser: &Browser,
     semaphore: &Arc<Semaphore>,
-) -> anyhow::Result<ScrapResult<Vec<QueuePage>>> {
-    tracing::info!("starting to scrap search pages");
+    notifier: &UnboundedSender<TaskNotification>,
+    events: &EventBus,
+    config: &ScraperConfig,
+    pages: Vec<Page>,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let (search_pages, details_pages): (Vec<_>, Vec<_>) =
+        pages.into_iter().partition(|page| page.page_kind == PageKind::Search);
+
+    let mut urls = scrap_search_pages(db, browser, semaphore, search_pages, shutdown).await?;
+    urls.extend(details_pages.into_iter().map(QueuePage::from));
+
+    QueueScraper::new(db.clone(), notifier.clone(), events.clone(), config)
+        .run(browser, urls, shutdown)
+        .await
+}
+
+/// Notifies every subscribed channel matching `product` when `price` differs from the
+/// `previous` observation on the same store.
+pub async fn notify_price_change(
+    db: &PgPool,
+    notifier: &UnboundedSender<TaskNotification>,
+    product: &Product,
+    store_id: StoreId,
+    previous: Option<&ProductPrice>,
+    price: f64,
+) -> anyhow::Result<()> {
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+
+    if previous.price == price {
+        return Ok(());
+    }
+
+    let channels = NotificationChannel::get_matching(db, store_id, product.id, &product.brand).await?;
+    let url = product.url.as_deref().unwrap_or_default();
 
-    let Ok(Some(pages)) = Page::get_all_search_pages(db).await else {
-        tracing::error!("failed to fetch stores from database");
-        return Ok(ScrapResult::Skip);
+    // drops are what people act on, so those are the ones worth a chart. It's only attached to
+    // messages of subscribed channels, and the price is already stored by now so a chart that
+    // fails to render is not worth failing over
+    let image = if price < previous.price && !channels.is_empty() {
+        chart::product_price_chart(db, product).await.unwrap_or_else(|e| {
+            tracing::error!("failed to render price chart of product {}: {e}", product.id.inner());
+            None
+        })
+    } else {
+        None
     };
 
+    let notification = TaskNotification {
+        title: format!("price change: {}", product.name),
+        message: format!("went from R$ {:.2} to R$ {:.2}\n{url}", previous.price, price),
+        channels: channels.into_iter().map(|channel| channel.channel_id).collect(),
+        image,
+        subscribers_only: false,
+    };
+
+    // the receiving end only goes away when the notifier is shutting down, nothing to do then
+    _ = notifier.send(notification);
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn scrap_search_pages(
+    db: &PgPool,
+    browser: &Browser,
+    semaphore: &Arc<Semaphore>,
+    pages: Vec<Page>,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<Vec<QueuePage>> {
+    tracing::info!("starting to scrap search pages");
+
     let mut handles = vec![];
 
     for page in pages {
         let permit = semaphore.clone().acquire_owned().await.unwrap();
+
+        if shutdown.is_cancelled() {
+            tracing::warn!("shutting down, not starting any new search page");
+            break;
+        }
+
         let browser = browser.clone();
         let db = db.clone();
 
@@ -100,12 +333,14 @@ async fn scrap_search_pages(
             let _permit = permit;
 
             let store_id = page.store_id;
+            let url = page.url.clone();
+            let handler = page.handler;
 
             let result = match page.handler {
                 PageHandler::KabumSearch => {
                     PageScraper::new(
-                        KabumSearchHandler::new(store_id, page.ean.clone(), page.gtin.clone()),
-                        db,
+                        KabumSearchHandler::new(store_id, page.ean.clone(), page.gtin.clone(), page.category_id),
+                        db.clone(),
                         page,
                     )
                     .run(&browser)
@@ -115,9 +350,23 @@ async fn scrap_search_pages(
             };
 
             match result {
-                Ok(urls) => Ok(urls),
+                Ok(urls) => {
+                    telemetry::page_scraped(store_id, handler);
+                    Ok(urls)
+                }
                 Err(e) => {
                     tracing::error!("failed to scrap page with error: {e}");
+                    telemetry::handler_error(store_id, handler);
+
+                    match ScrapeFailure::create(&db, store_id, &url, handler, &e.to_string()).await {
+                        Ok(failure) => {
+                            if let Err(e) = webhooks::scrape_failed(&db, &failure).await {
+                                tracing::error!("failed to queue scrape failure webhooks: {e}");
+                            }
+                        }
+                        Err(e) => tracing::error!("failed to record scrape failure: {e}"),
+                    }
+
                     anyhow::bail!("failed to scrap page with error: {e}");
                 }
             }
@@ -132,5 +381,5 @@ async fn scrap_search_pages(
         urls.extend(handle_urls);
     }
 
-    Ok(ScrapResult::Finished(urls))
+    Ok(urls)
 }
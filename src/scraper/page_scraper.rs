@@ -4,6 +4,7 @@ use sqlx::PgPool;
 use super::{QueuePage, ScrapHandler};
 use crate::models::page::Page;
 use crate::models::store::Store;
+use crate::telemetry;
 
 pub struct PageScraper<P>
 where
@@ -24,6 +25,8 @@ where
 
     pub async fn run(&mut self, browser: &Browser) -> anyhow::Result<Vec<QueuePage>> {
         let tab = browser.new_tab()?;
+        telemetry::browser_tabs(browser);
+
         let Some(store) = Store::get_by_id(&self.db, self.page.store_id).await? else {
             // TODO: if we don't have a store on a page, we have something really bad going on, so
             // this here is not the optimal error handling and should change
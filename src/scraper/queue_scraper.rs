@@ -1,60 +1,123 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use headless_chrome::Browser;
 use sqlx::PgPool;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
 use super::QueuePage;
+use crate::config::{AnomalyConfig, ScraperConfig};
+use crate::events::EventBus;
 use crate::models::page::PageHandler;
+use crate::models::scrape_failure::ScrapeFailure;
+use crate::notifier::TaskNotification;
 use crate::scraper::kabum_product_handler::KabumProductHandler;
 use crate::scraper::ScrapHandler;
+use crate::{telemetry, webhooks};
 
 pub struct QueueScraper {
     db: PgPool,
+    notifier: UnboundedSender<TaskNotification>,
+    events: EventBus,
+    concurrency: usize,
+    delay: Duration,
+    anomaly: AnomalyConfig,
 }
 
 impl QueueScraper {
-    pub fn new(db: PgPool) -> Self {
-        Self { db }
+    pub fn new(
+        db: PgPool,
+        notifier: UnboundedSender<TaskNotification>,
+        events: EventBus,
+        config: &ScraperConfig,
+    ) -> Self {
+        Self {
+            db,
+            notifier,
+            events,
+            concurrency: config.queue_concurrency,
+            delay: config.request_delay(),
+            anomaly: config.anomaly.clone(),
+        }
     }
 
-    pub async fn run(&mut self, browser: &Browser, queue: Vec<QueuePage>) -> anyhow::Result<()> {
-        const CONCURRENT_LIMIT: usize = 10;
-        let semaphore = Arc::new(Semaphore::new(CONCURRENT_LIMIT));
+    pub async fn run(
+        &mut self,
+        browser: &Browser,
+        queue: Vec<QueuePage>,
+        shutdown: &CancellationToken,
+    ) -> anyhow::Result<()> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
 
         // artificial delay to prevent rate limiting
-        // TODO: tweak this to a better default delay
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(300));
+        let mut interval = tokio::time::interval(self.delay);
         let mut handles = vec![];
+        let mut remaining = queue.len();
 
         for page in queue {
+            telemetry::queue_length(remaining);
+            remaining -= 1;
+
             interval.tick().await;
 
             let permit = semaphore.clone().acquire_owned().await?;
+
+            if shutdown.is_cancelled() {
+                tracing::warn!("shutting down, not starting any new product page");
+                break;
+            }
+
             let tab = browser.new_tab()?;
+            telemetry::browser_tabs(browser);
+
             let db = self.db.clone();
+            let notifier = self.notifier.clone();
+            let events = self.events.clone();
+            let anomaly = self.anomaly.clone();
 
             let handle = tokio::spawn(async move {
                 let _permit = permit;
 
+                let store_id = page.store_id;
+                let url = page.url.clone();
+                let page_handler = page.handler;
+
                 let mut handler = match page.handler {
-                    PageHandler::KabumProduct => KabumProductHandler::new(db),
+                    PageHandler::KabumProduct => KabumProductHandler::new(db.clone(), notifier, events, anomaly),
                     PageHandler::KabumSearch => unreachable!(),
                 };
 
                 match handler.run(tab, page).await {
-                    Ok(_) => {}
-                    Err(e) => tracing::error!("{}", e.to_string()),
+                    Ok(_) => telemetry::page_scraped(store_id, page_handler),
+                    Err(e) => {
+                        tracing::error!("{}", e.to_string());
+                        telemetry::handler_error(store_id, page_handler);
+
+                        match ScrapeFailure::create(&db, store_id, &url, page_handler, &e.to_string()).await {
+                            Ok(failure) => {
+                                if let Err(e) = webhooks::scrape_failed(&db, &failure).await {
+                                    tracing::error!("failed to queue scrape failure webhooks: {e}");
+                                }
+                            }
+                            Err(e) => tracing::error!("failed to record scrape failure: {e}"),
+                        }
+                    }
                 }
             });
 
             handles.push(handle);
         }
 
+        telemetry::queue_length(0);
+
         for handle in handles {
             handle.await?;
         }
 
+        telemetry::browser_tabs(browser);
+
         Ok(())
     }
 }
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+/// Lets long running tasks know the application is shutting down, and how long they have to
+/// finish what they are doing.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    pub token: CancellationToken,
+    pub timeout: Duration,
+}
+
+/// Returns a token that is cancelled once the process receives SIGINT or SIGTERM.
+pub fn listen() -> CancellationToken {
+    let token = CancellationToken::new();
+    let signal_token = token.clone();
+
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        tracing::info!("shutdown signal received, finishing in-flight work");
+        signal_token.cancel();
+    });
+
+    token
+}
+
+/// Resolves `timeout` after `token` is cancelled, bounding how long a graceful shutdown can take.
+pub async fn deadline(token: CancellationToken, timeout: Duration) {
+    token.cancelled().await;
+    tokio::time::sleep(timeout).await;
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to listen for ctrl-c: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => _ = terminate.recv().await,
+            Err(e) => {
+                tracing::error!("failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
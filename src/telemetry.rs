@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use headless_chrome::Browser;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::models::page::PageHandler;
+use crate::models::scrape_run::ScrapeRunStatus;
+use crate::models::store::StoreId;
+
+const HTTP_REQUESTS: &str = "http_requests_total";
+const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+const PAGES_SCRAPED: &str = "scraper_pages_scraped_total";
+const HANDLER_ERRORS: &str = "scraper_handler_errors_total";
+const PRICES_INSERTED: &str = "scraper_prices_inserted_total";
+const QUEUE_LENGTH: &str = "scraper_queue_length";
+const BROWSER_TABS: &str = "scraper_browser_tabs";
+const RUN_DURATION: &str = "scraper_run_duration_seconds";
+
+const HTTP_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+// a run goes over every page we track, so it takes from minutes to hours
+const RUN_BUCKETS: &[f64] = &[60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0];
+
+/// Installs the global prometheus recorder, every metric recorded before this is lost.
+pub fn install() -> anyhow::Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(HTTP_REQUEST_DURATION.into()), HTTP_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(RUN_DURATION.into()), RUN_BUCKETS)?
+        .install_recorder()?;
+
+    Ok(handle)
+}
+
+/// Counts and times every request by the route it matched, requests that matched no route are
+/// grouped together so random paths can't blow up the number of series.
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".into());
+    let method = request.method().to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+
+    counter!(HTTP_REQUESTS, "method" => method.clone(), "route" => route.clone(), "status" => status).increment(1);
+    histogram!(HTTP_REQUEST_DURATION, "method" => method, "route" => route).record(start.elapsed());
+
+    response
+}
+
+pub fn page_scraped(store_id: StoreId, handler: PageHandler) {
+    counter!(PAGES_SCRAPED, "store" => store_id.inner().to_string(), "handler" => handler.inner().to_string())
+        .increment(1);
+}
+
+pub fn handler_error(store_id: StoreId, handler: PageHandler) {
+    counter!(HANDLER_ERRORS, "store" => store_id.inner().to_string(), "handler" => handler.inner().to_string())
+        .increment(1);
+}
+
+pub fn price_inserted(store_id: StoreId) {
+    counter!(PRICES_INSERTED, "store" => store_id.inner().to_string()).increment(1);
+}
+
+pub fn queue_length(length: usize) {
+    gauge!(QUEUE_LENGTH).set(length as f64);
+}
+
+pub fn browser_tabs(browser: &Browser) {
+    let tabs = browser.get_tabs().lock().map(|tabs| tabs.len()).unwrap_or_default();
+    gauge!(BROWSER_TABS).set(tabs as f64);
+}
+
+/// The browser is gone once a run ends, and every tab with it.
+pub fn browser_tabs_closed() {
+    gauge!(BROWSER_TABS).set(0.0);
+}
+
+pub fn run_finished(status: ScrapeRunStatus, duration: Duration) {
+    histogram!(RUN_DURATION, "status" => status.inner().to_string()).record(duration);
+}
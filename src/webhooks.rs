@@ -0,0 +1,251 @@
+use chrono::Utc;
+use futures::StreamExt;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::WebhooksConfig;
+use crate::models::product::{Product, ProductId};
+use crate::models::product_price::ProductPrice;
+use crate::models::scrape_failure::ScrapeFailure;
+use crate::models::store::StoreId;
+use crate::models::webhook::WebhookEvent;
+use crate::models::webhook_delivery::{DueDelivery, WebhookDelivery};
+
+/// Header carrying the signature receivers should check before trusting a delivery.
+const SIGNATURE_HEADER: &str = "X-Promor-Signature";
+const EVENT_HEADER: &str = "X-Promor-Event";
+const DELIVERY_HEADER: &str = "X-Promor-Delivery";
+
+/// How many due deliveries are picked up on each poll.
+const BATCH_SIZE: i64 = 50;
+/// How many deliveries are sent at the same time, so a slow receiver doesn't hold up the rest.
+const CONCURRENCY: usize = 8;
+/// Retries are never pushed further apart than this, however many attempts failed.
+const MAX_RETRY_DELAY_SECS: u64 = 6 * 60 * 60;
+
+/// What is sent for the price events, a product's first observation on a store triggers none of
+/// them so there is always a previous price.
+#[derive(Debug, Serialize)]
+struct PriceEventPayload {
+    #[serde(rename = "productId")]
+    product_id: ProductId,
+    #[serde(rename = "productName")]
+    product_name: String,
+    brand: String,
+    #[serde(rename = "storeId")]
+    store_id: StoreId,
+    url: Option<String>,
+    #[serde(rename = "previousPrice")]
+    previous_price: f64,
+    price: f64,
+    available: bool,
+}
+
+/// The body of every delivery, `data` depends on the event.
+#[derive(Debug, Serialize)]
+struct Envelope<'a> {
+    id: i32,
+    event: &'a str,
+    #[serde(rename = "sentAt")]
+    sent_at: chrono::DateTime<Utc>,
+    data: &'a serde_json::Value,
+}
+
+/// Sends the queued deliveries until `shutdown` is cancelled. Deliveries live on the database,
+/// so whatever is still pending on shutdown is picked up by the next process.
+#[tracing::instrument(skip_all)]
+pub async fn start_thread(
+    db: PgPool,
+    config: WebhooksConfig,
+    shutdown: CancellationToken,
+) -> anyhow::Result<JoinHandle<()>> {
+    let client = reqwest::Client::builder().timeout(config.timeout()).build()?;
+
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.poll_interval());
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            if let Err(e) = deliver_due(&db, &client, &config).await {
+                tracing::error!("failed to deliver webhooks: {e}");
+            }
+        }
+    });
+
+    Ok(handle)
+}
+
+async fn deliver_due(db: &PgPool, client: &reqwest::Client, config: &WebhooksConfig) -> anyhow::Result<()> {
+    let deliveries = WebhookDelivery::get_due(db, BATCH_SIZE).await?;
+
+    futures::stream::iter(deliveries)
+        .for_each_concurrent(CONCURRENCY, |delivery| async move {
+            if let Err(e) = deliver(db, client, config, delivery).await {
+                tracing::error!("failed to record webhook delivery: {e}");
+            }
+        })
+        .await;
+
+    Ok(())
+}
+
+async fn deliver(
+    db: &PgPool,
+    client: &reqwest::Client,
+    config: &WebhooksConfig,
+    delivery: DueDelivery,
+) -> anyhow::Result<()> {
+    let sent_at = Utc::now();
+    let body = serde_json::to_string(&Envelope {
+        id: delivery.id.inner(),
+        event: delivery.event.inner(),
+        sent_at,
+        data: &delivery.payload,
+    })?;
+
+    match send(client, &delivery, sent_at, body).await {
+        Ok(status) => WebhookDelivery::mark_delivered(db, delivery.id, status).await,
+        Err((status, error)) => {
+            tracing::warn!(
+                "webhook delivery {} to {} failed: {error}",
+                delivery.id.inner(),
+                delivery.url
+            );
+
+            let backoff = config
+                .retry_delay_secs
+                .saturating_mul(1 << delivery.attempts.clamp(0, 20));
+            let retry_in = chrono::Duration::seconds(backoff.min(MAX_RETRY_DELAY_SECS) as i64);
+
+            WebhookDelivery::mark_attempt_failed(db, delivery.id, status, &error, config.max_attempts, retry_in).await
+        }
+    }
+}
+
+/// Posts `body` and returns the response status, anything other than a 2xx is a failure.
+async fn send(
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+    sent_at: chrono::DateTime<Utc>,
+    body: String,
+) -> Result<u16, (Option<u16>, String)> {
+    let response = client
+        .post(delivery.url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, sent_at.timestamp(), &body))
+        .header(EVENT_HEADER, delivery.event.inner())
+        .header(DELIVERY_HEADER, delivery.id.inner().to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    match status.is_success() {
+        true => Ok(status.as_u16()),
+        false => Err((Some(status.as_u16()), format!("receiver answered with {status}"))),
+    }
+}
+
+/// Signs `body` the same way stripe does: `t=<unix timestamp>,v1=<hex hmac-sha256>`, where the
+/// hmac covers `<timestamp>.<body>`. Including the timestamp lets receivers reject replays.
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    let signature = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    format!("t={timestamp},v1={signature}")
+}
+
+/// Queues the price events `price` triggers, compared to the `previous` observation of the same
+/// product on the same store and to the `lowest` price it was ever available for there.
+pub async fn price_observed(
+    db: &PgPool,
+    product: &Product,
+    previous: Option<&ProductPrice>,
+    lowest: Option<f64>,
+    price: &ProductPrice,
+) -> anyhow::Result<()> {
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+
+    let payload = serde_json::to_value(PriceEventPayload {
+        product_id: product.id,
+        product_name: product.name.clone(),
+        brand: product.brand.clone(),
+        store_id: price.store_id,
+        url: product.url.clone(),
+        previous_price: previous.price,
+        price: price.price,
+        available: price.available,
+    })?;
+
+    if previous.price != price.price {
+        WebhookDelivery::enqueue(db, WebhookEvent::PriceChanged, &payload).await?;
+    }
+
+    if price.available && lowest.is_some_and(|lowest| price.price < lowest) {
+        WebhookDelivery::enqueue(db, WebhookEvent::NewLow, &payload).await?;
+    }
+
+    if !previous.available && price.available {
+        WebhookDelivery::enqueue(db, WebhookEvent::BackInStock, &payload).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn scrape_failed(db: &PgPool, failure: &ScrapeFailure) -> anyhow::Result<()> {
+    WebhookDelivery::enqueue(db, WebhookEvent::ScrapeFailed, &serde_json::to_value(failure)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, r#"{"id":1}"#),
+            "t=1700000000,v1=2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+        );
+    }
+
+    #[test]
+    fn signature_changes_with_timestamp() {
+        assert_ne!(
+            sign("whsec_test", 1_700_000_000, "{}")[13..],
+            sign("whsec_test", 1_700_000_001, "{}")[13..]
+        );
+    }
+
+    #[test]
+    fn events_serialize_as_their_names() {
+        for event in [
+            WebhookEvent::PriceChanged,
+            WebhookEvent::NewLow,
+            WebhookEvent::BackInStock,
+            WebhookEvent::ScrapeFailed,
+        ] {
+            assert_eq!(serde_json::to_value(event).unwrap(), event.inner());
+        }
+    }
+}